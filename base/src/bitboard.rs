@@ -1,6 +1,23 @@
 use crate::core::{File, Rank, Sq};
+use crate::geometry::bitboard::file;
 use derive_more::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
-use std::fmt;
+use std::{fmt, iter};
+
+/// Direction of a one-step shift. North is toward rank 8 and East is toward file H.
+///
+/// Rank 8 is stored in the low bits, so shifting north moves the bits toward the least
+/// significant one.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Dir {
+    North,
+    South,
+    East,
+    West,
+    NE,
+    NW,
+    SE,
+    SW,
+}
 
 #[derive(
     Default,
@@ -28,6 +45,11 @@ impl Bitboard {
         Bitboard(1_u64 << sq.index())
     }
 
+    #[inline]
+    pub fn from_sqs(sqs: impl IntoIterator<Item = Sq>) -> Bitboard {
+        sqs.into_iter().collect()
+    }
+
     #[inline]
     pub const fn with(self, sq: Sq) -> Bitboard {
         Bitboard(self.0 | (1_u64 << sq.index()))
@@ -58,6 +80,24 @@ impl Bitboard {
         Bitboard(self.0 >> by)
     }
 
+    /// Shifts all the squares one step in the direction. The squares which leave the board are
+    /// dropped and do not wrap around to the other side.
+    #[inline]
+    pub const fn shift(self, dir: Dir) -> Bitboard {
+        let not_a = self.0 & !file(File::A).0;
+        let not_h = self.0 & !file(File::H).0;
+        Bitboard(match dir {
+            Dir::North => self.0 >> 8,
+            Dir::South => self.0 << 8,
+            Dir::East => not_h << 1,
+            Dir::West => not_a >> 1,
+            Dir::NE => not_h >> 7,
+            Dir::NW => not_a >> 9,
+            Dir::SE => not_h << 9,
+            Dir::SW => not_a << 7,
+        })
+    }
+
     #[inline]
     pub fn deposit_bits(self, mut x: u64) -> Bitboard {
         let mut res: u64 = 0;
@@ -73,6 +113,18 @@ impl Bitboard {
         Bitboard(res)
     }
 
+    /// Iterates over all the subsets of the bitboard, starting from the empty one and ending with
+    /// the bitboard itself.
+    #[inline]
+    pub fn iter_subsets(self) -> impl Iterator<Item = Bitboard> {
+        let mask = self.0;
+        // Carry-rippler trick: subtracting the mask propagates carries only through its bits.
+        iter::successors(Some(0_u64), move |&sub| {
+            (sub != mask).then(|| sub.wrapping_sub(mask) & mask)
+        })
+        .map(Bitboard)
+    }
+
     #[inline]
     pub fn set(&mut self, sq: Sq) {
         *self = self.with(sq);
@@ -128,10 +180,65 @@ impl Bitboard {
         Self(self.0.reverse_bits().swap_bytes())
     }
 
+    #[inline]
+    pub const fn flip_vertical(self) -> Self {
+        self.flipped_rank()
+    }
+
+    #[inline]
+    pub const fn flip_horizontal(self) -> Self {
+        self.flipped_file()
+    }
+
+    /// Flips the bitboard about the a8-h1 diagonal.
+    #[inline]
+    pub const fn flip_diagonal(self) -> Self {
+        const K1: u64 = 0x5500_5500_5500_5500;
+        const K2: u64 = 0x3333_0000_3333_0000;
+        const K4: u64 = 0x0f0f_0f0f_0000_0000;
+        let mut x = self.0;
+        let mut t = K4 & (x ^ (x << 28));
+        x ^= t ^ (t >> 28);
+        t = K2 & (x ^ (x << 14));
+        x ^= t ^ (t >> 14);
+        t = K1 & (x ^ (x << 7));
+        x ^= t ^ (t >> 7);
+        Self(x)
+    }
+
+    #[inline]
+    pub const fn rotate_180(self) -> Self {
+        self.flip_vertical().flip_horizontal()
+    }
+
     #[inline]
     pub fn first(self) -> Option<Sq> {
         self.into_iter().next()
     }
+
+    #[inline]
+    pub fn lsb(self) -> Option<Sq> {
+        self.first()
+    }
+
+    #[inline]
+    pub fn msb(self) -> Option<Sq> {
+        if self.0 == 0 {
+            return None;
+        }
+        unsafe {
+            Some(Sq::from_index_unchecked(
+                63 - self.0.leading_zeros() as usize,
+            ))
+        }
+    }
+
+    #[inline]
+    pub fn pop_lsb(&mut self) -> Option<Sq> {
+        let sq = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
 }
 
 impl From<Bitboard> for u64 {
@@ -189,6 +296,13 @@ impl Iterator for Iter {
     }
 }
 
+impl FromIterator<Sq> for Bitboard {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Sq>>(iter: I) -> Bitboard {
+        iter.into_iter().fold(Bitboard::EMPTY, Bitboard::with)
+    }
+}
+
 impl IntoIterator for Bitboard {
     type Item = Sq;
     type IntoIter = Iter;
@@ -220,6 +334,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_iter() {
+        let sqs = [
+            Sq::make(File::A, Rank::R4),
+            Sq::make(File::E, Rank::R2),
+            Sq::make(File::F, Rank::R3),
+        ];
+        assert_eq!(
+            Bitboard::from_sqs(sqs),
+            Bitboard::EMPTY.with(sqs[0]).with(sqs[1]).with(sqs[2])
+        );
+        assert_eq!(Bitboard::from_sqs([sqs[0], sqs[0]]), Bitboard::one(sqs[0]));
+        assert_eq!(Bitboard::from_sqs([]), Bitboard::EMPTY);
+
+        for raw in [0, 0x81, 0x0123_4567_89ab_cdef, u64::MAX] {
+            let bb = Bitboard::from_raw(raw);
+            assert_eq!(bb.into_iter().collect::<Bitboard>(), bb);
+        }
+    }
+
+    #[test]
+    fn test_shift() {
+        let e4 = Sq::make(File::E, Rank::R4);
+        assert_eq!(
+            Bitboard::one(e4).shift(Dir::North),
+            Bitboard::one(Sq::make(File::E, Rank::R5))
+        );
+        assert_eq!(
+            Bitboard::one(e4).shift(Dir::SW),
+            Bitboard::one(Sq::make(File::D, Rank::R3))
+        );
+
+        let dirs = [
+            (Dir::North, 0, -1),
+            (Dir::South, 0, 1),
+            (Dir::East, 1, 0),
+            (Dir::West, -1, 0),
+            (Dir::NE, 1, -1),
+            (Dir::NW, -1, -1),
+            (Dir::SE, 1, 1),
+            (Dir::SW, -1, 1),
+        ];
+        for sq in Sq::iter() {
+            for (dir, df, dr) in dirs {
+                let file = sq.file().index() as isize + df;
+                let rank = sq.rank().index() as isize + dr;
+                let expected = if (0..8).contains(&file) && (0..8).contains(&rank) {
+                    Bitboard::one(Sq::make(
+                        File::from_index(file as usize),
+                        Rank::from_index(rank as usize),
+                    ))
+                } else {
+                    Bitboard::EMPTY
+                };
+                assert_eq!(Bitboard::one(sq).shift(dir), expected, "{} {:?}", sq, dir);
+            }
+        }
+        assert_eq!(Bitboard::FULL.shift(Dir::East).len(), 56);
+        assert_eq!(Bitboard::FULL.shift(Dir::NW).len(), 49);
+    }
+
     #[test]
     fn test_bitops() {
         let ca = Sq::make(File::A, Rank::R4);
@@ -236,6 +411,93 @@ mod tests {
         assert_eq!((!bb1).len(), 62);
     }
 
+    #[test]
+    fn test_flip() {
+        let a1 = Sq::make(File::A, Rank::R1);
+        assert_eq!(
+            Bitboard::one(a1).flip_vertical(),
+            Bitboard::one(Sq::make(File::A, Rank::R8))
+        );
+        assert_eq!(
+            Bitboard::one(a1).flip_horizontal(),
+            Bitboard::one(Sq::make(File::H, Rank::R1))
+        );
+        assert_eq!(
+            Bitboard::one(a1).flip_diagonal(),
+            Bitboard::one(Sq::make(File::H, Rank::R8))
+        );
+        assert_eq!(
+            Bitboard::one(a1).rotate_180(),
+            Bitboard::one(Sq::make(File::H, Rank::R8))
+        );
+
+        for sq in Sq::iter() {
+            let bb = Bitboard::one(sq);
+            let transposed = Sq::make(
+                File::from_index(sq.rank().index()),
+                Rank::from_index(sq.file().index()),
+            );
+            assert_eq!(bb.flip_vertical(), Bitboard::one(sq.flipped_rank()));
+            assert_eq!(bb.flip_horizontal(), Bitboard::one(sq.flipped_file()));
+            assert_eq!(bb.flip_diagonal(), Bitboard::one(transposed));
+            assert_eq!(
+                bb.rotate_180(),
+                Bitboard::one(sq.flipped_rank().flipped_file())
+            );
+        }
+
+        let bb = Bitboard::from_raw(0x0123_4567_89ab_cdef);
+        assert_eq!(bb.flip_horizontal(), bb.flipped_file());
+        assert_eq!(bb.flip_diagonal().flip_diagonal(), bb);
+        assert_eq!(bb.rotate_180().rotate_180(), bb);
+    }
+
+    #[test]
+    fn test_lsb_msb() {
+        assert_eq!(Bitboard::EMPTY.lsb(), None);
+        assert_eq!(Bitboard::EMPTY.msb(), None);
+
+        let a8 = Sq::make(File::A, Rank::R8);
+        let h1 = Sq::make(File::H, Rank::R1);
+        assert_eq!(Bitboard::one(a8).lsb(), Some(a8));
+        assert_eq!(Bitboard::one(a8).msb(), Some(a8));
+        assert_eq!(Bitboard::one(h1).msb(), Some(h1));
+
+        let mut bb = Bitboard::EMPTY
+            .with(Sq::make(File::A, Rank::R4))
+            .with(Sq::make(File::E, Rank::R2))
+            .with(Sq::make(File::F, Rank::R3));
+        assert_eq!(bb.lsb(), Some(Sq::make(File::A, Rank::R4)));
+        assert_eq!(bb.msb(), Some(Sq::make(File::E, Rank::R2)));
+        let expected: Vec<_> = bb.into_iter().collect();
+        let mut popped = Vec::new();
+        while let Some(sq) = bb.pop_lsb() {
+            popped.push(sq);
+        }
+        assert_eq!(popped, expected);
+        assert_eq!(bb, Bitboard::EMPTY);
+        assert_eq!(bb.pop_lsb(), None);
+    }
+
+    #[test]
+    fn test_subsets() {
+        let empty: Vec<_> = Bitboard::EMPTY.iter_subsets().collect();
+        assert_eq!(empty, vec![Bitboard::EMPTY]);
+
+        for mask in [0x81_u64, 0x0000_1818_0000_0042, 0xff00_0000_0000_00ff] {
+            let mask = Bitboard::from_raw(mask);
+            let subsets: Vec<_> = mask.iter_subsets().collect();
+            assert_eq!(subsets.len(), 1 << mask.len());
+            assert_eq!(subsets.first(), Some(&Bitboard::EMPTY));
+            assert_eq!(subsets.last(), Some(&mask));
+            let mut raw: Vec<_> = subsets.iter().map(|b| b.as_raw()).collect();
+            assert!(raw.iter().all(|&b| b & !mask.as_raw() == 0));
+            raw.sort();
+            raw.dedup();
+            assert_eq!(raw.len(), subsets.len());
+        }
+    }
+
     #[test]
     fn test_format() {
         let bb = Bitboard::EMPTY
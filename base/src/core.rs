@@ -1,6 +1,39 @@
 use std::{fmt, hint, str::FromStr};
 use thiserror::Error;
 
+/// Serializes the types as strings, using their `Display` and `FromStr` implementations.
+#[cfg(feature = "serde")]
+#[macro_export]
+macro_rules! impl_serde_str {
+    ($($ty:ty),*) => {$(
+        impl $crate::serde::Serialize for $ty {
+            fn serialize<S: $crate::serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_str(self)
+            }
+        }
+
+        impl<'de> $crate::serde::Deserialize<'de> for $ty {
+            fn deserialize<D: $crate::serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                <String as $crate::serde::Deserialize>::deserialize(d)?
+                    .parse()
+                    .map_err($crate::serde::de::Error::custom)
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "serde")]
+impl_serde_str!(
+    File,
+    Rank,
+    Sq,
+    Color,
+    Piece,
+    Cell,
+    CastlingSide,
+    CastlingRights
+);
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 #[repr(u8)]
 pub enum File {
@@ -63,6 +96,15 @@ impl File {
     pub fn as_char(self) -> char {
         (b'a' + self as u8) as char
     }
+
+    #[inline]
+    pub const fn offset(self, delta: i8) -> Option<File> {
+        let val = self.index().wrapping_add(delta as isize as usize);
+        if val >= 8 {
+            return None;
+        }
+        Some(unsafe { Self::from_index_unchecked(val) })
+    }
 }
 
 impl fmt::Display for File {
@@ -71,6 +113,26 @@ impl fmt::Display for File {
     }
 }
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FileParseError {
+    #[error("bad file char {0:?}")]
+    BadChar(char),
+    #[error("bad string length")]
+    BadLength,
+}
+
+impl FromStr for File {
+    type Err = FileParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 1 {
+            return Err(FileParseError::BadLength);
+        }
+        let ch = s.as_bytes()[0] as char;
+        File::from_char(ch).ok_or(FileParseError::BadChar(ch))
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 #[repr(u8)]
 pub enum Rank {
@@ -133,6 +195,17 @@ impl Rank {
     pub fn as_char(self) -> char {
         (b'8' - self as u8) as char
     }
+
+    /// Offsets the rank index. Ranks are indexed from `R8`, so positive `delta` moves towards
+    /// `R1`, the same as `delta_rank` in `Sq::shift`.
+    #[inline]
+    pub const fn offset(self, delta: i8) -> Option<Rank> {
+        let val = self.index().wrapping_add(delta as isize as usize);
+        if val >= 8 {
+            return None;
+        }
+        Some(unsafe { Self::from_index_unchecked(val) })
+    }
 }
 
 impl fmt::Display for Rank {
@@ -141,6 +214,26 @@ impl fmt::Display for Rank {
     }
 }
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RankParseError {
+    #[error("bad rank char {0:?}")]
+    BadChar(char),
+    #[error("bad string length")]
+    BadLength,
+}
+
+impl FromStr for Rank {
+    type Err = RankParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 1 {
+            return Err(RankParseError::BadLength);
+        }
+        let ch = s.as_bytes()[0] as char;
+        Rank::from_char(ch).ok_or(RankParseError::BadChar(ch))
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Sq(u8);
 
@@ -186,6 +279,17 @@ impl Sq {
         Sq(self.0 ^ 7)
     }
 
+    /// Returns the color of the square, i.e. `Color::White` for light squares and `Color::Black`
+    /// for dark ones.
+    #[inline]
+    pub const fn color(self) -> Color {
+        if ((self.0 ^ (self.0 >> 3)) & 1) == 0 {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
     #[inline]
     pub const fn diag(self) -> usize {
         self.file().index() + self.rank().index()
@@ -196,6 +300,20 @@ impl Sq {
         7 - self.rank().index() + self.file().index()
     }
 
+    #[inline]
+    pub const fn king_distance(self, other: Sq) -> u8 {
+        let df = self.file().index().abs_diff(other.file().index()) as u8;
+        let dr = self.rank().index().abs_diff(other.rank().index()) as u8;
+        if df > dr { df } else { dr }
+    }
+
+    #[inline]
+    pub const fn taxicab(self, other: Sq) -> u8 {
+        let df = self.file().index().abs_diff(other.file().index()) as u8;
+        let dr = self.rank().index().abs_diff(other.rank().index()) as u8;
+        df + dr
+    }
+
     #[inline]
     pub const fn add(self, delta: isize) -> Sq {
         Sq::from_index(self.index().wrapping_add(delta as usize))
@@ -276,6 +394,28 @@ pub enum Color {
 }
 
 impl Color {
+    pub const COUNT: usize = 2;
+
+    #[inline]
+    pub const fn index(self) -> usize {
+        self as u8 as usize
+    }
+
+    #[inline]
+    pub const unsafe fn from_index_unchecked(val: usize) -> Self {
+        match val {
+            0 => Color::White,
+            1 => Color::Black,
+            _ => unsafe { hint::unreachable_unchecked() },
+        }
+    }
+
+    #[inline]
+    pub const fn from_index(val: usize) -> Self {
+        assert!(val < Self::COUNT, "index too large");
+        unsafe { Self::from_index_unchecked(val) }
+    }
+
     #[inline]
     pub const fn inv(self) -> Color {
         match self {
@@ -284,6 +424,12 @@ impl Color {
         }
     }
 
+    /// Iterates over the colors, White first.
+    #[inline]
+    pub fn iter() -> impl Iterator<Item = Self> {
+        (0..Self::COUNT).map(|x| unsafe { Self::from_index_unchecked(x) })
+    }
+
     #[inline]
     pub fn as_char(self) -> char {
         match self {
@@ -369,6 +515,45 @@ impl Piece {
     pub fn iter() -> impl Iterator<Item = Self> {
         (0..Self::COUNT).map(|x| unsafe { Self::from_index_unchecked(x) })
     }
+    /// Returns the piece as in UCI promotions, in lowercase.
+    #[inline]
+    pub fn as_char(self) -> char {
+        Cell::make(Color::Black, self).as_char()
+    }
+
+    #[inline]
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'p' | 'k' | 'n' | 'b' | 'r' | 'q' => Cell::from_char(c)?.piece(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.as_char())
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PieceParseError {
+    #[error("bad piece char {0:?}")]
+    BadChar(char),
+    #[error("bad string length")]
+    BadLength,
+}
+
+impl FromStr for Piece {
+    type Err = PieceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 1 {
+            return Err(PieceParseError::BadLength);
+        }
+        let ch = s.as_bytes()[0] as char;
+        Piece::from_char(ch).ok_or(PieceParseError::BadChar(ch))
+    }
 }
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -476,6 +661,13 @@ impl Cell {
         b".PKNBRQpknbrq"[self.index()] as char
     }
 
+    #[inline]
+    pub fn as_unicode_char(self) -> char {
+        [
+            '.', '♙', '♔', '♘', '♗', '♖', '♕', '♟', '♚', '♞', '♝', '♜', '♛',
+        ][self.index()]
+    }
+
     #[inline]
     pub fn from_char(c: char) -> Option<Self> {
         if c == '.' {
@@ -532,6 +724,80 @@ pub enum CastlingSide {
     King = 1,
 }
 
+impl CastlingSide {
+    pub const COUNT: usize = 2;
+
+    #[inline]
+    pub const fn index(self) -> usize {
+        self as u8 as usize
+    }
+
+    #[inline]
+    pub const unsafe fn from_index_unchecked(val: usize) -> Self {
+        match val {
+            0 => CastlingSide::Queen,
+            1 => CastlingSide::King,
+            _ => unsafe { hint::unreachable_unchecked() },
+        }
+    }
+
+    #[inline]
+    pub const fn from_index(val: usize) -> Self {
+        assert!(val < Self::COUNT, "index too large");
+        unsafe { Self::from_index_unchecked(val) }
+    }
+
+    /// Iterates over the sides, queenside first.
+    #[inline]
+    pub fn iter() -> impl DoubleEndedIterator<Item = Self> {
+        (0..Self::COUNT).map(|x| unsafe { Self::from_index_unchecked(x) })
+    }
+
+    /// Returns the side as in the castling rights of FEN, in lowercase.
+    #[inline]
+    pub fn as_char(self) -> char {
+        match self {
+            CastlingSide::Queen => 'q',
+            CastlingSide::King => 'k',
+        }
+    }
+
+    #[inline]
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'q' => Some(CastlingSide::Queen),
+            'k' => Some(CastlingSide::King),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CastlingSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.as_char())
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CastlingSideParseError {
+    #[error("bad castling side char {0:?}")]
+    BadChar(char),
+    #[error("bad string length")]
+    BadLength,
+}
+
+impl FromStr for CastlingSide {
+    type Err = CastlingSideParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 1 {
+            return Err(CastlingSideParseError::BadLength);
+        }
+        let ch = s.as_bytes()[0] as char;
+        CastlingSide::from_char(ch).ok_or(CastlingSideParseError::BadChar(ch))
+    }
+}
+
 #[derive(Default, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct CastlingRights(u8);
 
@@ -585,6 +851,15 @@ impl CastlingRights {
         self.unset(c, CastlingSide::Queen);
     }
 
+    /// Iterates over the set rights. White rights go first, and for each color the queenside
+    /// right goes before the kingside one.
+    #[inline]
+    pub fn iter(self) -> impl Iterator<Item = (Color, CastlingSide)> {
+        Color::iter()
+            .flat_map(|c| CastlingSide::iter().map(move |s| (c, s)))
+            .filter(move |&(c, s)| self.has(c, s))
+    }
+
     #[inline]
     pub const fn from_index(val: usize) -> CastlingRights {
         assert!(val < 16, "raw castling rights must be between 0 and 15");
@@ -676,6 +951,13 @@ mod tests {
             assert_eq!(file.index(), idx);
             assert_eq!(File::from_index(idx), file);
         }
+        assert_eq!(File::A.offset(0), Some(File::A));
+        assert_eq!(File::A.offset(7), Some(File::H));
+        assert_eq!(File::A.offset(-1), None);
+        assert_eq!(File::H.offset(1), None);
+        assert_eq!(File::H.offset(-7), Some(File::A));
+        assert_eq!(File::D.offset(i8::MIN), None);
+        assert_eq!(File::D.offset(i8::MAX), None);
     }
 
     #[test]
@@ -684,6 +966,11 @@ mod tests {
             assert_eq!(rank.index(), idx);
             assert_eq!(Rank::from_index(idx), rank);
         }
+        assert_eq!(Rank::R8.offset(1), Some(Rank::R7));
+        assert_eq!(Rank::R8.offset(-1), None);
+        assert_eq!(Rank::R1.offset(1), None);
+        assert_eq!(Rank::R1.offset(-7), Some(Rank::R8));
+        assert_eq!(Rank::R2.offset(-2), Some(Rank::R4));
     }
 
     #[test]
@@ -694,6 +981,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_color_side() {
+        for (idx, color) in Color::iter().enumerate() {
+            assert_eq!(color.index(), idx);
+            assert_eq!(Color::from_index(idx), color);
+        }
+        for (idx, side) in CastlingSide::iter().enumerate() {
+            assert_eq!(side.index(), idx);
+            assert_eq!(CastlingSide::from_index(idx), side);
+        }
+    }
+
     #[test]
     fn test_sq() {
         let mut sqs = Vec::new();
@@ -706,6 +1005,21 @@ mod tests {
             }
         }
         assert_eq!(sqs, Sq::iter().collect::<Vec<_>>());
+
+        let a1 = Sq::make(File::A, Rank::R1);
+        assert_eq!(a1.king_distance(a1), 0);
+        assert_eq!(a1.king_distance(Sq::make(File::H, Rank::R8)), 7);
+        assert_eq!(a1.king_distance(Sq::make(File::C, Rank::R2)), 2);
+        assert_eq!(a1.taxicab(a1), 0);
+        assert_eq!(a1.taxicab(Sq::make(File::H, Rank::R8)), 14);
+        assert_eq!(a1.taxicab(Sq::make(File::C, Rank::R2)), 3);
+
+        assert_eq!(a1.color(), Color::Black);
+        assert_eq!(Sq::make(File::H, Rank::R1).color(), Color::White);
+        for sq in Sq::iter() {
+            let light = crate::geometry::bitboard::LIGHT.has(sq);
+            assert_eq!(sq.color() == Color::White, light, "{}", sq);
+        }
     }
 
     #[test]
@@ -776,6 +1090,50 @@ mod tests {
         assert_eq!(CastlingRights::from_str("q"), Ok(rights));
     }
 
+    #[test]
+    fn test_castling_iter() {
+        assert_eq!(
+            Color::iter().collect::<Vec<_>>(),
+            [Color::White, Color::Black]
+        );
+        assert_eq!(
+            CastlingSide::iter().collect::<Vec<_>>(),
+            [CastlingSide::Queen, CastlingSide::King]
+        );
+        assert_eq!(
+            CastlingSide::iter().rev().collect::<Vec<_>>(),
+            [CastlingSide::King, CastlingSide::Queen]
+        );
+
+        assert_eq!(CastlingRights::EMPTY.iter().count(), 0);
+        assert_eq!(
+            CastlingRights::FULL.iter().collect::<Vec<_>>(),
+            [
+                (Color::White, CastlingSide::Queen),
+                (Color::White, CastlingSide::King),
+                (Color::Black, CastlingSide::Queen),
+                (Color::Black, CastlingSide::King),
+            ]
+        );
+        assert_eq!(
+            CastlingRights::from_str("Kq")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            [
+                (Color::White, CastlingSide::King),
+                (Color::Black, CastlingSide::Queen),
+            ]
+        );
+        for idx in 0..16 {
+            let rights = CastlingRights::from_index(idx);
+            let collected = rights
+                .iter()
+                .fold(CastlingRights::EMPTY, |r, (c, s)| r.with(c, s));
+            assert_eq!(collected, rights);
+        }
+    }
+
     #[test]
     fn test_sq_str() {
         assert_eq!(Sq::make(File::B, Rank::R4).to_string(), "b4".to_string());
@@ -793,4 +1151,30 @@ mod tests {
             assert_eq!(Cell::from_str(&s), Ok(cell));
         }
     }
+
+    #[test]
+    fn test_small_str() {
+        for file in File::iter() {
+            assert_eq!(File::from_str(&file.to_string()), Ok(file));
+        }
+        for rank in Rank::iter() {
+            assert_eq!(Rank::from_str(&rank.to_string()), Ok(rank));
+        }
+        for piece in Piece::iter() {
+            assert_eq!(Piece::from_str(&piece.to_string()), Ok(piece));
+        }
+        for side in CastlingSide::iter() {
+            assert_eq!(CastlingSide::from_str(&side.to_string()), Ok(side));
+        }
+        assert_eq!(Piece::Knight.to_string(), "n");
+        assert_eq!(CastlingSide::King.to_string(), "k");
+        assert_eq!(File::from_str("E"), Err(FileParseError::BadChar('E')));
+        assert_eq!(Rank::from_str("9"), Err(RankParseError::BadChar('9')));
+        assert_eq!(Piece::from_str("N"), Err(PieceParseError::BadChar('N')));
+        assert_eq!(Piece::from_str("."), Err(PieceParseError::BadChar('.')));
+        assert_eq!(
+            CastlingSide::from_str("kq"),
+            Err(CastlingSideParseError::BadLength)
+        );
+    }
 }
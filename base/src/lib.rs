@@ -3,3 +3,7 @@
 pub mod bitboard;
 pub mod core;
 pub mod geometry;
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use serde;
@@ -89,6 +89,27 @@ fn bench_is_move_semilegal(c: &mut Criterion) {
     }
 }
 
+fn bench_has_legal_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("has_legal_move");
+    for (name, board) in boards() {
+        group.bench_function(format!("{}_fast", name), |b| {
+            b.iter(|| black_box(board.has_legal_move()))
+        });
+        let mut moves = unsafe { UncheckedMoveList::<256>::new() };
+        group.bench_function(format!("{}_full", name), |b| {
+            b.iter(|| {
+                moves.clear();
+                MoveGen::new(&board).gen_all(&mut moves);
+                black_box(
+                    moves
+                        .iter()
+                        .any(|mv| unsafe { mv.is_legal_unchecked(&board) }),
+                );
+            })
+        });
+    }
+}
+
 fn bench_is_attacked(c: &mut Criterion) {
     let mut group = c.benchmark_group("is_attacked");
     for (name, board) in boards() {
@@ -118,6 +139,7 @@ criterion_group!(
     bench_gen_moves,
     bench_make_move,
     bench_is_move_semilegal,
+    bench_has_legal_move,
     bench_is_attacked,
     bench_king_attack,
 );
@@ -358,7 +358,7 @@ mod magic {
         let mut res = 0;
         for _ in Sq::iter() {
             res <<= 1;
-            if r.next_u64() % 8 == 0 {
+            if r.next_u64().is_multiple_of(8) {
                 res |= 1;
             }
         }
@@ -547,7 +547,9 @@ fn main() -> io::Result<()> {
 
     zobrist::generate(&Path::new(&out_dir).join("zobrist.rs"))?;
     near::generate(&Path::new(&out_dir).join("near.rs"))?;
-    magic::generate(&Path::new(&out_dir).join("magic.rs"))?;
+    if env::var_os("CARGO_FEATURE_NO_MAGIC").is_none() {
+        magic::generate(&Path::new(&out_dir).join("magic.rs"))?;
+    }
     between::generate(&Path::new(&out_dir).join("between.rs"))?;
 
     Ok(())
@@ -1,6 +1,9 @@
 use crate::bitboard::Bitboard;
 use crate::core::{Color, Sq};
 
+// With the `no-magic` feature, slider attacks are computed by scanning the rays on each call
+// instead of using the magic lookup tables. This is much slower, but makes the binary smaller.
+
 #[inline]
 const fn bb(val: u64) -> Bitboard {
     Bitboard::from_raw(val)
@@ -8,14 +11,17 @@ const fn bb(val: u64) -> Bitboard {
 
 include!(concat!(env!("OUT_DIR"), "/near.rs"));
 
+#[cfg(not(feature = "no-magic"))]
 struct MagicEntry {
     mask: Bitboard,
     post_mask: Bitboard,
     lookup: *const Bitboard,
 }
 
+#[cfg(not(feature = "no-magic"))]
 unsafe impl Sync for MagicEntry {}
 
+#[cfg(not(feature = "no-magic"))]
 include!(concat!(env!("OUT_DIR"), "/magic.rs"));
 
 #[inline]
@@ -36,6 +42,7 @@ pub fn pawn(color: Color, s: Sq) -> Bitboard {
     }
 }
 
+#[cfg(not(feature = "no-magic"))]
 #[inline]
 pub fn rook(s: Sq, occupied: Bitboard) -> Bitboard {
     unsafe {
@@ -47,6 +54,7 @@ pub fn rook(s: Sq, occupied: Bitboard) -> Bitboard {
     }
 }
 
+#[cfg(not(feature = "no-magic"))]
 #[inline]
 pub fn bishop(s: Sq, occupied: Bitboard) -> Bitboard {
     unsafe {
@@ -57,3 +65,89 @@ pub fn bishop(s: Sq, occupied: Bitboard) -> Bitboard {
         *entry.lookup.add(idx as usize) & entry.post_mask
     }
 }
+
+#[cfg(feature = "no-magic")]
+#[inline]
+pub fn rook(s: Sq, occupied: Bitboard) -> Bitboard {
+    scan::rook(s, occupied)
+}
+
+#[cfg(feature = "no-magic")]
+#[inline]
+pub fn bishop(s: Sq, occupied: Bitboard) -> Bitboard {
+    scan::bishop(s, occupied)
+}
+
+#[cfg(any(test, feature = "no-magic"))]
+mod scan {
+    use crate::bitboard::Bitboard;
+    use crate::core::Sq;
+
+    fn slide(s: Sq, occupied: Bitboard, dirs: &[(i8, i8); 4]) -> Bitboard {
+        let mut res = Bitboard::EMPTY;
+        for &(df, dr) in dirs {
+            let (mut file, mut rank) = (s.file(), s.rank());
+            while let (Some(f), Some(r)) = (file.offset(df), rank.offset(dr)) {
+                (file, rank) = (f, r);
+                let t = Sq::make(file, rank);
+                res.set(t);
+                if occupied.has(t) {
+                    break;
+                }
+            }
+        }
+        res
+    }
+
+    pub fn rook(s: Sq, occupied: Bitboard) -> Bitboard {
+        slide(s, occupied, &[(1, 0), (-1, 0), (0, 1), (0, -1)])
+    }
+
+    pub fn bishop(s: Sq, occupied: Bitboard) -> Bitboard {
+        slide(s, occupied, &[(1, 1), (1, -1), (-1, 1), (-1, -1)])
+    }
+}
+
+#[cfg(all(test, not(feature = "no-magic")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        // Simple xorshift, as we only need some arbitrary occupancies.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for s in Sq::iter() {
+            for i in 0..256 {
+                // Vary the density to get both long and short rays.
+                let occupied = match i % 3 {
+                    0 => next(),
+                    1 => next() & next(),
+                    _ => next() & next() & next(),
+                };
+                let occupied = Bitboard::from_raw(occupied);
+                assert_eq!(
+                    rook(s, occupied),
+                    scan::rook(s, occupied),
+                    "{} {:?}",
+                    s,
+                    occupied
+                );
+                assert_eq!(
+                    bishop(s, occupied),
+                    scan::bishop(s, occupied),
+                    "{} {:?}",
+                    s,
+                    occupied
+                );
+            }
+            assert_eq!(rook(s, Bitboard::EMPTY), scan::rook(s, Bitboard::EMPTY));
+            assert_eq!(bishop(s, Bitboard::EMPTY), scan::bishop(s, Bitboard::EMPTY));
+        }
+    }
+}
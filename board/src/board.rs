@@ -1,7 +1,7 @@
 use crate::bitboard::Bitboard;
 use crate::core::{self, CastlingRights, CastlingSide, Cell, Color, File, Piece, Rank, Sq};
-use crate::moves::{self, Move, RawUndo};
-use crate::{geometry, movegen, zobrist};
+use crate::moves::{self, Move, MoveKind, RawUndo};
+use crate::{attack, between, castling, geometry, movegen, see, zobrist};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::num::ParseIntError;
@@ -16,8 +16,17 @@ pub struct RawBoard {
     pub ep_src: Option<Sq>,
     pub move_counter: u16,
     pub move_number: u16,
+    pub chess960: bool,
+    /// Rook files for castling, indexed by color and castling side. Only meaningful in Chess960
+    /// mode; standard positions always use files A and H.
+    pub castling_files: [[File; 2]; 2],
 }
 
+pub const STANDARD_CASTLING_FILES: [[File; 2]; 2] = [[File::A, File::H]; 2];
+
+/// Piece values in centipawns, indexed by [`Piece::index`]. The king has no material value.
+pub const PIECE_VALUES: [i32; Piece::COUNT] = [100, 0, 300, 300, 500, 900];
+
 impl RawBoard {
     #[inline]
     pub const fn empty() -> Self {
@@ -28,6 +37,8 @@ impl RawBoard {
             ep_src: None,
             move_counter: 0,
             move_number: 1,
+            chess960: false,
+            castling_files: STANDARD_CASTLING_FILES,
         }
     }
 
@@ -40,6 +51,8 @@ impl RawBoard {
             ep_src: None,
             move_counter: 0,
             move_number: 1,
+            chess960: false,
+            castling_files: STANDARD_CASTLING_FILES,
         };
         for file in File::iter() {
             res.put2(file, Rank::R2, Cell::WhitePawn);
@@ -99,6 +112,17 @@ impl RawBoard {
         hash
     }
 
+    #[inline]
+    pub fn castling_rook(&self, c: Color, s: CastlingSide) -> Sq {
+        let file = unsafe {
+            *self
+                .castling_files
+                .get_unchecked(c as usize)
+                .get_unchecked(s as usize)
+        };
+        Sq::make(file, geometry::castling_rank(c))
+    }
+
     #[inline]
     pub fn ep_dst(&self) -> Option<Sq> {
         let p = self.ep_src?;
@@ -113,6 +137,43 @@ impl Default for RawBoard {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CheckInfo {
+    pub checkers: Bitboard,
+    pub slider: bool,
+}
+
+impl CheckInfo {
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.checkers.len()
+    }
+
+    #[inline]
+    pub fn is_check(&self) -> bool {
+        self.checkers.is_nonempty()
+    }
+
+    #[inline]
+    pub fn is_double(&self) -> bool {
+        self.count() > 1
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate,
+    Stalemate,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PositionInfo {
+    pub king: Sq,
+    pub check: CheckInfo,
+    pub pinned: Bitboard,
+}
+
 #[derive(Debug, Clone)]
 pub struct Board {
     pub(crate) r: RawBoard,
@@ -121,6 +182,7 @@ pub struct Board {
     pub(crate) black: Bitboard,
     pub(crate) all_v: Bitboard,
     pub(crate) cells: [Bitboard; Cell::COUNT],
+    pub(crate) history: Option<Vec<u64>>,
 }
 
 impl Board {
@@ -128,6 +190,49 @@ impl Board {
         RawBoard::start().try_into().unwrap()
     }
 
+    pub fn from_cells(
+        squares: [Cell; 64],
+        side: Color,
+        castling: CastlingRights,
+        ep_src: Option<Sq>,
+    ) -> Result<Board, ValidateError> {
+        RawBoard {
+            squares,
+            side,
+            castling,
+            ep_src,
+            ..RawBoard::empty()
+        }
+        .try_into()
+    }
+
+    /// Returns the same position with colors swapped, i.e. flips the board vertically and inverts
+    /// the colors of all the pieces and the side to move.
+    pub fn mirror(&self) -> Board {
+        let mut squares = [Cell::None; 64];
+        for (sq, cell) in self.iter_pieces() {
+            let (c, p) = (cell.color().unwrap(), cell.piece().unwrap());
+            squares[sq.flipped_rank().index()] = Cell::make(c.inv(), p);
+        }
+        let mut castling = CastlingRights::EMPTY;
+        for (c, s) in self.r.castling.iter() {
+            castling.set(c.inv(), s);
+        }
+        let [white_files, black_files] = self.r.castling_files;
+        RawBoard {
+            squares,
+            side: self.r.side.inv(),
+            castling,
+            ep_src: self.r.ep_src.map(Sq::flipped_rank),
+            move_counter: self.r.move_counter,
+            move_number: self.r.move_number,
+            chess960: self.r.chess960,
+            castling_files: [black_files, white_files],
+        }
+        .try_into()
+        .expect("mirrored position must be valid")
+    }
+
     #[inline]
     pub fn raw(&self) -> &RawBoard {
         &self.r
@@ -201,6 +306,18 @@ impl Board {
         self.hash
     }
 
+    /// Computes the hash from scratch, instead of taking the incrementally updated one.
+    #[inline]
+    pub fn recompute_hash(&self) -> u64 {
+        self.r.zobrist_hash()
+    }
+
+    /// Returns `true` if the incrementally updated hash matches the one computed from scratch.
+    #[inline]
+    pub fn hash_is_consistent(&self) -> bool {
+        self.recompute_hash() == self.hash
+    }
+
     #[inline]
     pub fn is_opponent_king_attacked(&self) -> bool {
         let c = self.r.side;
@@ -213,16 +330,329 @@ impl Board {
         movegen::is_square_attacked(self, self.king_pos(c), c.inv())
     }
 
+    /// Returns the pieces of color `c` attacking the square `s`, as if only the squares in
+    /// `occupied` were occupied. The pieces outside `occupied` are not returned, so removing a
+    /// slider from it reveals the x-ray attackers behind.
+    #[inline]
+    pub fn attackers_to(&self, s: Sq, c: Color, occupied: Bitboard) -> Bitboard {
+        let res = (self.piece(c, Piece::Pawn) & attack::pawn(c.inv(), s))
+            | (self.piece(c, Piece::King) & attack::king(s))
+            | (self.piece(c, Piece::Knight) & attack::knight(s))
+            | (attack::bishop(s, occupied) & self.piece_diag(c))
+            | (attack::rook(s, occupied) & self.piece_line(c));
+        res & occupied
+    }
+
     #[inline]
     pub fn checkers(&self) -> Bitboard {
         let c = self.r.side;
         movegen::square_attackers(self, self.king_pos(c), c.inv())
     }
 
+    #[inline]
+    pub fn checker_info(&self) -> CheckInfo {
+        let checkers = self.checkers();
+        let inv = self.r.side.inv();
+        let sliders = self.piece_diag(inv) | self.piece_line(inv);
+        CheckInfo {
+            checkers,
+            slider: (checkers & sliders).is_nonempty(),
+        }
+    }
+
+    pub fn pinned(&self) -> Bitboard {
+        let c = self.side();
+        let king = self.king_pos(c);
+        let snipers = (attack::bishop(king, Bitboard::EMPTY) & self.piece_diag(c.inv()))
+            | (attack::rook(king, Bitboard::EMPTY) & self.piece_line(c.inv()));
+        let mut res = Bitboard::EMPTY;
+        for s in snipers {
+            let blockers = between::between(s, king) & self.all();
+            if blockers.len() == 1 {
+                res |= blockers & self.color(c);
+            }
+        }
+        res
+    }
+
+    pub fn position_info(&self) -> PositionInfo {
+        PositionInfo {
+            king: self.king_pos(self.side()),
+            check: self.checker_info(),
+            pinned: self.pinned(),
+        }
+    }
+
+    // Faster than `is_legal_unchecked` for captures. Only valid for semi-legal captures, and
+    // `info` must be computed for this position.
+    #[inline]
+    pub fn is_capture_legal(&self, mv: Move, info: &PositionInfo) -> bool {
+        if mv.src() == info.king || mv.kind() == MoveKind::Enpassant {
+            return unsafe { mv.is_legal_unchecked(self) };
+        }
+        if info.check.is_double() || (info.check.is_check() && !info.check.checkers.has(mv.dst())) {
+            return false;
+        }
+        !info.pinned.has(mv.src()) || between::between(info.king, mv.dst()).has(mv.src())
+    }
+
     pub fn all(&self) -> Bitboard {
         self.all_v
     }
 
+    /// Iterates over all the pieces on the board, skipping empty squares.
+    #[inline]
+    pub fn iter_pieces(&self) -> impl Iterator<Item = (Sq, Cell)> + '_ {
+        self.all_v.into_iter().map(|sq| (sq, self.get(sq)))
+    }
+
+    #[inline]
+    pub fn has_legal_move(&self) -> bool {
+        movegen::MoveGen::new(self).has_legal_move()
+    }
+
+    pub fn legal_destinations(&self, sq: Sq) -> Bitboard {
+        let mut moves = movegen::MoveList::new();
+        movegen::MoveGen::new(self).gen_legal(&mut moves);
+        moves
+            .into_iter()
+            .filter(|mv| mv.src() == sq)
+            .fold(Bitboard::EMPTY, |bb, mv| bb.with(mv.dst()))
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match (self.has_legal_move(), self.is_check()) {
+            (true, _) => GameStatus::Ongoing,
+            (false, true) => GameStatus::Checkmate,
+            (false, false) => GameStatus::Stalemate,
+        }
+    }
+
+    #[inline]
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && !self.has_legal_move()
+    }
+
+    #[inline]
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && !self.has_legal_move()
+    }
+
+    // This clones the board and generates all the replies, so it's too slow to be called for
+    // every move in the search. The move must be legal.
+    pub fn gives_checkmate(&self, mv: Move) -> bool {
+        let mut b = self.clone();
+        _ = unsafe { b.make_move_unchecked(mv) };
+        b.is_checkmate()
+    }
+
+    /// Returns `true` if the move gives check, either directly or by uncovering a slider. The move
+    /// must be semi-legal.
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let c = self.r.side;
+        let king = self.king_pos(c.inv());
+        let (src, dst) = (mv.src(), mv.dst());
+        let (mut diag, mut line) = (
+            self.piece_diag(c).without(src),
+            self.piece_line(c).without(src),
+        );
+        let mut occupied = self.all().without(src).with(dst);
+        let moved = match mv.kind() {
+            MoveKind::Null => return false,
+            MoveKind::CastlingKingside | MoveKind::CastlingQueenside => {
+                // Only the rook can give check, the king cannot attack the opponent king.
+                let side = CastlingSide::try_from(mv.kind()).unwrap();
+                let (rook, rook_dst) = (self.r.castling_rook(c, side), castling::rook_dst(c, side));
+                occupied = self
+                    .all()
+                    .without(src)
+                    .without(rook)
+                    .with(dst)
+                    .with(rook_dst);
+                line = line.without(rook).with(rook_dst);
+                Piece::King
+            }
+            MoveKind::Enpassant => {
+                // Removing both pawns can uncover a slider along the rank.
+                occupied.unset(Sq::make(dst.file(), src.rank()));
+                Piece::Pawn
+            }
+            kind => kind
+                .promote()
+                .unwrap_or_else(|| self.get(src).piece().unwrap()),
+        };
+        match moved {
+            Piece::Bishop => diag.set(dst),
+            Piece::Rook => line.set(dst),
+            Piece::Queen => {
+                diag.set(dst);
+                line.set(dst);
+            }
+            Piece::Pawn if attack::pawn(c, dst).has(king) => return true,
+            Piece::Knight if attack::knight(dst).has(king) => return true,
+            _ => {}
+        }
+        (attack::bishop(king, occupied) & diag).is_nonempty()
+            || (attack::rook(king, occupied) & line).is_nonempty()
+    }
+
+    #[inline]
+    pub fn is_tactical(&self, mv: Move) -> bool {
+        !mv.is_quiet(self)
+    }
+
+    pub fn has_opposite_colored_bishops(&self) -> bool {
+        use geometry::bitboard::{DARK, LIGHT};
+
+        let white = self.piece(Color::White, Piece::Bishop);
+        let black = self.piece(Color::Black, Piece::Bishop);
+        if white.len() != 1 || black.len() != 1 {
+            return false;
+        }
+        ((white & LIGHT).is_nonempty() && (black & DARK).is_nonempty())
+            || ((white & DARK).is_nonempty() && (black & LIGHT).is_nonempty())
+    }
+
+    pub fn is_insufficient_material(&self) -> bool {
+        use geometry::bitboard::{DARK, LIGHT};
+
+        let heavy = [Piece::Pawn, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .any(|p| (self.piece(Color::White, p) | self.piece(Color::Black, p)).is_nonempty());
+        if heavy {
+            return false;
+        }
+        let knights =
+            self.piece(Color::White, Piece::Knight) | self.piece(Color::Black, Piece::Knight);
+        let bishops =
+            self.piece(Color::White, Piece::Bishop) | self.piece(Color::Black, Piece::Bishop);
+        if (knights | bishops).len() <= 1 {
+            return true;
+        }
+        knights.is_empty() && ((bishops & LIGHT).is_empty() || (bishops & DARK).is_empty())
+    }
+
+    #[inline]
+    pub fn see(&self, mv: Move) -> i32 {
+        see::see(self, mv)
+    }
+
+    #[inline]
+    pub fn piece_count(&self, c: Color, p: Piece) -> u32 {
+        self.piece(c, p).len()
+    }
+
+    #[inline]
+    pub fn material(&self, c: Color) -> i32 {
+        Piece::iter()
+            .map(|p| PIECE_VALUES[p.index()] * self.piece_count(c, p) as i32)
+            .sum()
+    }
+
+    #[inline]
+    pub fn non_pawn_material(&self, c: Color) -> i32 {
+        self.material(c)
+            - PIECE_VALUES[Piece::Pawn.index()] * self.piece_count(c, Piece::Pawn) as i32
+    }
+
+    #[inline]
+    pub fn material_key(&self) -> u64 {
+        Cell::iter()
+            .skip(1)
+            // Four bits per cell kind are enough, as there may be at most 10 pieces of each kind.
+            .map(|c| (self.cell(c).len() as u64) << (4 * c.index()))
+            .fold(0, |acc, x| acc | x)
+    }
+
+    pub fn material_signature(&self) -> String {
+        const ORDER: [Piece; 6] = [
+            Piece::King,
+            Piece::Queen,
+            Piece::Rook,
+            Piece::Bishop,
+            Piece::Knight,
+            Piece::Pawn,
+        ];
+        let mut res = String::new();
+        for color in Color::iter() {
+            if color == Color::Black {
+                res.push('v');
+            }
+            for p in ORDER {
+                let ch = Cell::make(Color::White, p).as_char();
+                for _ in 0..self.piece(color, p).len() {
+                    res.push(ch);
+                }
+            }
+        }
+        res
+    }
+
+    /// Renders the board as a grid of ASCII characters, for debugging purposes.
+    pub fn ascii(&self) -> String {
+        self.pretty(Cell::as_char)
+    }
+
+    /// Same as [`Board::ascii`], but uses Unicode chess glyphs for pieces.
+    pub fn unicode(&self) -> String {
+        self.pretty(Cell::as_unicode_char)
+    }
+
+    fn pretty(&self, cell_char: fn(Cell) -> char) -> String {
+        let mut res = String::new();
+        for rank in Rank::iter() {
+            res.push(rank.as_char());
+            for file in File::iter() {
+                res.push(' ');
+                res.push(cell_char(self.get2(file, rank)));
+            }
+            res.push('\n');
+        }
+        res.push(' ');
+        for file in File::iter() {
+            res.push(' ');
+            res.push(file.as_char());
+        }
+        res.push('\n');
+
+        let side = match self.r.side {
+            Color::White => "White",
+            Color::Black => "Black",
+        };
+        res += &format!("{} to move, castling: ", side);
+        format_castling(&self.r, &mut res).unwrap();
+        if let Some(ep) = self.r.ep_dst() {
+            res += &format!(", en passant: {}", ep);
+        }
+        res.push('\n');
+        res
+    }
+
+    pub fn track_history(&mut self) {
+        self.history.get_or_insert_with(Vec::new);
+    }
+
+    #[inline]
+    pub fn history(&self) -> Option<&[u64]> {
+        self.history.as_deref()
+    }
+
+    /// Iterates over the indices in [`Board::history`] of the earlier occurrences of the current
+    /// position since the last irreversible move, the latest first.
+    pub fn repetitions(&self) -> impl Iterator<Item = usize> + '_ {
+        let history = self.history.as_deref().unwrap_or(&[]);
+        let len = history.len().min(self.r.move_counter as usize);
+        (history.len() - len..history.len())
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .filter(move |&i| history[i] == self.hash)
+    }
+
+    pub fn is_repetition(&self, count: usize) -> bool {
+        self.repetitions().count() + 1 >= count
+    }
+
     #[inline]
     pub unsafe fn make_move_unchecked(&mut self, mv: Move) -> RawUndo {
         unsafe { moves::make_move_unchecked(self, mv) }
@@ -233,6 +663,16 @@ impl Board {
         unsafe { moves::unmake_move_unchecked(self, mv, u) }
     }
 
+    #[inline]
+    pub unsafe fn make_null_move_unchecked(&mut self) -> RawUndo {
+        unsafe { moves::make_null_move_unchecked(self) }
+    }
+
+    #[inline]
+    pub unsafe fn unmake_null_move_unchecked(&mut self, u: RawUndo) {
+        unsafe { moves::unmake_null_move_unchecked(self, u) }
+    }
+
     #[inline]
     pub unsafe fn try_make_move_unchecked(&mut self, mv: Move) -> Option<RawUndo> {
         let u = unsafe { moves::make_move_unchecked(self, mv) };
@@ -309,17 +749,26 @@ impl TryFrom<RawBoard> for Board {
         }
 
         // Reset bad castling flags
-        for color in [Color::White, Color::Black] {
+        if !raw.chess960 {
+            raw.castling_files = STANDARD_CASTLING_FILES;
+        }
+        for color in Color::iter() {
             let rank = geometry::castling_rank(color);
-            if raw.get2(File::E, rank) != Cell::make(color, Piece::King) {
-                raw.castling.unset(color, CastlingSide::Queen);
-                raw.castling.unset(color, CastlingSide::King);
-            }
-            if raw.get2(File::A, rank) != Cell::make(color, Piece::Rook) {
-                raw.castling.unset(color, CastlingSide::Queen);
-            }
-            if raw.get2(File::H, rank) != Cell::make(color, Piece::Rook) {
-                raw.castling.unset(color, CastlingSide::King);
+            let king = File::iter().find(|&f| raw.get2(f, rank) == Cell::make(color, Piece::King));
+            for side in CastlingSide::iter() {
+                let rook = raw.castling_rook(color, side);
+                let valid = king.is_some_and(|king| {
+                    let order_ok = match side {
+                        CastlingSide::Queen => rook.file() < king,
+                        CastlingSide::King => rook.file() > king,
+                    };
+                    (raw.chess960 || king == File::E)
+                        && order_ok
+                        && raw.get(rook) == Cell::make(color, Piece::Rook)
+                });
+                if !valid {
+                    raw.castling.unset(color, side);
+                }
             }
         }
 
@@ -376,6 +825,7 @@ impl TryFrom<RawBoard> for Board {
             black,
             all_v: white | black,
             cells,
+            history: None,
         };
         if res.is_opponent_king_attacked() {
             return Err(ValidateError::OpponentKingAttacked);
@@ -508,10 +958,91 @@ fn parse_ep_src(s: &str, side: Color) -> Result<Option<Sq>, RawFenParseError> {
     Ok(Some(Sq::make(ep.file(), geometry::ep_src_rank(side))))
 }
 
-impl FromStr for RawBoard {
-    type Err = RawFenParseError;
+/// Parses castling rights in either the standard, X-FEN or Shredder-FEN notation, and detects
+/// whether the position is Chess960.
+///
+/// In Chess960 mode, `KQkq` letters mean the outermost rook on the corresponding side of the king,
+/// as X-FEN requires. Otherwise, they mean the rooks on the h- and a-files. File letters denote the
+/// rook file directly and always imply Chess960.
+fn parse_castling(
+    s: &str,
+    squares: &[Cell; 64],
+    mut chess960: bool,
+) -> Result<(CastlingRights, [[File; 2]; 2], bool), core::CastlingRightsParseError> {
+    type Error = core::CastlingRightsParseError;
 
-    fn from_str(s: &str) -> Result<RawBoard, Self::Err> {
+    if s == "-" {
+        return Ok((CastlingRights::EMPTY, STANDARD_CASTLING_FILES, chess960));
+    }
+    if s.is_empty() {
+        return Err(Error::EmptyString);
+    }
+    let mut castling = CastlingRights::EMPTY;
+    let mut files = STANDARD_CASTLING_FILES;
+    for b in s.bytes() {
+        let c = b as char;
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let rank = geometry::castling_rank(color);
+        let king = File::iter()
+            .find(|&f| squares[Sq::make(f, rank).index()] == Cell::make(color, Piece::King));
+        let is_rook =
+            |f: File| squares[Sq::make(f, rank).index()] == Cell::make(color, Piece::Rook);
+        let (side, file) = match c.to_ascii_lowercase() {
+            lower @ ('k' | 'q') => {
+                let side = if lower == 'k' {
+                    CastlingSide::King
+                } else {
+                    CastlingSide::Queen
+                };
+                // X-FEN: in Chess960, the letter means the outermost rook on its side of the king.
+                let rook = king.filter(|_| chess960).and_then(|king| {
+                    let mut rooks = File::iter().filter(|&f| {
+                        is_rook(f)
+                            && match side {
+                                CastlingSide::Queen => f < king,
+                                CastlingSide::King => f > king,
+                            }
+                    });
+                    match side {
+                        CastlingSide::Queen => rooks.next(),
+                        CastlingSide::King => rooks.last(),
+                    }
+                });
+                (
+                    side,
+                    rook.unwrap_or(STANDARD_CASTLING_FILES[0][side.index()]),
+                )
+            }
+            lower @ 'a'..='h' => {
+                chess960 = true;
+                let file = File::from_char(lower).unwrap();
+                let side = if file < king.unwrap_or(File::E) {
+                    CastlingSide::Queen
+                } else {
+                    CastlingSide::King
+                };
+                (side, file)
+            }
+            _ => return Err(Error::BadChar(c)),
+        };
+        if castling.has(color, side) {
+            return Err(Error::DuplicateChar(c));
+        }
+        castling.set(color, side);
+        files[color.index()][side.index()] = file;
+    }
+    Ok((castling, files, chess960))
+}
+
+impl RawBoard {
+    /// Parses the FEN. In Chess960 mode, `KQkq` mean the outermost rooks, as in X-FEN. Otherwise,
+    /// they mean the rooks on the h- and a-files, and only the rook file letters (as in
+    /// Shredder-FEN) turn Chess960 on.
+    pub fn from_fen(s: &str, chess960: bool) -> Result<RawBoard, RawFenParseError> {
         type Error = RawFenParseError;
 
         if !s.is_ascii() {
@@ -521,7 +1052,8 @@ impl FromStr for RawBoard {
 
         let squares = parse_squares(iter.next().ok_or(Error::NoBoard)?)?;
         let side = Color::from_str(iter.next().ok_or(Error::NoMoveSide)?)?;
-        let castling = CastlingRights::from_str(iter.next().ok_or(Error::NoCastling)?)?;
+        let (castling, castling_files, chess960) =
+            parse_castling(iter.next().ok_or(Error::NoCastling)?, &squares, chess960)?;
         let ep_src = parse_ep_src(iter.next().ok_or(Error::NoEnpassant)?, side)?;
         let move_counter = match iter.next() {
             Some(s) => u16::from_str(s).map_err(Error::MoveCounter)?,
@@ -543,18 +1075,40 @@ impl FromStr for RawBoard {
             ep_src,
             move_counter,
             move_number,
+            chess960,
+            castling_files,
         })
     }
 }
 
+impl FromStr for RawBoard {
+    type Err = RawFenParseError;
+
+    fn from_str(s: &str) -> Result<RawBoard, Self::Err> {
+        RawBoard::from_fen(s, false)
+    }
+}
+
+impl Board {
+    /// Parses the FEN, see [`RawBoard::from_fen`].
+    pub fn from_fen(s: &str, chess960: bool) -> Result<Board, FenParseError> {
+        Ok(RawBoard::from_fen(s, chess960)?.try_into()?)
+    }
+}
+
 impl FromStr for Board {
     type Err = FenParseError;
 
     fn from_str(s: &str) -> Result<Board, Self::Err> {
-        Ok(RawBoard::from_str(s)?.try_into()?)
+        Board::from_fen(s, false)
     }
 }
 
+// With the `serde` feature, `RawBoard` and `Board` serialize as FEN. Deserializing a `Board`
+// validates the position.
+#[cfg(feature = "serde")]
+pawnyowl_base::impl_serde_str!(RawBoard, Board);
+
 fn format_squares(squares: &[Cell; 64], f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
     for rank in Rank::iter() {
         if rank.index() != 0 {
@@ -580,10 +1134,31 @@ fn format_squares(squares: &[Cell; 64], f: &mut fmt::Formatter<'_>) -> Result<()
     Ok(())
 }
 
+fn format_castling(b: &RawBoard, f: &mut impl fmt::Write) -> Result<(), fmt::Error> {
+    if !b.chess960 || b.castling == CastlingRights::EMPTY {
+        return write!(f, "{}", b.castling);
+    }
+    // Use Shredder-FEN in Chess960 mode, as it is always unambiguous. FEN lists the kingside
+    // right first.
+    for color in Color::iter() {
+        for side in CastlingSide::iter().rev() {
+            if b.castling.has(color, side) {
+                let c = b.castling_rook(color, side).file().as_char();
+                match color {
+                    Color::White => write!(f, "{}", c.to_ascii_uppercase())?,
+                    Color::Black => write!(f, "{}", c)?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 impl fmt::Display for RawBoard {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         format_squares(&self.squares, f)?;
-        write!(f, " {} {}", self.side, self.castling)?;
+        write!(f, " {} ", self.side)?;
+        format_castling(self, f)?;
         match self.ep_dst() {
             Some(p) => write!(f, " {}", p)?,
             None => write!(f, " -")?,
@@ -606,8 +1181,8 @@ mod tests {
 
     #[test]
     fn test_size() {
-        assert_eq!(mem::size_of::<RawBoard>(), 72);
-        assert_eq!(mem::size_of::<Board>(), 208);
+        assert_eq!(mem::size_of::<RawBoard>(), 78);
+        assert_eq!(mem::size_of::<Board>(), 240);
     }
 
     #[test]
@@ -658,6 +1233,151 @@ mod tests {
             board.to_string(),
             "r1bq1b1r/ppppkppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK1R1 w Q - 6 5"
         );
+
+        // Without any rook on the kingside, the right is dropped.
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K3 w KQkq - 0 1").unwrap();
+        assert_eq!(board.to_string(), "r3k2r/8/8/8/8/8/8/R3K3 w Qkq - 0 1");
+    }
+
+    #[test]
+    fn test_castling_fen() {
+        const FEN_960: &str = "rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w GAga - 0 1";
+
+        let b = Board::from_str(FEN_960).unwrap();
+        assert!(b.raw().chess960);
+        assert_eq!(b.raw().castling, CastlingRights::FULL);
+        assert_eq!(b.raw().castling_files, [[File::A, File::G]; 2]);
+        assert_eq!(b.to_string(), FEN_960);
+
+        // X-FEN in Chess960 mode.
+        let b2 = Board::from_fen("rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w KQkq - 0 1", true);
+        assert_eq!(b2.unwrap(), b);
+        let b2 = Board::from_fen("rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w KAkq - 0 1", true);
+        assert_eq!(b2.unwrap(), b);
+
+        // Outermost rooks are chosen by X-FEN.
+        let b = Board::from_fen("1r1k1rr1/8/8/8/8/8/8/1RRK1RR1 w KQkq - 0 1", true).unwrap();
+        assert_eq!(b.raw().castling_files, [[File::B, File::G]; 2]);
+        assert_eq!(b.to_string(), "1r1k1rr1/8/8/8/8/8/8/1RRK1RR1 w GBgb - 0 1");
+        let b = Board::from_str("1r1k1rr1/8/8/8/8/8/8/1RRK1RR1 w FCfc - 0 1").unwrap();
+        assert_eq!(b.raw().castling_files, [[File::C, File::F]; 2]);
+        let b = Board::from_fen("1r2k1rr/8/8/8/8/8/8/1R2KR1R w KQkq - 0 1", true).unwrap();
+        assert!(b.raw().chess960);
+        assert_eq!(b.raw().castling_files, [[File::B, File::H]; 2]);
+        assert_eq!(b.to_string(), "1r2k1rr/8/8/8/8/8/8/1R2KR1R w HBhb - 0 1");
+
+        // Outside Chess960 mode, `KQkq` mean the h- and a-file rooks, even if the king or the
+        // rooks are elsewhere.
+        let b = Board::from_str("rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w KQkq - 0 1").unwrap();
+        assert!(!b.raw().chess960);
+        assert_eq!(b.raw().castling, CastlingRights::EMPTY);
+        let b = Board::from_str("1r2k1rr/8/8/8/8/8/8/1R2KR1R w KQkq - 0 1").unwrap();
+        assert!(!b.raw().chess960);
+        assert_eq!(b.to_string(), "1r2k1rr/8/8/8/8/8/8/1R2KR1R w Kk - 0 1");
+        let b = Board::from_str("rnbqk1r1/8/8/8/8/8/8/RNBQK1R1 w KQkq - 0 1").unwrap();
+        assert!(!b.raw().chess960);
+        assert_eq!(b.to_string(), "rnbqk1r1/8/8/8/8/8/8/RNBQK1R1 w Qq - 0 1");
+
+        // File letters imply Chess960 even for standard positions, while `KQkq` does not.
+        let b = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").unwrap();
+        assert!(b.raw().chess960);
+        assert_eq!(b.to_string(), "r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1");
+        let b = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(!b.raw().chess960);
+        assert_eq!(b.to_string(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+
+        assert_eq!(
+            RawBoard::from_str("r3k2r/8/8/8/8/8/8/R3K2R w HKha - 0 1"),
+            Err(RawFenParseError::Castling(
+                core::CastlingRightsParseError::DuplicateChar('K')
+            ))
+        );
+        assert_eq!(
+            RawBoard::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkx - 0 1"),
+            Err(RawFenParseError::Castling(
+                core::CastlingRightsParseError::BadChar('x')
+            ))
+        );
+    }
+
+    #[test]
+    fn test_iter_pieces() {
+        let b = Board::from_str("8/8/4k3/3r4/8/3RP3/4K3/8 w - - 0 1").unwrap();
+        let pieces: Vec<_> = b.iter_pieces().collect();
+        assert_eq!(
+            pieces,
+            vec![
+                (Sq::make(File::E, Rank::R6), Cell::BlackKing),
+                (Sq::make(File::D, Rank::R5), Cell::BlackRook),
+                (Sq::make(File::D, Rank::R3), Cell::WhiteRook),
+                (Sq::make(File::E, Rank::R3), Cell::WhitePawn),
+                (Sq::make(File::E, Rank::R2), Cell::WhiteKing),
+            ]
+        );
+
+        let b = Board::start();
+        assert_eq!(b.iter_pieces().count(), 32);
+        for (sq, cell) in b.iter_pieces() {
+            assert_eq!(b.get(sq), cell);
+            assert_ne!(cell, Cell::None);
+        }
+    }
+
+    #[test]
+    fn test_mirror() {
+        for (fen, mirrored) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            ),
+            (
+                "r3k3/8/8/3pP3/8/8/8/4K2R w Kq d6 0 12",
+                "4k2r/8/8/8/3Pp3/8/8/R3K3 b Qk d3 0 12",
+            ),
+            (
+                "1rq1r1k1/1p3ppp/pB3n2/3ppP2/Pbb1P3/1PN2B2/2P2QPP/R1R4K w - - 1 21",
+                "r1r4k/2p2qpp/1pn2b2/pBB1p3/3PPp2/Pb3N2/1P3PPP/1RQ1R1K1 b - - 1 21",
+            ),
+            (
+                "rk4r1/pppppppp/8/8/8/8/PPPPPPPP/2KR2R1 w ga - 3 7",
+                "2kr2r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 b GA - 3 7",
+            ),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert_eq!(b.mirror().to_string(), mirrored);
+            assert_eq!(b.mirror().mirror(), b);
+        }
+    }
+
+    #[test]
+    fn test_pretty() {
+        let mut b = Board::start();
+        b.make_uci_move("e2e4").unwrap();
+        assert_eq!(
+            b.ascii(),
+            concat!(
+                "8 r n b q k b n r\n",
+                "7 p p p p p p p p\n",
+                "6 . . . . . . . .\n",
+                "5 . . . . . . . .\n",
+                "4 . . . . P . . .\n",
+                "3 . . . . . . . .\n",
+                "2 P P P P . P P P\n",
+                "1 R N B Q K B N R\n",
+                "  a b c d e f g h\n",
+                "Black to move, castling: KQkq, en passant: e3\n",
+            )
+        );
+
+        let b = Board::from_str("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let unicode = b.unicode();
+        assert!(unicode.starts_with("8 . . . . ♚ . . .\n"), "{}", unicode);
+        assert!(unicode.contains("\n1 . . . . ♔ . . ♖\n"), "{}", unicode);
+        assert!(
+            unicode.ends_with("\nWhite to move, castling: K\n"),
+            "{}",
+            unicode
+        );
     }
 
     #[test]
@@ -687,4 +1407,380 @@ mod tests {
         assert_eq!(raw.move_counter, 10);
         assert_eq!(raw.move_number, 1);
     }
+
+    #[test]
+    fn test_material() {
+        let b = Board::start();
+        assert_eq!(b.piece_count(Color::White, Piece::Pawn), 8);
+        assert_eq!(b.piece_count(Color::Black, Piece::Knight), 2);
+        assert_eq!(b.material(Color::White), 3900);
+        assert_eq!(b.non_pawn_material(Color::Black), 3100);
+
+        assert_eq!(b.material_signature(), "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP");
+
+        let b = Board::from_str("8/8/4k3/3r4/8/3RP3/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(b.material_signature(), "KRPvKR");
+        assert_eq!(b.material(Color::White), 600);
+        assert_eq!(b.non_pawn_material(Color::White), 500);
+        assert_eq!(b.material(Color::Black), 500);
+        let b2 = Board::from_str("3r4/8/8/1k6/8/P7/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(b2.material_signature(), "KRPvKR");
+        assert_eq!(b.material_key(), b2.material_key());
+
+        let b3 = Board::from_str("8/8/4k3/3r4/8/3R4/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(b3.material_signature(), "KRvKR");
+        assert_ne!(b.material_key(), b3.material_key());
+
+        let b4 = Board::from_str("8/8/4k3/3r4/8/3Rp3/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(b4.material_signature(), "KRvKRP");
+        assert_ne!(b.material_key(), b4.material_key());
+    }
+
+    #[test]
+    fn test_tactical() {
+        let b = Board::from_str("3r3n/2P5/k7/3pP3/8/8/8/1N2K2R w K d6 0 1").unwrap();
+        for (mv, tactical) in [
+            ("c7c8q", true),
+            ("c7c8n", true),
+            ("c7d8r", true),
+            ("e5d6", true),
+            ("e5e6", false),
+            ("b1c3", false),
+            ("e1g1", false),
+            ("h1h8", true),
+            ("h1h7", false),
+        ] {
+            let mv = Move::from_uci_legal(mv, &b).unwrap();
+            assert_eq!(b.is_tactical(mv), tactical, "{}", mv);
+        }
+        assert!(!b.is_tactical(Move::NULL));
+    }
+
+    #[test]
+    fn test_gives_check() {
+        for (fen, mv, check) in [
+            // Direct checks.
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8", true),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a7", false),
+            ("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1", "d2d3", false),
+            ("4k3/8/8/8/8/3P4/8/4K3 w - - 0 1", "d3d4", false),
+            ("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1", "d6d7", true),
+            ("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1", "d2d4", false),
+            ("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1", "b1d2", false),
+            ("4k3/8/8/3N4/8/8/8/4K3 w - - 0 1", "d5f6", true),
+            ("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", "f1b5", true),
+            // Discovered checks.
+            ("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1", "e2c3", true),
+            ("4k3/8/8/8/8/8/4P3/4R1K1 w - - 0 1", "e2e4", false),
+            ("4k3/8/8/8/8/8/5P2/6KB w - - 0 1", "f2f3", false),
+            ("4k3/8/8/8/8/8/2B5/1Q4K1 w - - 0 1", "c2d3", false),
+            ("7k/8/8/8/8/8/1N6/Q5K1 w - - 0 1", "b2d3", true),
+            // Castling, the rook gives check.
+            ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", true),
+            ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1", true),
+            ("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1g1", false),
+            // En passant, removing both pawns uncovers the rook.
+            ("8/8/8/KPp4k/8/8/8/8 w - c6 0 1", "b5c6", false),
+            ("8/8/8/R1pP3k/8/8/8/4K3 w - c6 0 1", "d5c6", true),
+            ("8/8/1k6/2pP4/8/8/8/4K3 w - c6 0 1", "d5c6", false),
+            ("8/8/3k4/2pP4/8/8/8/4K3 w - c6 0 1", "d5c6", false),
+            ("8/3k4/8/2pP4/8/8/8/4K3 w - c6 0 1", "d5c6", true),
+            // Promotions.
+            ("4k3/2P5/8/8/8/8/8/4K3 w - - 0 1", "c7c8q", true),
+            ("4k3/2P5/8/8/8/8/8/4K3 w - - 0 1", "c7c8r", true),
+            ("4k3/2P5/8/8/8/8/8/4K3 w - - 0 1", "c7c8b", false),
+            ("4k3/2P5/8/8/8/8/8/4K3 w - - 0 1", "c7c8n", false),
+            ("8/2P1k3/8/8/8/8/8/4K3 w - - 0 1", "c7c8n", true),
+            ("1n6/2P5/3k4/8/8/8/8/4K3 w - - 0 1", "c7b8n", false),
+            ("1n6/2P5/8/2k5/8/8/8/4K3 w - - 0 1", "c7b8n", false),
+            ("1n6/2P1k3/8/8/8/8/8/4K3 w - - 0 1", "c7b8q", false),
+            ("1n6/2P5/8/8/8/8/7k/4K3 w - - 0 1", "c7b8b", true),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mv = Move::from_uci_legal(mv, &b).unwrap();
+            assert_eq!(b.gives_check(mv), check, "{} {}", fen, mv);
+        }
+
+        // Compare with making the move, for all the move kinds.
+        let mut kinds = [false; MoveKind::COUNT];
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K2Q b - d3 0 1",
+            "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+            "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mut moves = movegen::MoveList::new();
+            movegen::MoveGen::new(&b).gen_legal(&mut moves);
+            for mv in moves {
+                let mut after = b.clone();
+                after.make_move(mv).unwrap();
+                assert_eq!(b.gives_check(mv), after.is_check(), "{} {}", fen, mv);
+                kinds[mv.kind().index()] = true;
+            }
+        }
+        assert!(kinds[1..].iter().all(|&k| k));
+        assert!(!Board::start().gives_check(Move::NULL));
+    }
+
+    #[test]
+    fn test_from_cells() {
+        let b = Board::from_cells(
+            RawBoard::start().squares,
+            Color::White,
+            CastlingRights::FULL,
+            None,
+        )
+        .unwrap();
+        assert_eq!(b, Board::start());
+        assert_eq!(b.zobrist_hash(), Board::start().zobrist_hash());
+        assert_eq!(b.all(), Board::start().all());
+
+        assert_eq!(
+            Board::from_cells([Cell::None; 64], Color::White, CastlingRights::EMPTY, None),
+            Err(ValidateError::NoKing(Color::White))
+        );
+    }
+
+    #[test]
+    fn test_opposite_colored_bishops() {
+        for (fen, ocb) in [
+            ("8/5p2/4kb2/3p4/3P4/2KB4/5P2/8 w - - 0 1", true),
+            ("8/5p2/4kb2/3p4/3P4/2K1B3/5P2/8 w - - 0 1", false),
+            ("8/5p2/4kb2/3p4/3P4/2KBB3/5P2/8 w - - 0 1", false),
+            ("8/5p2/4k3/3p4/3P4/2KB4/5P2/8 w - - 0 1", false),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert_eq!(b.has_opposite_colored_bishops(), ocb, "{}", fen);
+        }
+        assert!(!Board::start().has_opposite_colored_bishops());
+    }
+
+    #[test]
+    fn test_checkmate() {
+        let b = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        assert!(!b.is_checkmate());
+        assert!(!b.is_stalemate());
+        assert!(b.gives_checkmate(Move::from_uci_legal("a1a8", &b).unwrap()));
+        assert!(!b.gives_checkmate(Move::from_uci_legal("a1a7", &b).unwrap()));
+
+        let b = Board::from_str("6k1/5pp1/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        assert!(!b.gives_checkmate(Move::from_uci_legal("a1a8", &b).unwrap()));
+
+        let b = Board::from_str("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 1 1").unwrap();
+        assert!(b.is_checkmate());
+        assert!(!b.is_stalemate());
+
+        let b = Board::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!b.is_checkmate());
+        assert!(b.is_stalemate());
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(Board::start().status(), GameStatus::Ongoing);
+        let b = Board::from_str("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+        assert_eq!(b.status(), GameStatus::Checkmate);
+        let b = Board::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(b.status(), GameStatus::Stalemate);
+        let b = Board::from_str("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert_eq!(b.status(), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn test_legal_destinations() {
+        let sq = |s: &str| Sq::from_str(s).unwrap();
+        let bb = |ss: &[&str]| ss.iter().fold(Bitboard::EMPTY, |bb, s| bb.with(sq(s)));
+
+        let b = Board::from_str("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            b.legal_destinations(sq("e2")),
+            bb(&["e3", "e4", "e5", "e6", "e7"])
+        );
+        assert_eq!(b.legal_destinations(sq("e7")), Bitboard::EMPTY);
+        assert_eq!(b.legal_destinations(sq("a1")), Bitboard::EMPTY);
+
+        let b = Board::from_str("4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        assert_eq!(
+            b.legal_destinations(sq("e1")),
+            bb(&["d1", "d2", "e2", "f2", "f1", "g1"])
+        );
+        assert_eq!(b.legal_destinations(sq("e5")), bb(&["d6", "e6"]));
+    }
+
+    #[test]
+    fn test_checker_info() {
+        let info = Board::start().checker_info();
+        assert!(!info.is_check());
+        assert_eq!(info.count(), 0);
+        assert!(!info.slider);
+
+        let b = Board::from_str("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1").unwrap();
+        let info = b.checker_info();
+        assert_eq!(info.checkers, Bitboard::EMPTY.with2(File::F, Rank::R3));
+        assert!(info.is_check() && !info.is_double() && !info.slider);
+
+        let b = Board::from_str("4k3/8/8/b7/8/8/8/4K3 w - - 0 1").unwrap();
+        let info = b.checker_info();
+        assert_eq!(info.checkers, Bitboard::EMPTY.with2(File::A, Rank::R5));
+        assert!(info.is_check() && !info.is_double() && info.slider);
+
+        let b = Board::from_str("4k3/8/8/8/8/5n2/8/r3K3 w - - 0 1").unwrap();
+        let info = b.checker_info();
+        assert_eq!(
+            info.checkers,
+            Bitboard::EMPTY
+                .with2(File::F, Rank::R3)
+                .with2(File::A, Rank::R1)
+        );
+        assert_eq!(info.count(), 2);
+        assert!(info.is_double() && info.slider);
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut b = Board::start();
+        assert!(!b.is_repetition(2));
+        b.track_history();
+
+        let mut undos = Vec::new();
+        for _ in 0..2 {
+            for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                let mv = Move::from_uci_legal(mv, &b).unwrap();
+                undos.push((mv, unsafe { b.make_move_unchecked(mv) }));
+            }
+        }
+        assert_eq!(b.history().unwrap().len(), 8);
+        assert_eq!(b.repetitions().collect::<Vec<_>>(), [4, 0]);
+        assert!(b.is_repetition(3));
+        assert!(!b.is_repetition(4));
+
+        let (mv, u) = undos.pop().unwrap();
+        unsafe { b.unmake_move_unchecked(mv, u) };
+        assert_eq!(b.history().unwrap().len(), 7);
+        assert!(b.is_repetition(2));
+        assert!(!b.is_repetition(3));
+
+        b.make_uci_move("f6h5").unwrap();
+        b.make_uci_move("e2e4").unwrap();
+        b.make_uci_move("h5f6").unwrap();
+        assert!(b.is_repetition(1));
+        assert!(!b.is_repetition(2));
+    }
+
+    #[test]
+    fn test_capture_legal() {
+        for fen in [
+            "4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1",
+            "4k3/4r3/8/2n5/8/8/4R3/4K3 w - - 0 1",
+            "4k3/4r3/8/8/8/8/4B3/4K2b w - - 0 1",
+            "4k3/8/3q4/2b5/3N4/8/8/r3K3 w - - 0 1",
+            "8/8/8/KPp4r/8/8/8/6k1 w - c6 0 1",
+            "r1bqk2r/ppp2ppp/2np1n2/1Bb1p3/4P3/2PP1N2/PP3PPP/RNBQK2R w KQkq - 0 6",
+            "4k3/8/8/8/8/5n2/3Q4/r3K3 w - - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let info = b.position_info();
+            let mut moves = movegen::MoveList::new();
+            movegen::MoveGen::new(&b).gen_capture(&mut moves);
+            for mv in moves {
+                assert_eq!(
+                    b.is_capture_legal(mv, &info),
+                    unsafe { mv.is_legal_unchecked(&b) },
+                    "{} {}",
+                    fen,
+                    mv
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        let b = Board::from_str("3q2k1/8/8/3r4/8/2N5/3R4/3RK3 w - - 0 1").unwrap();
+        let d5 = Sq::from_str("d5").unwrap();
+        let (d1, d2, c3) = (
+            Sq::from_str("d1").unwrap(),
+            Sq::from_str("d2").unwrap(),
+            Sq::from_str("c3").unwrap(),
+        );
+        let all = b.all();
+        assert_eq!(
+            b.attackers_to(d5, Color::White, all),
+            Bitboard::EMPTY.with(d2).with(c3)
+        );
+        assert_eq!(
+            b.attackers_to(d5, Color::Black, all),
+            Bitboard::one(Sq::from_str("d8").unwrap())
+        );
+        assert_eq!(
+            b.attackers_to(d5, Color::White, all.without(d2)),
+            Bitboard::EMPTY.with(d1).with(c3)
+        );
+        assert_eq!(
+            b.attackers_to(d5, Color::White, all.without(d2).without(c3)),
+            Bitboard::one(d1)
+        );
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        for (fen, res) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KB2 b - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", false),
+            ("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1", true),
+            ("3bk3/8/8/8/8/8/8/4KB2 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/4KBN1 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/P7/4K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", false),
+            ("3qk3/8/8/8/8/8/8/4K3 w - - 0 1", false),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert_eq!(b.is_insufficient_material(), res, "{}", fen);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let fen = "r3k2r/8/8/8/4Pp2/8/8/R3K2R b KQkq e3 0 10";
+        let b = Board::from_str(fen).unwrap();
+        let s = serde_json::to_string(&b).unwrap();
+        assert_eq!(s, format!("\"{}\"", fen));
+        assert_eq!(serde_json::from_str::<Board>(&s).unwrap(), b);
+        assert_eq!(serde_json::from_str::<RawBoard>(&s).unwrap(), *b.raw());
+
+        // The raw board may be invalid, but the board may not.
+        let invalid = "\"4k3/8/8/8/8/8/8/4K2K w - - 0 1\"";
+        assert!(serde_json::from_str::<RawBoard>(invalid).is_ok());
+        assert!(serde_json::from_str::<Board>(invalid).is_err());
+
+        assert_eq!(serde_json::to_string(&Cell::BlackQueen).unwrap(), "\"q\"");
+        assert_eq!(
+            serde_json::from_str::<Color>("\"b\"").unwrap(),
+            Color::Black
+        );
+        assert_eq!(
+            serde_json::from_str::<CastlingRights>("\"Kq\"").unwrap(),
+            CastlingRights::EMPTY
+                .with(Color::White, CastlingSide::King)
+                .with(Color::Black, CastlingSide::Queen)
+        );
+        assert_eq!(serde_json::to_string(&Piece::Knight).unwrap(), "\"n\"");
+        assert_eq!(serde_json::from_str::<File>("\"e\"").unwrap(), File::E);
+        assert_eq!(serde_json::from_str::<Rank>("\"4\"").unwrap(), Rank::R4);
+        assert_eq!(
+            serde_json::to_string(&CastlingSide::Queen).unwrap(),
+            "\"q\""
+        );
+        assert!(serde_json::from_str::<File>("\"E\"").is_err());
+        assert!(serde_json::from_str::<Cell>("\"x\"").is_err());
+    }
 }
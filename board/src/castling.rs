@@ -1,5 +1,6 @@
 use crate::bitboard::Bitboard;
-use crate::core::{CastlingSide, Color};
+use crate::core::{CastlingSide, Color, File, Sq};
+use crate::{between, geometry};
 
 #[inline]
 pub const fn offset(c: Color) -> usize {
@@ -28,3 +29,33 @@ pub const fn srcs(c: Color, s: CastlingSide) -> Bitboard {
 }
 
 pub const ALL_SRCS: Bitboard = Bitboard::from_raw(0x91 | (0x91 << 56));
+
+#[inline]
+pub fn king_dst(c: Color, s: CastlingSide) -> Sq {
+    let file = match s {
+        CastlingSide::King => File::G,
+        CastlingSide::Queen => File::C,
+    };
+    Sq::make(file, geometry::castling_rank(c))
+}
+
+#[inline]
+pub fn rook_dst(c: Color, s: CastlingSide) -> Sq {
+    let file = match s {
+        CastlingSide::King => File::F,
+        CastlingSide::Queen => File::D,
+    };
+    Sq::make(file, geometry::castling_rank(c))
+}
+
+// In Chess960, all the squares the king and the rook pass through must be empty, except for the
+// king and the rook themselves.
+#[inline]
+pub fn pass_960(king: Sq, rook: Sq, c: Color, s: CastlingSide) -> Bitboard {
+    let (king_dst, rook_dst) = (self::king_dst(c, s), self::rook_dst(c, s));
+    let path = between::between(king, king_dst)
+        | between::between(rook, rook_dst)
+        | Bitboard::one(king_dst)
+        | Bitboard::one(rook_dst);
+    path & !Bitboard::one(king) & !Bitboard::one(rook)
+}
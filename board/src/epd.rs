@@ -0,0 +1,288 @@
+use crate::board::{Board, FenParseError};
+use crate::moves::{Move, SanParseError};
+use std::{collections::HashMap, str::FromStr};
+use thiserror::Error;
+
+/// Operations of an EPD record, such as `bm Qg6; id "WAC.001";`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpdOps {
+    ops: HashMap<String, Vec<String>>,
+    best_moves: Vec<Move>,
+    avoid_moves: Vec<Move>,
+}
+
+impl EpdOps {
+    /// Returns the operands of the operation. The quotes around string operands are removed.
+    pub fn get(&self, opcode: &str) -> Option<&[String]> {
+        self.ops.get(opcode).map(Vec::as_slice)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.ops.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Position identifier from the `id` operation.
+    pub fn id(&self) -> Option<&str> {
+        self.get("id")?.first().map(String::as_str)
+    }
+
+    /// Moves from the `bm` operation.
+    pub fn best_moves(&self) -> &[Move] {
+        &self.best_moves
+    }
+
+    /// Moves from the `am` operation.
+    pub fn avoid_moves(&self) -> &[Move] {
+        &self.avoid_moves
+    }
+}
+
+#[derive(Debug, Clone, Error, Eq, PartialEq)]
+pub enum EpdParseError {
+    #[error("bad position: {0}")]
+    Position(#[from] FenParseError),
+    #[error("bad opcode {0:?}")]
+    BadOpcode(String),
+    #[error("duplicate operation {0:?}")]
+    DuplicateOp(String),
+    #[error("unterminated string in operation {0:?}")]
+    UnterminatedString(String),
+    #[error("bad operand {operand:?} in operation {opcode:?}")]
+    BadOperand { opcode: String, operand: String },
+    #[error("bad move {operand:?} in operation {opcode:?}: {source}")]
+    BadMove {
+        opcode: String,
+        operand: String,
+        source: SanParseError,
+    },
+}
+
+fn is_delim(c: char) -> bool {
+    c.is_whitespace() || c == ';'
+}
+
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    s.split_at(s.find(char::is_whitespace).unwrap_or(s.len()))
+}
+
+fn is_opcode(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits the operations into opcodes and operands. The semicolon after the last operation may
+/// be omitted.
+fn parse_ops(s: &str) -> Result<Vec<(String, Vec<String>)>, EpdParseError> {
+    let mut res = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let (opcode, tail) = rest.split_at(rest.find(is_delim).unwrap_or(rest.len()));
+        if !is_opcode(opcode) {
+            return Err(EpdParseError::BadOpcode(opcode.to_string()));
+        }
+        rest = tail;
+        let mut operands = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(tail) = rest.strip_prefix(';') {
+                rest = tail;
+                break;
+            }
+            if rest.is_empty() {
+                break;
+            }
+            if let Some(tail) = rest.strip_prefix('"') {
+                let end = tail
+                    .find('"')
+                    .ok_or_else(|| EpdParseError::UnterminatedString(opcode.to_string()))?;
+                operands.push(tail[..end].to_string());
+                rest = &tail[end + 1..];
+            } else {
+                let (operand, tail) = rest.split_at(rest.find(is_delim).unwrap_or(rest.len()));
+                operands.push(operand.to_string());
+                rest = tail;
+            }
+        }
+        res.push((opcode.to_string(), operands));
+        rest = rest.trim_start();
+    }
+    Ok(res)
+}
+
+impl Board {
+    /// Parses an EPD record, which is the first four fields of FEN followed by the operations.
+    ///
+    /// The move counters are taken from the `hmvc` and `fmvn` operations. Records that carry
+    /// them as the fifth and sixth fields, like FEN, are also accepted. Missing counters default
+    /// to `0` and `1`. The operands of `bm` and `am` are parsed as SAN moves.
+    pub fn from_epd(s: &str) -> Result<(Board, EpdOps), EpdParseError> {
+        let mut fields = Vec::new();
+        let mut rest = s;
+        for _ in 0..4 {
+            let (field, tail) = split_word(rest);
+            if field.is_empty() {
+                break;
+            }
+            fields.push(field);
+            rest = tail;
+        }
+        let mut counters = ["0", "1"];
+        for counter in &mut counters {
+            let (word, tail) = split_word(rest);
+            if word.is_empty() || !word.bytes().all(|b| b.is_ascii_digit()) {
+                break;
+            }
+            *counter = word;
+            rest = tail;
+        }
+
+        let mut ops = EpdOps::default();
+        for (opcode, operands) in parse_ops(rest)? {
+            if ops.ops.contains_key(&opcode) {
+                return Err(EpdParseError::DuplicateOp(opcode));
+            }
+            ops.ops.insert(opcode, operands);
+        }
+        for (opcode, counter) in ["hmvc", "fmvn"].into_iter().zip(&mut counters) {
+            let Some(operands) = ops.get(opcode) else {
+                continue;
+            };
+            match operands {
+                [value] if u16::from_str(value).is_ok() => *counter = value,
+                _ => {
+                    return Err(EpdParseError::BadOperand {
+                        opcode: opcode.to_string(),
+                        operand: operands.join(" "),
+                    });
+                }
+            }
+        }
+        if fields.len() == 4 {
+            fields.extend(counters);
+        }
+        let board = Board::from_str(&fields.join(" "))?;
+
+        for (opcode, moves) in [("bm", &mut ops.best_moves), ("am", &mut ops.avoid_moves)] {
+            for operand in ops.ops.get(opcode).into_iter().flatten() {
+                let mv =
+                    Move::from_san(operand, &board).map_err(|source| EpdParseError::BadMove {
+                        opcode: opcode.to_string(),
+                        operand: operand.clone(),
+                        source,
+                    })?;
+                moves.push(mv);
+            }
+        }
+        Ok((board, ops))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::RawFenParseError;
+
+    #[test]
+    fn test_epd() {
+        let (b, ops) = Board::from_epd(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+        )
+        .unwrap();
+        assert_eq!(
+            b.to_string(),
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops.id(), Some("WAC.001"));
+        assert_eq!(ops.get("bm"), Some(&["Qg6".to_string()][..]));
+        assert_eq!(
+            ops.best_moves(),
+            &[Move::from_uci_legal("g3g6", &b).unwrap()]
+        );
+        assert!(ops.avoid_moves().is_empty());
+
+        // Several moves, strings with separators inside, and no final semicolon.
+        let (b, ops) = Board::from_epd(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - am e4 d4 ;c0 \"a; b\";noop",
+        )
+        .unwrap();
+        assert_eq!(ops.avoid_moves().len(), 2);
+        assert_eq!(
+            ops.avoid_moves()[1],
+            Move::from_uci_legal("d2d4", &b).unwrap()
+        );
+        assert_eq!(ops.get("c0"), Some(&["a; b".to_string()][..]));
+        assert_eq!(ops.get("noop"), Some(&[][..]));
+        assert_eq!(ops.id(), None);
+    }
+
+    #[test]
+    fn test_epd_counters() {
+        let fen = "4k3/8/8/8/8/8/8/4K2R w K - 12 40";
+        let (b, ops) = Board::from_epd(fen).unwrap();
+        assert_eq!(b.to_string(), fen);
+        assert!(ops.is_empty());
+
+        let (b, _) = Board::from_epd("4k3/8/8/8/8/8/8/4K2R w K - hmvc 12; fmvn 40;").unwrap();
+        assert_eq!(b.to_string(), fen);
+        let (b, ops) = Board::from_epd("4k3/8/8/8/8/8/8/4K2R w K - 12 bm O-O;").unwrap();
+        assert_eq!(b.to_string(), "4k3/8/8/8/8/8/8/4K2R w K - 12 1");
+        assert_eq!(ops.best_moves()[0].to_string(), "e1g1");
+    }
+
+    #[test]
+    fn test_epd_errors() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert_eq!(
+            Board::from_epd(&format!("{} bm e5;", start)),
+            Err(EpdParseError::BadMove {
+                opcode: "bm".into(),
+                operand: "e5".into(),
+                source: SanParseError::Illegal,
+            })
+        );
+        assert_eq!(
+            Board::from_epd(&format!("{} am Nf3 Nxe4;", start)),
+            Err(EpdParseError::BadMove {
+                opcode: "am".into(),
+                operand: "Nxe4".into(),
+                source: SanParseError::Illegal,
+            })
+        );
+        assert_eq!(
+            Board::from_epd(&format!("{} id \"x; bm e4;", start)),
+            Err(EpdParseError::UnterminatedString("id".into()))
+        );
+        assert_eq!(
+            Board::from_epd(&format!("{} bm e4; bm d4;", start)),
+            Err(EpdParseError::DuplicateOp("bm".into()))
+        );
+        assert_eq!(
+            Board::from_epd(&format!("{} 1bm e4;", start)),
+            Err(EpdParseError::BadOpcode("1bm".into()))
+        );
+        assert_eq!(
+            Board::from_epd(&format!("{} hmvc x;", start)),
+            Err(EpdParseError::BadOperand {
+                opcode: "hmvc".into(),
+                operand: "x".into(),
+            })
+        );
+        assert_eq!(
+            Board::from_epd("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w"),
+            Err(EpdParseError::Position(FenParseError::Fen(
+                RawFenParseError::NoCastling
+            )))
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use crate::board::Board;
+use crate::moves::{Move, RawUndo, ValidateError};
+
+/// A game as a start position plus a list of moves, with the board positioned at some ply.
+///
+/// Undos for the moves before the current ply are kept, so [`Game::goto`] only makes or unmakes
+/// the moves between the current and the requested ply instead of replaying the game from the
+/// start. This costs one `RawUndo` per ply, which is negligible even for very long games.
+#[derive(Debug, Clone)]
+pub struct Game {
+    start: Board,
+    moves: Vec<Move>,
+    undos: Vec<RawUndo>,
+    board: Board,
+}
+
+impl Game {
+    pub fn new(start: Board) -> Self {
+        Self {
+            board: start.clone(),
+            start,
+            moves: Vec::new(),
+            undos: Vec::new(),
+        }
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn ply(&self) -> usize {
+        self.undos.len()
+    }
+
+    /// Makes the move at the current ply, discarding all the moves after it.
+    pub fn push(&mut self, mv: Move) -> Result<(), ValidateError> {
+        mv.validate(&self.board)?;
+        self.moves.truncate(self.ply());
+        self.moves.push(mv);
+        self.undos
+            .push(unsafe { self.board.make_move_unchecked(mv) });
+        Ok(())
+    }
+
+    pub fn goto(&mut self, ply: usize) -> &Board {
+        assert!(ply <= self.moves.len(), "ply {} is out of range", ply);
+        while self.ply() > ply {
+            let u = self.undos.pop().unwrap();
+            let mv = self.moves[self.ply()];
+            unsafe { self.board.unmake_move_unchecked(mv, u) };
+        }
+        while self.ply() < ply {
+            let mv = self.moves[self.ply()];
+            self.undos
+                .push(unsafe { self.board.make_move_unchecked(mv) });
+        }
+        &self.board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goto() {
+        let mut game = Game::new(Board::start());
+        let mut fens = vec![game.board().to_string()];
+        for mv in ["e2e4", "c7c5", "g1f3", "d7d6", "d2d4", "c5d4", "f3d4"] {
+            let mv = Move::from_uci_legal(mv, game.board()).unwrap();
+            game.push(mv).unwrap();
+            fens.push(game.board().to_string());
+        }
+        assert_eq!(game.ply(), 7);
+
+        for ply in [3, 0, 7, 5, 6, 1, 4, 2, 7] {
+            assert_eq!(game.goto(ply).to_string(), fens[ply]);
+            assert_eq!(game.ply(), ply);
+        }
+        assert_eq!(game.board().zobrist_hash(), {
+            let mut b = game.start().clone();
+            for mv in game.moves() {
+                b.make_move(*mv).unwrap();
+            }
+            b.zobrist_hash()
+        });
+
+        game.goto(2);
+        let mv = Move::from_uci_legal("b1c3", game.board()).unwrap();
+        game.push(mv).unwrap();
+        assert_eq!(game.moves().len(), 3);
+        assert_eq!(game.goto(2).to_string(), fens[2]);
+        assert!(game.push(Move::NULL).is_err());
+        assert_eq!(game.moves().len(), 3);
+    }
+}
@@ -2,21 +2,26 @@
 
 pub use pawnyowl_base::{bitboard, core, geometry};
 
+pub mod attack;
 pub mod board;
 pub mod diff;
+pub mod epd;
+pub mod game;
 pub mod movegen;
 pub mod moves;
+pub mod pawns;
+pub mod perft;
+pub mod see;
 pub mod selftest;
 
-mod attack;
 mod between;
 mod castling;
 mod generic;
-mod pawns;
 mod zobrist;
 
 pub use bitboard::Bitboard;
-pub use board::{Board, RawBoard};
+pub use board::{Board, CheckInfo, GameStatus, PositionInfo, RawBoard};
 pub use core::{CastlingRights, Cell, Color, File, Piece, Rank, Sq};
-pub use movegen::{MoveGen, MoveList, MovePush};
+pub use game::Game;
+pub use movegen::{FnMovePush, MoveGen, MoveList, MovePush};
 pub use moves::{Move, MoveKind};
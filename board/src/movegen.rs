@@ -20,12 +20,20 @@ pub fn is_square_attacked(b: &Board, s: Sq, c: Color) -> bool {
 
 #[inline]
 pub fn square_attackers(b: &Board, s: Sq, c: Color) -> Bitboard {
-    let all = b.all();
-    (b.piece(c, Piece::Pawn) & attack::pawn(c.inv(), s))
-        | (b.piece(c, Piece::King) & attack::king(s))
-        | (b.piece(c, Piece::Knight) & attack::knight(s))
-        | (attack::bishop(s, all) & b.piece_diag(c))
-        | (attack::rook(s, all) & b.piece_line(c))
+    b.attackers_to(s, c, b.all())
+}
+
+// Checks everything except that the king is not in check and that the king's destination is not
+// attacked. The latter is left for the legality check, as the rook may shield it before castling.
+pub(crate) fn can_castle_960(b: &Board, c: Color, side: CastlingSide) -> bool {
+    if !b.r.castling.has(c, side) {
+        return false;
+    }
+    let (king, rook) = (b.king_pos(c), b.r.castling_rook(c, side));
+    (castling::pass_960(king, rook, c, side) & b.all()).is_empty()
+        && between::between(king, castling::king_dst(c, side))
+            .into_iter()
+            .all(|s| !is_square_attacked(b, s, c.inv()))
 }
 
 pub trait MovePush {
@@ -36,7 +44,8 @@ const GEN_SIMPLE: usize = 1 << 0;
 const GEN_CAPTURE: usize = 1 << 1;
 const GEN_SIMPLE_PROMOTE: usize = 1 << 2;
 const GEN_CASTLING: usize = 1 << 3;
-const GEN_MAX: usize = 1 << 4;
+const GEN_NO_UNDERPROMOTE: usize = 1 << 4;
+const GEN_ALL: usize = GEN_SIMPLE | GEN_CAPTURE | GEN_SIMPLE_PROMOTE | GEN_CASTLING;
 
 #[inline]
 fn has_bit(mask: usize, bit: usize) -> bool {
@@ -66,6 +75,27 @@ impl MovePush for Vec<Move> {
     }
 }
 
+/// Move sink which calls the closure on each move.
+pub struct FnMovePush<F>(pub F);
+
+impl<F: FnMut(Move)> MovePush for FnMovePush<F> {
+    #[inline]
+    fn push(&mut self, m: Move) {
+        (self.0)(m);
+    }
+}
+
+/// Move sink which only counts the moves pushed into it.
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MoveCounter(pub usize);
+
+impl MovePush for MoveCounter {
+    #[inline]
+    fn push(&mut self, _: Move) {
+        self.0 += 1;
+    }
+}
+
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct UncheckedMoveList<const N: usize>(ArrayVec<Move, N>);
 
@@ -109,10 +139,61 @@ impl<const N: usize> MovePush for UncheckedMoveList<N> {
     }
 }
 
+struct LegalFilter<'a, P> {
+    b: &'a Board,
+    king: Sq,
+    pinned: Bitboard,
+    inner: &'a mut P,
+}
+
+impl<P: MovePush> MovePush for LegalFilter<'_, P> {
+    #[inline]
+    fn push(&mut self, m: Move) {
+        let legal = if m.src() == self.king
+            || matches!(
+                m.kind(),
+                MoveKind::Enpassant | MoveKind::CastlingKingside | MoveKind::CastlingQueenside
+            ) {
+            unsafe { m.is_legal_unchecked(self.b) }
+        } else {
+            // Non-king moves already respect the check mask, so only the pins remain.
+            !self.pinned.has(m.src())
+                || between::between(self.king, m.dst()).has(m.src())
+                || between::between(self.king, m.src()).has(m.dst())
+        };
+        if legal {
+            self.inner.push(m);
+        }
+    }
+}
+
+struct EvasionFilter<'a, P> {
+    b: &'a Board,
+    king: Sq,
+    pinned: Bitboard,
+    inner: &'a mut P,
+}
+
+impl<P: MovePush> MovePush for EvasionFilter<'_, P> {
+    #[inline]
+    fn push(&mut self, m: Move) {
+        let legal = if m.src() == self.king || m.kind() == MoveKind::Enpassant {
+            unsafe { m.is_legal_unchecked(self.b) }
+        } else {
+            // A pinned piece cannot leave the pin line, and a move along it never stops the check.
+            !self.pinned.has(m.src())
+        };
+        if legal {
+            self.inner.push(m);
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct MoveGenCtx {
     check_mask: Bitboard,
     check: CheckKind,
+    pinned: Bitboard,
     hash: u64,
 }
 
@@ -120,19 +201,21 @@ impl From<&Board> for MoveGenCtx {
     #[inline]
     fn from(b: &Board) -> Self {
         let king = b.king_pos(b.side());
-        let king_attackers = b.checkers();
-        let (check, check_mask) = match king_attackers.len() {
+        let info = b.checker_info();
+        let (check, check_mask) = match info.count() {
             0 => (CheckKind::None, Bitboard::FULL),
-            1 => {
-                let checker = king_attackers.first().unwrap();
-                let check_mask = between::between(checker, king) | king_attackers;
+            1 if info.slider => {
+                let checker = info.checkers.first().unwrap();
+                let check_mask = between::between(checker, king) | info.checkers;
                 (CheckKind::Single, check_mask)
             }
+            1 => (CheckKind::Single, info.checkers),
             _ => (CheckKind::Double, Bitboard::EMPTY),
         };
         Self {
             check_mask,
             check,
+            pinned: b.pinned(),
             hash: b.zobrist_hash(),
         }
     }
@@ -160,6 +243,11 @@ impl<'a> MoveGen<'a> {
         &self.c
     }
 
+    #[inline]
+    pub fn pinned(&self) -> Bitboard {
+        self.c.pinned
+    }
+
     #[inline(never)]
     fn do_gen2<C: generic::Color, const MASK: usize>(&self, p: &mut impl MovePush) {
         const PROMOTES: [MoveKind; 4] = [
@@ -168,7 +256,13 @@ impl<'a> MoveGen<'a> {
             MoveKind::PromoteRook,
             MoveKind::PromoteQueen,
         ];
+        const PROMOTES_REDUCED: [MoveKind; 2] = [MoveKind::PromoteKnight, MoveKind::PromoteQueen];
 
+        let promotes: &[MoveKind] = if has_bit(MASK, GEN_NO_UNDERPROMOTE) {
+            &PROMOTES_REDUCED
+        } else {
+            &PROMOTES
+        };
         let b = self.b;
         let c = C::COLOR;
         let all = b.all();
@@ -248,7 +342,7 @@ impl<'a> MoveGen<'a> {
                 if has_bit(MASK, GEN_SIMPLE_PROMOTE) {
                     // Simple promote
                     for d in pawns::advance_forward(c, pawn & promote) & !all & dst_mask {
-                        for pr in PROMOTES {
+                        for &pr in promotes {
                             p.push(unsafe { Move::new_unchecked(pr, d.add_unchecked(df), d) });
                         }
                     }
@@ -281,12 +375,12 @@ impl<'a> MoveGen<'a> {
                 {
                     let pawn = pawn & promote;
                     for d in pawns::advance_left(c, pawn) & dst_mask {
-                        for pr in PROMOTES {
+                        for &pr in promotes {
                             p.push(unsafe { Move::new_unchecked(pr, d.add_unchecked(dl), d) });
                         }
                     }
                     for d in pawns::advance_right(c, pawn) & dst_mask {
-                        for pr in PROMOTES {
+                        for &pr in promotes {
                             p.push(unsafe { Move::new_unchecked(pr, d.add_unchecked(dr), d) });
                         }
                     }
@@ -308,8 +402,13 @@ impl<'a> MoveGen<'a> {
             }
         }
 
+        if has_bit(MASK, GEN_CASTLING) && self.c.check == CheckKind::None && b.r.chess960 {
+            self.gen_castling_960(c, p);
+        }
+
         if has_bit(MASK, GEN_CASTLING)
             && self.c.check == CheckKind::None
+            && !b.r.chess960
             && b.r.castling.has_color(c)
         {
             let rank = geometry::castling_rank(c);
@@ -338,6 +437,16 @@ impl<'a> MoveGen<'a> {
         }
     }
 
+    fn gen_castling_960(&self, c: Color, p: &mut impl MovePush) {
+        let b = self.b;
+        for side in CastlingSide::iter() {
+            if can_castle_960(b, c, side) {
+                let (src, dst) = (b.king_pos(c), b.r.castling_rook(c, side));
+                p.push(unsafe { Move::new_unchecked(MoveKind::from(side), src, dst) });
+            }
+        }
+    }
+
     #[inline]
     fn do_gen<const MASK: usize>(&self, p: &mut impl MovePush) {
         match self.b.side() {
@@ -346,9 +455,85 @@ impl<'a> MoveGen<'a> {
         }
     }
 
+    pub fn has_legal_move(&self) -> bool {
+        let b = self.b;
+        let c = b.side();
+
+        // King moves go first, as they are often the only way to escape from check.
+        let king = b.king_pos(c);
+        for d in attack::king(king) & !b.color(c) {
+            if unsafe { Move::new_unchecked(MoveKind::Simple, king, d).is_legal_unchecked(b) } {
+                return true;
+            }
+        }
+        if self.c.check == CheckKind::Double {
+            return false;
+        }
+
+        let mut moves = MoveList::new();
+        self.gen_capture(&mut moves);
+        if moves.iter().any(|mv| unsafe { mv.is_legal_unchecked(b) }) {
+            return true;
+        }
+        moves.clear();
+        self.gen_simple(&mut moves);
+        moves.iter().any(|mv| unsafe { mv.is_legal_unchecked(b) })
+    }
+
     #[inline]
     pub fn gen_all(&self, p: &mut impl MovePush) {
-        self.do_gen::<{ GEN_MAX - 1 }>(p)
+        self.do_gen::<{ GEN_ALL }>(p)
+    }
+
+    pub fn gen_legal(&self, p: &mut impl MovePush) {
+        let b = self.b;
+        let mut filter = LegalFilter {
+            b,
+            king: b.king_pos(b.side()),
+            pinned: self.c.pinned,
+            inner: p,
+        };
+        self.gen_all(&mut filter)
+    }
+
+    /// Returns the number of legal moves without storing them.
+    pub fn count_legal(&self) -> usize {
+        let mut counter = MoveCounter::default();
+        self.gen_legal(&mut counter);
+        counter.0
+    }
+
+    /// Generates the legal moves which escape from check. In double check, only the king moves
+    /// are considered. Must be called only if the side to move is in check; otherwise, nothing is
+    /// generated.
+    pub fn gen_evasions(&self, p: &mut impl MovePush) {
+        let b = self.b;
+        let c = b.side();
+        let king = b.king_pos(c);
+        let mut filter = EvasionFilter {
+            b,
+            king,
+            pinned: self.c.pinned,
+            inner: p,
+        };
+        match self.c.check {
+            CheckKind::None => {}
+            CheckKind::Single => {
+                self.do_gen::<{ GEN_SIMPLE | GEN_CAPTURE | GEN_SIMPLE_PROMOTE }>(&mut filter)
+            }
+            CheckKind::Double => {
+                for d in attack::king(king) & !b.color(c) {
+                    filter.push(unsafe { Move::new_unchecked(MoveKind::Simple, king, d) });
+                }
+            }
+        }
+    }
+
+    /// Same as `gen_all()`, but skips bishop and rook promotions. Note that the resulting move
+    /// list is incomplete, so it must not be used for perft or legal move enumeration.
+    #[inline]
+    pub fn gen_all_no_underpromotions(&self, p: &mut impl MovePush) {
+        self.do_gen::<{ GEN_ALL | GEN_NO_UNDERPROMOTE }>(p)
     }
 
     #[inline]
@@ -429,4 +614,186 @@ mod tests {
             Bitboard::EMPTY.with2(File::E, Rank::R5),
         );
     }
+
+    #[test]
+    fn test_has_legal_move() {
+        for (fen, res) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                true,
+            ),
+            ("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 1 1", false),
+            ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", false),
+            ("7k/5Q2/6K1/8/8/8/7p/8 b - - 0 1", true),
+            ("k7/P7/K7/8/8/8/8/8 b - - 0 1", false),
+            ("k7/P7/K7/8/8/8/1p6/8 b - - 0 1", true),
+            ("1k6/8/1K6/8/8/8/8/1R6 b - - 0 1", true),
+            ("4k3/8/8/8/8/8/3n4/r3K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/3p1np1/8/r3K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/3n4/r3KQ2 w - - 0 1", true),
+            ("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1", true),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mut moves = MoveList::new();
+            MoveGen::new(&b).gen_all(&mut moves);
+            let expected = moves.iter().any(|mv| unsafe { mv.is_legal_unchecked(&b) });
+            assert_eq!(expected, res, "{}", fen);
+            assert_eq!(MoveGen::new(&b).has_legal_move(), res, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_pinned() {
+        let b = Board::from_str("4k3/4r3/8/b7/8/2P5/4R3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            MoveGen::new(&b).pinned(),
+            Bitboard::EMPTY
+                .with2(File::C, Rank::R3)
+                .with2(File::E, Rank::R2)
+        );
+        let b = Board::from_str("4k3/4r3/8/b7/8/2P5/4R3/4K3 b - - 0 1").unwrap();
+        assert_eq!(
+            MoveGen::new(&b).pinned(),
+            Bitboard::EMPTY.with2(File::E, Rank::R7)
+        );
+        let b = Board::from_str("4k3/4r3/8/b7/8/2P5/3NR3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            MoveGen::new(&b).pinned(),
+            Bitboard::EMPTY.with2(File::E, Rank::R2)
+        );
+    }
+
+    #[test]
+    fn test_gen_legal() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/4r3/8/2n5/8/8/4R3/4K3 w - - 0 1",
+            "4k3/8/3q4/2b5/3N4/8/8/r3K3 w - - 0 1",
+            "8/8/8/KPp4r/8/8/8/6k1 w - c6 0 1",
+            "4k3/8/8/b7/8/8/3N4/r3K2R w K - 0 1",
+            "4k3/8/8/8/8/5n2/3Q4/r3K3 w - - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            "4k3/8/8/b7/8/2P5/8/4K1r1 w - - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mut expected = MoveList::new();
+            MoveGen::new(&b).gen_all(&mut expected);
+            expected.retain(|m| unsafe { m.is_legal_unchecked(&b) });
+            let mut moves = MoveList::new();
+            MoveGen::new(&b).gen_legal(&mut moves);
+            assert_eq!(moves, expected, "{}", fen);
+            assert_eq!(MoveGen::new(&b).count_legal(), moves.len(), "{}", fen);
+
+            let mut vec = Vec::new();
+            MoveGen::new(&b).gen_legal(&mut vec);
+            assert_eq!(vec[..], moves[..], "{}", fen);
+            let mut pushed = Vec::new();
+            MoveGen::new(&b).gen_legal(&mut FnMovePush(|m| pushed.push(m)));
+            assert_eq!(pushed, vec, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_gen_evasions() {
+        let check = |b: &Board| {
+            let mut expected: Vec<_> = Vec::new();
+            MoveGen::new(b).gen_legal(&mut expected);
+            let mut moves: Vec<_> = Vec::new();
+            MoveGen::new(b).gen_evasions(&mut moves);
+            let key = |m: &Move| (m.src().index(), m.dst().index(), m.kind() as u8);
+            expected.sort_by_key(key);
+            moves.sort_by_key(key);
+            assert_eq!(moves, expected, "{}", b);
+        };
+
+        for fen in [
+            // Block, capture, and king moves.
+            "4k3/4r3/8/2n5/8/8/3P1B2/4K3 w - - 0 1",
+            // Pinned pieces cannot help.
+            "4k3/8/8/b7/8/2P5/3N4/r3K3 w - - 0 1",
+            // Double check.
+            "4k3/8/8/8/8/5n2/3Q4/r3K3 w - - 0 1",
+            // The checking pawn is captured en passant.
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            // Promotion blocks and captures.
+            "1r5k/P7/8/8/8/8/8/1K4r1 w - - 0 1",
+            // Checkmate.
+            "4k3/8/8/8/8/8/5PPP/3r2K1 w - - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert!(b.is_check(), "{}", fen);
+            check(&b);
+        }
+
+        // Follow pseudo-random games and check every position in check.
+        for seed in 0..20_u64 {
+            let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+            let mut b = Board::start();
+            for _ in 0..200 {
+                if b.is_check() {
+                    check(&b);
+                }
+                let mut moves = MoveList::new();
+                MoveGen::new(&b).gen_legal(&mut moves);
+                if moves.is_empty() {
+                    break;
+                }
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                b.make_move(moves[(state % moves.len() as u64) as usize])
+                    .unwrap();
+            }
+        }
+
+        let b = Board::start();
+        let mut moves = MoveList::new();
+        MoveGen::new(&b).gen_evasions(&mut moves);
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn test_no_underpromotions() {
+        let b = Board::from_str("1r5k/2P5/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let promotes = |moves: &MoveList| {
+            let mut res: Vec<_> = moves
+                .iter()
+                .filter_map(|m| m.kind().promote().map(|p| (m.dst(), p)))
+                .collect();
+            res.sort_by_key(|(s, p)| (s.index(), p.index()));
+            res
+        };
+
+        let mut full = MoveList::new();
+        MoveGen::new(&b).gen_all(&mut full);
+        let mut reduced = MoveList::new();
+        MoveGen::new(&b).gen_all_no_underpromotions(&mut reduced);
+
+        let (b8, c8) = (Sq::make(File::B, Rank::R8), Sq::make(File::C, Rank::R8));
+        assert_eq!(
+            promotes(&full),
+            vec![
+                (b8, Piece::Knight),
+                (b8, Piece::Bishop),
+                (b8, Piece::Rook),
+                (b8, Piece::Queen),
+                (c8, Piece::Knight),
+                (c8, Piece::Bishop),
+                (c8, Piece::Rook),
+                (c8, Piece::Queen),
+            ]
+        );
+        assert_eq!(
+            promotes(&reduced),
+            vec![
+                (b8, Piece::Knight),
+                (b8, Piece::Queen),
+                (c8, Piece::Knight),
+                (c8, Piece::Queen),
+            ]
+        );
+        assert_eq!(full.len() - reduced.len(), 4);
+    }
 }
@@ -8,6 +8,7 @@ use std::{fmt, hint};
 use thiserror::Error;
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum MoveKind {
     #[default]
@@ -109,7 +110,14 @@ impl MoveKind {
     }
 }
 
+/// With the `serde` feature, `Move` serializes structurally as its kind, source and
+/// destination. UCI strings are not used, as parsing them back requires a board.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "MoveRepr", try_from = "MoveRepr")
+)]
 pub struct Move {
     kind: MoveKind,
     src: Sq,
@@ -117,6 +125,34 @@ pub struct Move {
     unused: u8,
 }
 
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MoveRepr {
+    kind: MoveKind,
+    src: Sq,
+    dst: Sq,
+}
+
+#[cfg(feature = "serde")]
+impl From<Move> for MoveRepr {
+    fn from(m: Move) -> Self {
+        MoveRepr {
+            kind: m.kind,
+            src: m.src,
+            dst: m.dst,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<MoveRepr> for Move {
+    type Error = ValidateError;
+
+    fn try_from(m: MoveRepr) -> Result<Self, Self::Error> {
+        Move::new(m.kind, m.src, m.dst)
+    }
+}
+
 #[derive(Debug, Clone, Error, Eq, PartialEq)]
 pub enum ValidateError {
     #[error("move is not well-formed")]
@@ -127,15 +163,45 @@ pub enum ValidateError {
     NotLegal,
 }
 
+/// Move packed into 16 bits: four bits for the kind, then six bits for the source and six bits
+/// for the destination. Every well-formed move, including [`Move::NULL`], survives packing and
+/// unpacking unchanged.
+///
+/// With the `serde` feature, `PackedMove` serializes as its `u16` value.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "u16", try_from = "u16")
+)]
 pub struct PackedMove(u16);
 
 impl PackedMove {
+    pub const NULL: PackedMove = PackedMove(0);
+
     pub fn value(self) -> u16 {
         self.0
     }
 }
 
+impl From<PackedMove> for u16 {
+    fn from(m: PackedMove) -> Self {
+        m.0
+    }
+}
+
+impl TryFrom<u16> for PackedMove {
+    type Error = ValidateError;
+
+    fn try_from(val: u16) -> Result<Self, Self::Error> {
+        if (val >> 12) as usize >= MoveKind::COUNT || !Move::from(PackedMove(val)).is_well_formed()
+        {
+            return Err(ValidateError::NotWellFormed);
+        }
+        Ok(PackedMove(val))
+    }
+}
+
 impl From<Move> for PackedMove {
     fn from(m: Move) -> Self {
         let val = (m.kind.index() << 12) | (m.src.index() << 6) | m.dst.index();
@@ -196,8 +262,7 @@ impl Move {
 
     #[inline]
     pub fn from_uci(s: &str, b: &Board) -> Result<Self, UciParseError> {
-        let u = UciMove::from_str(s)?;
-        Ok(u.into_move(b)?)
+        UciMove::from_str(s)?.into_move(b)
     }
 
     #[inline]
@@ -264,13 +329,19 @@ impl Move {
 
         match self.kind {
             MoveKind::Simple => true,
-            MoveKind::CastlingKingside => [Color::White, Color::Black].into_iter().any(|c| {
+            // Chess960 castling is encoded as the king capturing its own rook, so only the
+            // direction of the move can be checked here.
+            MoveKind::CastlingKingside => Color::iter().any(|c| {
                 let rank = geometry::castling_rank(c);
-                self.src == Sq::make(File::E, rank) && self.dst == Sq::make(File::G, rank)
+                self.src.rank() == rank
+                    && self.dst.rank() == rank
+                    && self.src.file() < self.dst.file()
             }),
-            MoveKind::CastlingQueenside => [Color::White, Color::Black].into_iter().any(|c| {
+            MoveKind::CastlingQueenside => Color::iter().any(|c| {
                 let rank = geometry::castling_rank(c);
-                self.src == Sq::make(File::E, rank) && self.dst == Sq::make(File::C, rank)
+                self.src.rank() == rank
+                    && self.dst.rank() == rank
+                    && self.src.file() > self.dst.file()
             }),
             MoveKind::PawnSimple => {
                 self.src.file().index().abs_diff(self.dst.file().index()) <= 1
@@ -280,14 +351,14 @@ impl Move {
             }
             MoveKind::PawnDouble => {
                 self.src.file() == self.dst.file()
-                    && [Color::White, Color::Black].into_iter().any(|c| {
+                    && Color::iter().any(|c| {
                         self.src.rank() == geometry::double_move_src_rank(c)
                             && self.dst.rank() == geometry::double_move_dst_rank(c)
                     })
             }
             MoveKind::Enpassant => {
                 self.src.file().index().abs_diff(self.dst.file().index()) == 1
-                    && [Color::White, Color::Black].into_iter().any(|c| {
+                    && Color::iter().any(|c| {
                         self.src.rank() == geometry::ep_src_rank(c)
                             && self.dst.rank() == geometry::ep_dst_rank(c)
                     })
@@ -297,7 +368,7 @@ impl Move {
             | MoveKind::PromoteRook
             | MoveKind::PromoteQueen => {
                 self.src.file().index().abs_diff(self.dst.file().index()) <= 1
-                    && [Color::White, Color::Black].into_iter().any(|c| {
+                    && Color::iter().any(|c| {
                         self.src.rank() == geometry::promote_src_rank(c)
                             && self.dst.rank() == geometry::promote_dst_rank(c)
                     })
@@ -320,6 +391,110 @@ impl Move {
     pub const fn dst(self) -> Sq {
         self.dst
     }
+
+    /// Returns the piece captured by the move. The move must be semi-legal.
+    ///
+    /// En passant captures a pawn, though the destination square is empty. In Chess960, the king
+    /// may castle onto its own rook, which is not a capture.
+    #[inline]
+    pub fn captured(self, b: &Board) -> Option<Piece> {
+        match self.kind {
+            MoveKind::Enpassant => Some(Piece::Pawn),
+            MoveKind::Null | MoveKind::CastlingKingside | MoveKind::CastlingQueenside => None,
+            _ => b.get(self.dst).piece(),
+        }
+    }
+
+    #[inline]
+    pub fn is_capture(self, b: &Board) -> bool {
+        match self.kind {
+            MoveKind::Enpassant => true,
+            MoveKind::Null | MoveKind::CastlingKingside | MoveKind::CastlingQueenside => false,
+            _ => b.get(self.dst) != Cell::None,
+        }
+    }
+
+    /// Returns `true` if the move is neither a capture nor a promotion.
+    #[inline]
+    pub fn is_quiet(self, b: &Board) -> bool {
+        self.kind.promote().is_none() && !self.is_capture(b)
+    }
+
+    pub fn from_san(s: &str, b: &Board) -> Result<Move, SanParseError> {
+        let san = SanMove::from_str(s)?;
+        let mut moves = movegen::MoveList::new();
+        movegen::MoveGen::new(b).gen_all(&mut moves);
+        let mut found = None;
+        for mv in moves {
+            if !san.matches(mv, b) || !unsafe { mv.is_legal_unchecked(b) } {
+                continue;
+            }
+            if found.is_some() {
+                return Err(SanParseError::Ambiguous);
+            }
+            found = Some(mv);
+        }
+        found.ok_or(SanParseError::Illegal)
+    }
+
+    pub fn to_san(self, b: &Board) -> String {
+        let mut res = String::new();
+        match self.kind {
+            MoveKind::Null => return "--".into(),
+            MoveKind::CastlingKingside => res.push_str("O-O"),
+            MoveKind::CastlingQueenside => res.push_str("O-O-O"),
+            _ => {
+                let src_cell = b.get(self.src);
+                let piece = src_cell.piece().unwrap();
+                let capture = self.kind == MoveKind::Enpassant || b.get(self.dst) != Cell::None;
+                if piece == Piece::Pawn {
+                    if capture {
+                        res.push(self.src.file().as_char());
+                    }
+                } else {
+                    res.push(Cell::make(Color::White, piece).as_char());
+                    let mut moves = movegen::MoveList::new();
+                    movegen::MoveGen::new(b).gen_all(&mut moves);
+                    let (mut ambiguous, mut same_file, mut same_rank) = (false, false, false);
+                    for mv in moves {
+                        if mv.dst == self.dst
+                            && mv.src != self.src
+                            && b.get(mv.src) == src_cell
+                            && unsafe { mv.is_legal_unchecked(b) }
+                        {
+                            ambiguous = true;
+                            same_file |= mv.src.file() == self.src.file();
+                            same_rank |= mv.src.rank() == self.src.rank();
+                        }
+                    }
+                    if ambiguous {
+                        if !same_file {
+                            res.push(self.src.file().as_char());
+                        } else if !same_rank {
+                            res.push(self.src.rank().as_char());
+                        } else {
+                            res.push_str(&self.src.to_string());
+                        }
+                    }
+                }
+                if capture {
+                    res.push('x');
+                }
+                res.push_str(&self.dst.to_string());
+                if let Some(p) = self.kind.promote() {
+                    res.push('=');
+                    res.push(Cell::make(Color::White, p).as_char());
+                }
+            }
+        }
+
+        let mut after = b.clone();
+        after.make_move(self).expect("move must be legal");
+        if after.is_check() {
+            res.push(if after.has_legal_move() { '+' } else { '#' });
+        }
+        res
+    }
 }
 
 impl Default for Move {
@@ -359,19 +534,60 @@ pub struct RawUndo {
     move_counter: u16,
 }
 
+impl RawUndo {
+    /// Cell on the destination square before the move.
+    #[inline]
+    pub fn dst_cell(&self) -> Cell {
+        self.dst_cell
+    }
+
+    /// Cell captured by the move `mv` this undo was returned for. Unlike [`RawUndo::dst_cell`],
+    /// it is the pawn for en passant, and nothing for castling.
+    #[inline]
+    pub fn captured_cell(&self, mv: Move) -> Cell {
+        match mv.kind {
+            MoveKind::Enpassant => match self.ep_src {
+                Some(ep) if ep.rank() == geometry::ep_src_rank(Color::White) => {
+                    Cell::make(Color::Black, Piece::Pawn)
+                }
+                Some(_) => Cell::make(Color::White, Piece::Pawn),
+                None => Cell::None,
+            },
+            MoveKind::CastlingKingside | MoveKind::CastlingQueenside => Cell::None,
+            _ => self.dst_cell,
+        }
+    }
+
+    #[inline]
+    pub fn prev_castling(&self) -> CastlingRights {
+        self.castling
+    }
+
+    /// Pawn which could be captured en passant before the move.
+    #[inline]
+    pub fn prev_ep(&self) -> Option<Sq> {
+        self.ep_src
+    }
+
+    #[inline]
+    pub fn prev_move_counter(&self) -> u16 {
+        self.move_counter
+    }
+}
+
 fn update_castling(b: &mut Board, change: Bitboard) {
-    if (change & castling::ALL_SRCS).is_empty() {
+    if !b.r.chess960 && (change & castling::ALL_SRCS).is_empty() {
         return;
     }
 
     let mut castling = b.r.castling;
-    for (c, s) in [
-        (Color::White, CastlingSide::Queen),
-        (Color::White, CastlingSide::King),
-        (Color::Black, CastlingSide::Queen),
-        (Color::Black, CastlingSide::King),
-    ] {
-        if (change & castling::srcs(c, s)).is_nonempty() {
+    for (c, s) in b.r.castling.iter() {
+        let srcs = if b.r.chess960 {
+            Bitboard::one(b.king_pos(c)) | Bitboard::one(b.r.castling_rook(c, s))
+        } else {
+            castling::srcs(c, s)
+        };
+        if (change & srcs).is_nonempty() {
             castling.unset(c, s);
         }
     }
@@ -482,6 +698,41 @@ fn do_make_castling_queenside(b: &mut Board, c: Color, inv: bool) {
     }
 }
 
+#[inline(always)]
+fn do_make_castling_960(b: &mut Board, mv: Move, c: Color, inv: bool) {
+    let side = CastlingSide::try_from(mv.kind).unwrap();
+    let king = Cell::make(c, Piece::King);
+    let rook = Cell::make(c, Piece::Rook);
+    let (king_dst, rook_dst) = (castling::king_dst(c, side), castling::rook_dst(c, side));
+    // The king or the rook may stay in place, or land on the other one's source square, so
+    // clear the sources first and then fill the destinations.
+    if inv {
+        b.r.put(king_dst, Cell::None);
+        b.r.put(rook_dst, Cell::None);
+        b.r.put(mv.src, king);
+        b.r.put(mv.dst, rook);
+    } else {
+        b.r.put(mv.src, Cell::None);
+        b.r.put(mv.dst, Cell::None);
+        b.r.put(king_dst, king);
+        b.r.put(rook_dst, rook);
+        b.hash ^= zobrist::squares(king, mv.src)
+            ^ zobrist::squares(king, king_dst)
+            ^ zobrist::squares(rook, mv.dst)
+            ^ zobrist::squares(rook, rook_dst);
+    }
+    let king_change = Bitboard::one(mv.src) ^ Bitboard::one(king_dst);
+    let rook_change = Bitboard::one(mv.dst) ^ Bitboard::one(rook_dst);
+    *b.color_mut(c) ^= king_change ^ rook_change;
+    *b.cell_mut(king) ^= king_change;
+    *b.cell_mut(rook) ^= rook_change;
+    if !inv {
+        b.hash ^= zobrist::castling(b.r.castling);
+        b.r.castling.unset_color(c);
+        b.hash ^= zobrist::castling(b.r.castling);
+    }
+}
+
 #[inline(never)]
 fn do_make_move<C: generic::Color>(b: &mut Board, mv: Move) -> RawUndo {
     let c = C::COLOR;
@@ -537,6 +788,9 @@ fn do_make_move<C: generic::Color>(b: &mut Board, mv: Move) -> RawUndo {
             *b.cell_mut(dst_cell) &= !dst;
             update_castling(b, change);
         }
+        MoveKind::CastlingKingside | MoveKind::CastlingQueenside if b.r.chess960 => {
+            do_make_castling_960(b, mv, c, false);
+        }
         MoveKind::CastlingKingside => {
             do_make_castling_kingside(b, c, false);
         }
@@ -551,7 +805,12 @@ fn do_make_move<C: generic::Color>(b: &mut Board, mv: Move) -> RawUndo {
         }
     }
 
-    if dst_cell != Cell::None || src_cell == pawn {
+    let irreversible = match mv.kind {
+        // In Chess960, the castling move's destination holds our own rook.
+        MoveKind::Null | MoveKind::CastlingKingside | MoveKind::CastlingQueenside => false,
+        _ => dst_cell != Cell::None || src_cell == pawn,
+    };
+    if irreversible {
         b.r.move_counter = 0;
     } else {
         b.r.move_counter += 1;
@@ -569,12 +828,20 @@ fn do_make_move<C: generic::Color>(b: &mut Board, mv: Move) -> RawUndo {
 
 #[inline]
 pub(crate) unsafe fn make_move_unchecked(b: &mut Board, mv: Move) -> RawUndo {
+    if let Some(history) = &mut b.history {
+        history.push(b.hash);
+    }
     match b.r.side {
         Color::White => do_make_move::<generic::White>(b, mv),
         Color::Black => do_make_move::<generic::Black>(b, mv),
     }
 }
 
+#[inline]
+pub(crate) unsafe fn make_null_move_unchecked(b: &mut Board) -> RawUndo {
+    unsafe { make_move_unchecked(b, Move::NULL) }
+}
+
 #[inline(never)]
 fn do_unmake_move<C: generic::Color>(b: &mut Board, mv: Move, u: RawUndo) {
     let c = C::COLOR;
@@ -613,6 +880,9 @@ fn do_unmake_move<C: generic::Color>(b: &mut Board, mv: Move, u: RawUndo) {
                 *b.cell_mut(dst_cell) |= dst;
             }
         }
+        MoveKind::CastlingKingside | MoveKind::CastlingQueenside if b.r.chess960 => {
+            do_make_castling_960(b, mv, c, true);
+        }
         MoveKind::CastlingKingside => {
             do_make_castling_kingside(b, c, true);
         }
@@ -644,6 +914,14 @@ pub(crate) unsafe fn unmake_move_unchecked(b: &mut Board, mv: Move, u: RawUndo)
         Color::White => do_unmake_move::<generic::Black>(b, mv, u),
         Color::Black => do_unmake_move::<generic::White>(b, mv, u),
     }
+    if let Some(history) = &mut b.history {
+        history.pop();
+    }
+}
+
+#[inline]
+pub(crate) unsafe fn unmake_null_move_unchecked(b: &mut Board, u: RawUndo) {
+    unsafe { unmake_move_unchecked(b, Move::NULL, u) }
 }
 
 #[inline(always)]
@@ -724,15 +1002,24 @@ fn do_is_move_semilegal<C: generic::Color>(b: &Board, mv: Move) -> bool {
                 Some(Piece::Pawn) | None => unreachable!(),
             }
         }
+        MoveKind::CastlingKingside | MoveKind::CastlingQueenside if b.r.chess960 => {
+            let side = CastlingSide::try_from(mv.kind).unwrap();
+            mv.src == b.king_pos(c)
+                && mv.dst == b.r.castling_rook(c, side)
+                && !movegen::is_square_attacked(b, mv.src, c.inv())
+                && movegen::can_castle_960(b, c, side)
+        }
         MoveKind::CastlingKingside => {
-            mv.src.rank() == geometry::castling_rank(c)
+            mv.src == Sq::make(File::E, geometry::castling_rank(c))
+                && mv.dst == castling::king_dst(c, CastlingSide::King)
                 && b.r.castling.has(c, CastlingSide::King)
                 && (b.all() & castling::pass(c, CastlingSide::King)).is_empty()
                 && !movegen::is_square_attacked(b, mv.src, c.inv())
                 && !movegen::is_square_attacked(b, unsafe { mv.src.add_unchecked(1) }, c.inv())
         }
         MoveKind::CastlingQueenside => {
-            mv.src.rank() == geometry::castling_rank(c)
+            mv.src == Sq::make(File::E, geometry::castling_rank(c))
+                && mv.dst == castling::king_dst(c, CastlingSide::Queen)
                 && b.r.castling.has(c, CastlingSide::Queen)
                 && (b.all() & castling::pass(c, CastlingSide::Queen)).is_empty()
                 && !movegen::is_square_attacked(b, mv.src, c.inv())
@@ -767,6 +1054,13 @@ fn do_is_move_legal<C: generic::Color>(b: &Board, mv: Move) -> bool {
     let src_cell = b.get(mv.src);
 
     if src_cell == Cell::make(c, Piece::King) {
+        if b.r.chess960
+            && let Ok(side) = CastlingSide::try_from(mv.kind)
+        {
+            let all = b.all() ^ src ^ dst ^ Bitboard::one(castling::rook_dst(c, side));
+            let king_dst = castling::king_dst(c, side);
+            return !is_square_attacked_masked(b, king_dst, inv, all, Bitboard::FULL);
+        }
         return !is_square_attacked_masked(b, mv.dst, inv, b.all() ^ src, Bitboard::FULL);
     }
 
@@ -800,10 +1094,130 @@ pub enum UciParseError {
     BadDst(SqParseError),
     #[error("bad promote char {0:?}")]
     BadPromote(char),
+    #[error("no piece on source square {0}")]
+    EmptySource(Sq),
+    #[error("piece on source square {0} has wrong color")]
+    WrongColor(Sq),
     #[error("invalid move: {0}")]
     Validate(#[from] ValidateError),
 }
 
+#[derive(Debug, Clone, Error, Eq, PartialEq)]
+pub enum SanParseError {
+    #[error("bad syntax")]
+    BadSyntax,
+    #[error("bad destination: {0}")]
+    BadDst(SqParseError),
+    #[error("bad promote char {0:?}")]
+    BadPromote(char),
+    #[error("no legal move matches")]
+    Illegal,
+    #[error("move is ambiguous")]
+    Ambiguous,
+}
+
+enum SanMove {
+    Castling(MoveKind),
+    Move {
+        piece: Piece,
+        src_file: Option<File>,
+        src_rank: Option<Rank>,
+        dst: Sq,
+        promote: Option<Piece>,
+    },
+}
+
+impl FromStr for SanMove {
+    type Err = SanParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_end_matches(['+', '#', '!', '?']);
+        match s {
+            "O-O" | "0-0" => return Ok(SanMove::Castling(MoveKind::CastlingKingside)),
+            "O-O-O" | "0-0-0" => return Ok(SanMove::Castling(MoveKind::CastlingQueenside)),
+            _ => {}
+        }
+        if !s.is_ascii() {
+            return Err(SanParseError::BadSyntax);
+        }
+
+        let (piece, s) = match s.as_bytes().first() {
+            Some(b'K') => (Piece::King, &s[1..]),
+            Some(b'Q') => (Piece::Queen, &s[1..]),
+            Some(b'R') => (Piece::Rook, &s[1..]),
+            Some(b'B') => (Piece::Bishop, &s[1..]),
+            Some(b'N') => (Piece::Knight, &s[1..]),
+            _ => (Piece::Pawn, s),
+        };
+        let (s, promote) = match s.split_once('=') {
+            Some((s, p)) if p.len() == 1 => (s, Some(p.as_bytes()[0])),
+            Some(_) => return Err(SanParseError::BadSyntax),
+            None => match s.as_bytes().last() {
+                Some(&c) if piece == Piece::Pawn && c.is_ascii_uppercase() => {
+                    (&s[..s.len() - 1], Some(c))
+                }
+                _ => (s, None),
+            },
+        };
+        let promote = match promote {
+            Some(b'N') => Some(Piece::Knight),
+            Some(b'B') => Some(Piece::Bishop),
+            Some(b'R') => Some(Piece::Rook),
+            Some(b'Q') => Some(Piece::Queen),
+            Some(c) => return Err(SanParseError::BadPromote(c as char)),
+            None => None,
+        };
+
+        let s = s.replacen('x', "", 1);
+        if !(2..=4).contains(&s.len()) {
+            return Err(SanParseError::BadSyntax);
+        }
+        let (src, dst) = s.split_at(s.len() - 2);
+        let dst = Sq::from_str(dst).map_err(SanParseError::BadDst)?;
+        let (mut src_file, mut src_rank) = (None, None);
+        for c in src.chars() {
+            if let Some(f) = File::from_char(c) {
+                src_file = Some(f);
+            } else if let Some(r) = Rank::from_char(c) {
+                src_rank = Some(r);
+            } else {
+                return Err(SanParseError::BadSyntax);
+            }
+        }
+        Ok(SanMove::Move {
+            piece,
+            src_file,
+            src_rank,
+            dst,
+            promote,
+        })
+    }
+}
+
+impl SanMove {
+    fn matches(&self, mv: Move, b: &Board) -> bool {
+        match *self {
+            SanMove::Castling(kind) => mv.kind == kind,
+            SanMove::Move {
+                piece,
+                src_file,
+                src_rank,
+                dst,
+                promote,
+            } => {
+                !matches!(
+                    mv.kind,
+                    MoveKind::CastlingKingside | MoveKind::CastlingQueenside
+                ) && b.get(mv.src) == Cell::make(b.r.side, piece)
+                    && mv.dst == dst
+                    && mv.kind.promote() == promote
+                    && src_file.is_none_or(|f| mv.src.file() == f)
+                    && src_rank.is_none_or(|r| mv.src.rank() == r)
+            }
+        }
+    }
+}
+
 impl FromStr for UciMove {
     type Err = UciParseError;
 
@@ -832,14 +1246,16 @@ impl FromStr for UciMove {
 }
 
 impl UciMove {
-    fn into_move(self, b: &Board) -> Result<Move, ValidateError> {
+    fn into_move(self, b: &Board) -> Result<Move, UciParseError> {
         let c = b.r.side;
         match self {
             UciMove::Null => Ok(Move::NULL),
             UciMove::Move { src, dst, promote } => {
                 let src_cell = b.get(src);
-                if src_cell.color() != Some(c) {
-                    return Err(ValidateError::NotWellFormed);
+                match src_cell.color() {
+                    None => return Err(UciParseError::EmptySource(src)),
+                    Some(color) if color != c => return Err(UciParseError::WrongColor(src)),
+                    Some(_) => {}
                 }
 
                 let kind = match promote {
@@ -856,6 +1272,15 @@ impl UciMove {
                                 MoveKind::PawnSimple
                             }
                         }
+                        Piece::King if b.r.chess960 => {
+                            if b.get(dst) != Cell::make(c, Piece::Rook) {
+                                MoveKind::Simple
+                            } else if src.file() < dst.file() {
+                                MoveKind::CastlingKingside
+                            } else {
+                                MoveKind::CastlingQueenside
+                            }
+                        }
                         Piece::King => {
                             let r = geometry::castling_rank(c);
                             if src == Sq::make(File::E, r) && dst == Sq::make(File::G, r) {
@@ -870,7 +1295,7 @@ impl UciMove {
                     },
                 };
 
-                Move::new(kind, src, dst)
+                Ok(Move::new(kind, src, dst)?)
             }
         }
     }
@@ -898,6 +1323,15 @@ fn do_diff_after_move<C: generic::Color>(
             l.del(mv.src, pawn);
             l.upd(mv.dst, u.dst_cell, src_cell);
         }
+        MoveKind::CastlingKingside | MoveKind::CastlingQueenside if b.r.chess960 => {
+            let side = CastlingSide::try_from(mv.kind).unwrap();
+            let king = Cell::make(c, Piece::King);
+            let rook = Cell::make(c, Piece::Rook);
+            l.del(mv.src, king);
+            l.del(mv.dst, rook);
+            l.add(castling::king_dst(c, side), king);
+            l.add(castling::rook_dst(c, side), rook);
+        }
         MoveKind::CastlingKingside => {
             let king = Cell::make(c, Piece::King);
             let rook = Cell::make(c, Piece::Rook);
@@ -1022,6 +1456,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_capture() {
+        let b = Board::from_str("3r3n/2P5/k7/3pP3/8/8/8/1N2K2R w K d6 0 1").unwrap();
+        for (mv, captured, quiet) in [
+            ("c7c8q", None, false),
+            ("c7d8r", Some(Piece::Rook), false),
+            ("e5d6", Some(Piece::Pawn), false),
+            ("e5e6", None, true),
+            ("b1c3", None, true),
+            ("e1g1", None, true),
+            ("h1h8", Some(Piece::Knight), false),
+        ] {
+            let mv = Move::from_uci_legal(mv, &b).unwrap();
+            assert_eq!(mv.captured(&b), captured, "{}", mv);
+            assert_eq!(mv.is_capture(&b), captured.is_some(), "{}", mv);
+            assert_eq!(mv.is_quiet(&b), quiet, "{}", mv);
+        }
+
+        // Castling onto the own rook is not a capture.
+        let b = Board::from_str("4k3/8/8/8/8/8/8/4K1R1 w G - 0 1").unwrap();
+        let mv = Move::from_uci_legal("e1g1", &b).unwrap();
+        assert_eq!(mv.kind(), MoveKind::CastlingKingside);
+        assert_eq!(mv.captured(&b), None);
+        assert!(mv.is_quiet(&b));
+        assert!(Move::NULL.is_quiet(&b));
+    }
+
     #[test]
     fn test_undo() {
         let mut b =
@@ -1056,6 +1517,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_null_move() {
+        for (fen, fen_after) in [
+            (
+                "r3k3/8/8/3pP3/8/8/8/4K3 w q d6 0 5",
+                "r3k3/8/8/3pP3/8/8/8/4K3 b q - 1 5",
+            ),
+            (
+                "r3k3/8/8/8/3Pp3/8/8/4K3 b q d3 7 5",
+                "r3k3/8/8/8/3Pp3/8/8/4K3 w q - 8 6",
+            ),
+        ] {
+            let mut b = Board::from_str(fen).unwrap();
+            let b_copy = b.clone();
+            let after = Board::from_str(fen_after).unwrap();
+            let u = unsafe { make_null_move_unchecked(&mut b) };
+            assert_eq!(b, after);
+            assert_eq!(b.zobrist_hash(), after.zobrist_hash());
+            unsafe { unmake_null_move_unchecked(&mut b, u) };
+            assert_eq!(b, b_copy);
+            assert_eq!(b.zobrist_hash(), b_copy.zobrist_hash());
+        }
+    }
+
+    #[test]
+    fn test_castling_960() {
+        for (fen, mv_str, kind, fen_after) in [
+            (
+                "6kr/pppppppp/8/8/8/8/PPPPPPPP/6KR w Hh - 3 7",
+                "g1h1",
+                MoveKind::CastlingKingside,
+                "6kr/pppppppp/8/8/8/8/PPPPPPPP/5RK1 b h - 4 7",
+            ),
+            (
+                "rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w GAga - 0 1",
+                "b1g1",
+                MoveKind::CastlingKingside,
+                "rk4r1/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 b ga - 1 1",
+            ),
+            (
+                "rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w GAga - 0 1",
+                "b1a1",
+                MoveKind::CastlingQueenside,
+                "rk4r1/pppppppp/8/8/8/8/PPPPPPPP/2KR2R1 b ga - 1 1",
+            ),
+        ] {
+            let mut b = Board::from_str(fen).unwrap();
+            let b_copy = b.clone();
+            let mv = Move::from_uci_legal(mv_str, &b).unwrap();
+            assert_eq!(mv.kind(), kind);
+            assert_eq!(mv.to_string(), mv_str);
+            let u = unsafe { make_move_unchecked(&mut b, mv) };
+            assert_eq!(b.to_string(), fen_after);
+            assert_eq!(b.zobrist_hash(), b.raw().zobrist_hash());
+            unsafe { unmake_move_unchecked(&mut b, mv, u) };
+            assert_eq!(b, b_copy);
+            assert_eq!(b.zobrist_hash(), b_copy.zobrist_hash());
+        }
+
+        // The rook shields the king's destination from the queen before castling.
+        let b = Board::from_str("2k5/8/8/8/8/8/8/qR1K4 w B - 0 1").unwrap();
+        let mv = Move::from_uci("d1b1", &b).unwrap();
+        assert!(mv.is_semilegal(&b));
+        assert!(!unsafe { mv.is_legal_unchecked(&b) });
+
+        // Rooks moving away from their files lose castling rights.
+        let mut b = Board::from_str("rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w GAga - 0 1").unwrap();
+        b.make_uci_move("g1h1").unwrap();
+        assert_eq!(
+            b.raw().castling,
+            CastlingRights::FULL.without(Color::White, CastlingSide::King)
+        );
+    }
+
     #[test]
     fn test_pawns() {
         let mut b = Board::from_str("3K4/3p4/8/3PpP2/8/5p2/6P1/2k5 w - e6 0 1").unwrap();
@@ -1093,7 +1628,15 @@ mod tests {
 
         assert_eq!(
             Move::from_uci("a3a4", &b),
-            Err(UciParseError::Validate(ValidateError::NotWellFormed))
+            Err(UciParseError::EmptySource(Sq::make(File::A, Rank::R3)))
+        );
+        assert_eq!(
+            Move::from_uci("a7a6", &b),
+            Err(UciParseError::WrongColor(Sq::make(File::A, Rank::R7)))
+        );
+        assert_eq!(
+            Move::from_uci("e4e5k", &b),
+            Err(UciParseError::BadPromote('k'))
         );
 
         let m = Move::from_uci("e1d1", &b).unwrap();
@@ -1106,6 +1649,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_raw_undo() {
+        let mut b = Board::from_str("r3k3/8/8/3pP3/8/8/8/R3K3 w Qq d6 7 20").unwrap();
+        let mv = Move::from_uci_legal("e5d6", &b).unwrap();
+        let u = unsafe { b.make_move_unchecked(mv) };
+        assert_eq!(u.dst_cell(), Cell::None);
+        assert_eq!(u.captured_cell(mv), Cell::BlackPawn);
+        assert_eq!(u.prev_ep(), Some(Sq::from_str("d5").unwrap()));
+        assert_eq!(u.prev_castling(), CastlingRights::from_str("Qq").unwrap());
+        assert_eq!(u.prev_move_counter(), 7);
+
+        let mv = Move::from_uci_legal("a8a1", &b).unwrap();
+        let u = unsafe { b.make_move_unchecked(mv) };
+        assert_eq!(u.dst_cell(), Cell::WhiteRook);
+        assert_eq!(u.captured_cell(mv), Cell::WhiteRook);
+        assert_eq!(u.prev_ep(), None);
+        assert_eq!(u.prev_castling(), CastlingRights::from_str("Qq").unwrap());
+        assert_eq!(u.prev_move_counter(), 0);
+        assert_eq!(b.raw().castling, CastlingRights::EMPTY);
+
+        let mut b = Board::from_str("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let mv = Move::from_uci_legal("e1g1", &b).unwrap();
+        let u = unsafe { b.make_move_unchecked(mv) };
+        assert_eq!(u.captured_cell(mv), Cell::None);
+    }
+
     #[test]
     fn test_pack() {
         let b = Board::start();
@@ -1121,5 +1690,133 @@ mod tests {
         assert_eq!(p.value(), 8109);
         let m2 = Move::from(p);
         assert_eq!(m, m2);
+
+        assert_eq!(PackedMove::try_from(8109), Ok(p));
+        assert_eq!(PackedMove::try_from(0), Ok(PackedMove::NULL));
+        assert_eq!(PackedMove::from(Move::NULL), PackedMove::NULL);
+        assert_eq!(Move::from(PackedMove::NULL), Move::NULL);
+        assert!(PackedMove::try_from(0xf000).is_err());
+        assert!(PackedMove::try_from(1 << 12).is_err());
+    }
+
+    #[test]
+    fn test_pack_all() {
+        let mut count = 0;
+        for kind in MoveKind::iter() {
+            for src in Sq::iter() {
+                for dst in Sq::iter() {
+                    let Ok(m) = Move::new(kind, src, dst) else {
+                        continue;
+                    };
+                    let p = PackedMove::from(m);
+                    assert_eq!(Move::from(p), m);
+                    assert_eq!(PackedMove::try_from(p.value()), Ok(p));
+                    count += 1;
+                }
+            }
+        }
+        assert!(count > 1000);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let b = Board::start();
+        let m = Move::from_uci_legal("g1f3", &b).unwrap();
+
+        let s = serde_json::to_string(&m).unwrap();
+        assert_eq!(s, r#"{"kind":"Simple","src":"g1","dst":"f3"}"#);
+        assert_eq!(serde_json::from_str::<Move>(&s).unwrap(), m);
+        assert!(
+            serde_json::from_str::<Move>(r#"{"kind":"PawnDouble","src":"g1","dst":"f3"}"#).is_err()
+        );
+
+        let p = PackedMove::from(m);
+        let s = serde_json::to_string(&p).unwrap();
+        assert_eq!(s, "8109");
+        assert_eq!(serde_json::from_str::<PackedMove>(&s).unwrap(), p);
+        assert!(serde_json::from_str::<PackedMove>("65535").is_err());
+    }
+
+    #[test]
+    fn test_from_san() {
+        for (fen, san, mv_str) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "Nf3",
+                "g1f3",
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R w KQkq - 0 3",
+                "Nbd2",
+                "b1d2",
+            ),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6", "e5d6"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O-O", "e1c1"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O+", "e1g1"),
+            ("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8=Q+", "e7e8q"),
+            ("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1", "e8N", "e7e8n"),
+            ("4r3/3P2k1/8/8/8/8/8/4K3 w - - 0 1", "dxe8=R!?", "d7e8r"),
+            ("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "Qa1b2", "a1b2"),
+            ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "R1a3", "a1a3"),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let m = Move::from_uci_legal(mv_str, &b).unwrap();
+            assert_eq!(Move::from_san(san, &b), Ok(m), "{} {}", fen, san);
+        }
+
+        let b = Board::from_str("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1").unwrap();
+        assert_eq!(Move::from_san("Qb2", &b), Err(SanParseError::Ambiguous));
+        assert_eq!(Move::from_san("Qab2", &b), Err(SanParseError::Ambiguous));
+        assert_eq!(Move::from_san("Qh5", &b), Err(SanParseError::Illegal));
+        assert_eq!(Move::from_san("O-O", &b), Err(SanParseError::Illegal));
+        assert_eq!(Move::from_san("Q", &b), Err(SanParseError::BadSyntax));
+        assert!(matches!(
+            Move::from_san("Qz9", &b),
+            Err(SanParseError::BadDst(_))
+        ));
+        assert_eq!(
+            Move::from_san("a8=K", &b),
+            Err(SanParseError::BadPromote('K'))
+        );
+
+        let b = Board::start();
+        let mut moves = movegen::MoveList::new();
+        movegen::MoveGen::new(&b).gen_all(&mut moves);
+        for m in moves {
+            assert_eq!(Move::from_san(&m.to_san(&b), &b), Ok(m));
+        }
+    }
+
+    #[test]
+    fn test_san() {
+        for (fen, mv_str, san) in [
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R w KQkq - 0 3",
+                "b1d2",
+                "Nbd2",
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                "e4d5",
+                "exd5",
+            ),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", "O-O"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1c1", "O-O-O"),
+            ("2k5/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q", "e8=Q+"),
+            ("4r3/3P2k1/8/8/8/8/8/4K3 w - - 0 1", "d7e8n", "dxe8=N+"),
+            (
+                "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2",
+                "d8h4",
+                "Qh4#",
+            ),
+            ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3"),
+            ("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2", "Qa1b2"),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let m = Move::from_uci_legal(mv_str, &b).unwrap();
+            assert_eq!(m.to_san(&b), san, "{} {}", fen, mv_str);
+        }
     }
 }
@@ -1,4 +1,4 @@
-use crate::bitboard::Bitboard;
+use crate::bitboard::{Bitboard, Dir};
 use crate::core::{Color, File};
 use crate::geometry::bitboard;
 
@@ -27,3 +27,137 @@ pub fn advance_right(c: Color, b: Bitboard) -> Bitboard {
         Color::Black => b.shl(9),
     }
 }
+
+/// Fills the squares in front of the given ones, including the squares themselves.
+#[inline]
+pub fn front_fill(c: Color, mut b: Bitboard) -> Bitboard {
+    match c {
+        Color::White => {
+            b |= b.shr(8);
+            b |= b.shr(16);
+            b | b.shr(32)
+        }
+        Color::Black => {
+            b |= b.shl(8);
+            b |= b.shl(16);
+            b | b.shl(32)
+        }
+    }
+}
+
+/// Fills the squares behind the given ones, including the squares themselves.
+#[inline]
+pub fn rear_fill(c: Color, b: Bitboard) -> Bitboard {
+    front_fill(c.inv(), b)
+}
+
+/// Squares strictly in front of the given ones.
+#[inline]
+pub fn front_span(c: Color, b: Bitboard) -> Bitboard {
+    front_fill(c, advance_forward(c, b))
+}
+
+/// Squares that the pawns can ever attack while advancing, i.e. the front spans on the
+/// adjacent files.
+#[inline]
+pub fn attack_span(c: Color, b: Bitboard) -> Bitboard {
+    front_fill(c, advance_left(c, b) | advance_right(c, b))
+}
+
+/// Whole files containing the given squares.
+#[inline]
+pub fn file_fill(b: Bitboard) -> Bitboard {
+    front_fill(Color::White, b) | rear_fill(Color::White, b)
+}
+
+/// Pawns with no enemy pawns in front of them on the same or adjacent files.
+#[inline]
+pub fn passed(c: Color, own: Bitboard, enemy: Bitboard) -> Bitboard {
+    own & !(front_span(c.inv(), enemy) | attack_span(c.inv(), enemy))
+}
+
+/// Pawns with another friendly pawn behind them on the same file.
+#[inline]
+pub fn doubled(c: Color, own: Bitboard) -> Bitboard {
+    own & front_span(c, own)
+}
+
+/// Pawns with no friendly pawns on the adjacent files.
+#[inline]
+pub fn isolated(own: Bitboard) -> Bitboard {
+    let files = file_fill(own);
+    own & !(files.shift(Dir::West) | files.shift(Dir::East))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::core::{Piece, Sq};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_spans() {
+        let sq = |s: &str| Sq::from_str(s).unwrap();
+        let bb = |sqs: &[&str]| sqs.iter().fold(Bitboard::EMPTY, |b, s| b.with(sq(s)));
+        let pawns = bb(&["a2", "e5"]);
+
+        assert_eq!(
+            front_fill(Color::White, pawns),
+            bb(&[
+                "a2", "a3", "a4", "a5", "a6", "a7", "a8", "e5", "e6", "e7", "e8"
+            ])
+        );
+        assert_eq!(
+            rear_fill(Color::White, pawns),
+            bb(&["a2", "a1", "e5", "e4", "e3", "e2", "e1"])
+        );
+        assert_eq!(
+            rear_fill(Color::Black, pawns),
+            front_fill(Color::White, pawns)
+        );
+        assert_eq!(
+            front_span(Color::White, pawns),
+            bb(&["a3", "a4", "a5", "a6", "a7", "a8", "e6", "e7", "e8"])
+        );
+        assert_eq!(
+            front_span(Color::Black, pawns),
+            bb(&["a1", "e4", "e3", "e2", "e1"])
+        );
+        assert_eq!(
+            attack_span(Color::White, pawns),
+            bb(&[
+                "b3", "b4", "b5", "b6", "b7", "b8", "d6", "d7", "d8", "f6", "f7", "f8"
+            ])
+        );
+        assert_eq!(
+            attack_span(Color::Black, bb(&["h7"])),
+            bb(&["g6", "g5", "g4", "g3", "g2", "g1"])
+        );
+        assert!(front_span(Color::White, bb(&["c8"])).is_empty());
+    }
+
+    #[test]
+    fn test_structure() {
+        let b = Board::from_str("4k3/1p4p1/8/2P5/4P3/P1P4p/P7/4K3 w - - 0 1").unwrap();
+        let (white, black) = (
+            b.piece(Color::White, Piece::Pawn),
+            b.piece(Color::Black, Piece::Pawn),
+        );
+        let sqs = |bb: Bitboard| bb.into_iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let c5 = Bitboard::one(Sq::from_str("c5").unwrap());
+        assert_eq!(sqs(front_span(Color::White, c5)), ["c8", "c7", "c6"]);
+        assert_eq!(
+            sqs(attack_span(Color::Black, c5)),
+            ["b4", "d4", "b3", "d3", "b2", "d2", "b1", "d1"]
+        );
+        assert_eq!(sqs(passed(Color::White, white, black)), ["e4"]);
+        assert_eq!(sqs(passed(Color::Black, black, white)), ["g7", "h3"]);
+        assert_eq!(sqs(doubled(Color::White, white)), ["c5", "a3"]);
+        assert!(doubled(Color::Black, black).is_empty());
+        assert_eq!(sqs(isolated(white)), ["c5", "e4", "a3", "c3", "a2"]);
+        assert_eq!(sqs(isolated(black)), ["b7"]);
+        assert_eq!(file_fill(white).len(), 24);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::board::Board;
+use crate::movegen::{MoveGen, MoveList};
+use crate::moves::Move;
+
+pub fn perft(b: &mut Board, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if depth == 1 {
+        return MoveGen::new(b).count_legal() as u64;
+    }
+    let mut moves = MoveList::new();
+    MoveGen::new(b).gen_legal(&mut moves);
+    let mut count = 0;
+    for mv in moves {
+        let u = unsafe { b.make_move_unchecked(mv) };
+        count += perft(b, depth - 1);
+        unsafe { b.unmake_move_unchecked(mv, u) };
+    }
+    count
+}
+
+pub fn perft_divide(b: &mut Board, depth: usize) -> Vec<(Move, u64)> {
+    let mut res = Vec::new();
+    perft_divide_with(b, depth, |mv, count| {
+        res.push((mv, count));
+        true
+    });
+    res
+}
+
+/// Same as [`perft_divide`], but passes the count for each root move to `f` as soon as it is
+/// known. Stops before the next root move if `f` returns `false`.
+pub fn perft_divide_with(b: &mut Board, depth: usize, mut f: impl FnMut(Move, u64) -> bool) {
+    assert!(depth >= 1);
+    let mut moves = MoveList::new();
+    MoveGen::new(b).gen_legal(&mut moves);
+    moves.sort_by_cached_key(|mv| mv.to_string());
+    for mv in moves {
+        let u = unsafe { b.make_move_unchecked(mv) };
+        let count = perft(b, depth - 1);
+        unsafe { b.unmake_move_unchecked(mv, u) };
+        if !f(mv, count) {
+            break;
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct PerftEntry {
+    hash: u64,
+    depth: usize,
+    count: u64,
+}
+
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+}
+
+impl PerftTable {
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: vec![PerftEntry::default(); size.max(1).next_power_of_two()],
+        }
+    }
+
+    #[inline]
+    fn slot(&self, hash: u64) -> usize {
+        hash as usize & (self.entries.len() - 1)
+    }
+
+    #[inline]
+    fn get(&self, hash: u64, depth: usize) -> Option<u64> {
+        let e = unsafe { self.entries.get_unchecked(self.slot(hash)) };
+        (e.hash == hash && e.depth == depth).then_some(e.count)
+    }
+
+    #[inline]
+    fn put(&mut self, hash: u64, depth: usize, count: u64) {
+        let slot = self.slot(hash);
+        unsafe { *self.entries.get_unchecked_mut(slot) = PerftEntry { hash, depth, count } };
+    }
+}
+
+pub fn perft_hashed(b: &mut Board, depth: usize, tt: &mut PerftTable) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if depth == 1 {
+        return MoveGen::new(b).count_legal() as u64;
+    }
+    let hash = b.zobrist_hash();
+    if let Some(count) = tt.get(hash, depth) {
+        return count;
+    }
+    let mut moves = MoveList::new();
+    MoveGen::new(b).gen_legal(&mut moves);
+    let mut count = 0;
+    for mv in moves {
+        let u = unsafe { b.make_move_unchecked(mv) };
+        count += perft_hashed(b, depth - 1, tt);
+        unsafe { b.unmake_move_unchecked(mv, u) };
+    }
+    tt.put(hash, depth, count);
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_divide() {
+        let mut b =
+            Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let div = perft_divide(&mut b, 2);
+        assert_eq!(div.len(), 48);
+        assert_eq!(div.iter().map(|&(_, c)| c).sum::<u64>(), 2039);
+        for (mv, count) in [
+            ("d5e6", 46),
+            ("e1c1", 43),
+            ("e2a6", 36),
+            ("e5d7", 45),
+            ("f3f6", 39),
+        ] {
+            let mv = Move::from_uci_legal(mv, &b).unwrap();
+            assert_eq!(div.iter().find(|&&(m, _)| m == mv), Some(&(mv, count)));
+        }
+        assert!(div.is_sorted_by_key(|(mv, _)| mv.to_string()));
+
+        let div = perft_divide(&mut b, 3);
+        assert_eq!(div.iter().map(|&(_, c)| c).sum::<u64>(), 97862);
+        assert_eq!(perft(&mut b, 3), 97862);
+        assert_eq!(perft(&mut b, 1), 48);
+        assert_eq!(perft(&mut b, 0), 1);
+
+        let mut seen = Vec::new();
+        perft_divide_with(&mut b, 2, |mv, count| {
+            seen.push((mv, count));
+            seen.len() < 5
+        });
+        assert_eq!(seen, perft_divide(&mut b, 2)[..5]);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::bitboard::Bitboard;
+use crate::board::{Board, PIECE_VALUES};
+use crate::core::{Color, Piece, Sq};
+use crate::geometry;
+use crate::moves::{Move, MoveKind};
+
+const ATTACKER_ORDER: [Piece; Piece::COUNT] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+#[inline]
+fn value(p: Piece) -> i32 {
+    PIECE_VALUES[p.index()]
+}
+
+#[inline]
+fn attackers(b: &Board, s: Sq, occupied: Bitboard) -> Bitboard {
+    b.attackers_to(s, Color::White, occupied) | b.attackers_to(s, Color::Black, occupied)
+}
+
+pub fn see(b: &Board, mv: Move) -> i32 {
+    let (src, dst) = (mv.src(), mv.dst());
+    let mut occupied = b.all().without(src);
+    let mut gain = [0_i32; 32];
+
+    gain[0] = match mv.kind() {
+        MoveKind::Enpassant => {
+            let captured = unsafe { dst.add_unchecked(-geometry::pawn_forward_delta(b.side())) };
+            occupied.unset(captured);
+            value(Piece::Pawn)
+        }
+        _ => b.get(dst).piece().map_or(0, value),
+    };
+    let mut on_dst = match mv.kind().promote() {
+        Some(p) => {
+            gain[0] += value(p) - value(Piece::Pawn);
+            value(p)
+        }
+        None => b.get(src).piece().map_or(0, value),
+    };
+
+    let promote_rank = |c: Color| dst.rank() == geometry::promote_dst_rank(c);
+    let mut attack = attackers(b, dst, occupied);
+    let mut c = b.side().inv();
+    let mut depth = 1;
+    loop {
+        let our = attack & b.color(c);
+        let Some((p, s)) = ATTACKER_ORDER
+            .iter()
+            .find_map(|&p| (our & b.piece(c, p)).first().map(|s| (p, s)))
+        else {
+            break;
+        };
+
+        occupied.unset(s);
+        attack = attackers(b, dst, occupied);
+        if p == Piece::King && (attack & b.color(c.inv())).is_nonempty() {
+            break;
+        }
+
+        gain[depth] = on_dst - gain[depth - 1];
+        on_dst = value(p);
+        if p == Piece::Pawn && promote_rank(c) {
+            gain[depth] += value(Piece::Queen) - value(Piece::Pawn);
+            on_dst = value(Piece::Queen);
+        }
+        depth += 1;
+        c = c.inv();
+    }
+
+    for d in (1..depth).rev() {
+        gain[d - 1] = -(-gain[d - 1]).max(gain[d]);
+    }
+    gain[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_simple() {
+        for (fen, mv, res) in [
+            (
+                "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+                "e1e5",
+                100,
+            ),
+            (
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5",
+                -200,
+            ),
+            ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 100),
+            ("4k3/8/1n6/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 0),
+            ("4k3/8/8/8/3q4/8/3R4/3RK3 w - - 0 1", "d2d4", 900),
+            ("3rk3/8/8/8/3q4/8/3R4/3RK3 w - - 0 1", "d2d4", 900),
+            ("3rk3/3r4/8/8/3q4/8/3R4/3RK3 w - - 0 1", "d2d4", 400),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mv = Move::from_uci_legal(mv, &b).unwrap();
+            assert_eq!(see(&b, mv), res, "{} {}", fen, mv);
+        }
+    }
+
+    #[test]
+    fn test_special() {
+        for (fen, mv, res) in [
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+            ("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 0),
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", 800),
+            ("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", -100),
+            ("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n", -100),
+            ("rk6/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8q", 400),
+            ("4k3/8/2p5/3p4/8/5B2/8/4K3 w - - 0 1", "f3d5", -200),
+            ("4k3/8/2p5/3p4/8/5B2/6Q1/4K3 w - - 0 1", "f3d5", -100),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mv = Move::from_uci_legal(mv, &b).unwrap();
+            assert_eq!(see(&b, mv), res, "{} {}", fen, mv);
+        }
+    }
+}
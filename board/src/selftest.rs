@@ -57,6 +57,7 @@ impl DiffListener for &mut SquareDiffListener {
 pub fn selftest(b: &Board) {
     // Check that the board itself is valid.
     test_board_valid(b);
+    assert!(b.hash_is_consistent());
 
     // Check that `to_string()` and `from_str()` are symmetrical.
     let fen = b.to_string();
@@ -72,6 +73,17 @@ pub fn selftest(b: &Board) {
 
     let mut moves_capture = MoveList::new();
     move_gen.gen_capture(&mut moves_capture);
+
+    // Check that the fast capture legality check agrees with the general one.
+    let info = b.position_info();
+    for m in &moves_capture {
+        assert_eq!(
+            b.is_capture_legal(*m, &info),
+            unsafe { m.is_legal_unchecked(b) },
+            "capture {} legality mismatch",
+            m
+        );
+    }
     filter_legal_moves(b, &mut moves_capture);
 
     let mut moves_simple = MoveList::new();
@@ -94,6 +106,15 @@ pub fn selftest(b: &Board) {
     assert_eq!(moves, moves2);
     assert_eq!(moves_simple, moves_simple2);
 
+    // Check that `has_legal_move()` agrees with the generated moves.
+    assert_eq!(move_gen.has_legal_move(), !moves.is_empty());
+
+    // Check that `gen_legal()` generates exactly the legal moves.
+    let mut moves_legal = MoveList::new();
+    move_gen.gen_legal(&mut moves_legal);
+    moves_legal.sort_by_key(move_key);
+    assert_eq!(moves, moves_legal);
+
     // Check that move parser works correctly.
     for m in &moves {
         assert_eq!(Move::from_uci(&m.to_string(), b), Ok(*m));
@@ -105,12 +126,12 @@ pub fn selftest(b: &Board) {
     for kind in MoveKind::iter() {
         for src in Sq::iter() {
             for dst in Sq::iter() {
-                if let Ok(mv) = Move::new(kind, src, dst) {
-                    if mv.semi_validate(b).is_ok() {
-                        semilegals.push(mv);
-                        if unsafe { mv.is_legal_unchecked(b) } {
-                            moves3.push(mv);
-                        }
+                if let Ok(mv) = Move::new(kind, src, dst)
+                    && mv.semi_validate(b).is_ok()
+                {
+                    semilegals.push(mv);
+                    if unsafe { mv.is_legal_unchecked(b) } {
+                        moves3.push(mv);
                     }
                 }
             }
@@ -128,11 +149,23 @@ pub fn selftest(b: &Board) {
         assert_eq!(is_legal, is_legal2);
         if is_legal {
             test_board_valid(&b_clone);
+            assert!(b_clone.hash_is_consistent(), "hash mismatch after {}", m);
         }
         unsafe { b_clone.unmake_move_unchecked(*m, u) };
         assert_eq!(BoardFullEq(&b_clone), BoardFullEq(b));
     }
 
+    // Check that the null move keeps the hash consistent.
+    if !b.is_check() {
+        let u = unsafe { b_clone.make_null_move_unchecked() };
+        assert!(
+            b_clone.hash_is_consistent(),
+            "hash mismatch after null move"
+        );
+        unsafe { b_clone.unmake_null_move_unchecked(u) };
+        assert_eq!(BoardFullEq(&b_clone), BoardFullEq(b));
+    }
+
     // Check that packing moves works correctly.
     for m in &semilegals {
         assert_eq!(Move::from(PackedMove::from(*m)), *m);
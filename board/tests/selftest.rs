@@ -1,5 +1,9 @@
 use arrayvec::ArrayVec;
-use pawnyowl_board::{Board, Color, File, Move, MoveGen, MoveList, Rank, Sq, movegen, selftest};
+use pawnyowl_board::board::PIECE_VALUES;
+use pawnyowl_board::{
+    Board, Cell, Color, File, Move, MoveGen, MoveKind, MoveList, Piece, Rank, Sq, geometry,
+    movegen, selftest,
+};
 use std::io::{BufRead, Write};
 use std::str::FromStr;
 
@@ -9,6 +13,7 @@ pub struct Options {
     pub dump_trace_chains: bool,
     pub run_self_test: bool,
     pub attack_heatmaps: bool,
+    pub verify_see: bool,
 }
 
 impl Default for Options {
@@ -19,6 +24,7 @@ impl Default for Options {
             dump_trace_chains: false,
             run_self_test: true,
             attack_heatmaps: true,
+            verify_see: false,
         }
     }
 }
@@ -28,6 +34,56 @@ pub struct Tester<'a, W> {
     writer: &'a mut W,
 }
 
+fn see_value(p: Piece) -> i32 {
+    PIECE_VALUES[p.index()]
+}
+
+fn see_recapture(board: &mut Board, dst: Sq) -> Option<Move> {
+    let c = board.side();
+    let attackers = movegen::square_attackers(board, dst, c);
+    let (piece, src) = [
+        Piece::Pawn,
+        Piece::Knight,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::Queen,
+        Piece::King,
+    ]
+    .into_iter()
+    .find_map(|p| (attackers & board.piece(c, p)).first().map(|s| (p, s)))?;
+    let kind = match piece {
+        Piece::Pawn if dst.rank() == geometry::promote_dst_rank(c) => MoveKind::PromoteQueen,
+        Piece::Pawn => MoveKind::PawnSimple,
+        _ => MoveKind::Simple,
+    };
+    let mv = Move::new(kind, src, dst).unwrap();
+    if piece == Piece::King {
+        let u = unsafe { board.make_move_unchecked(mv) };
+        let attacked = board.is_opponent_king_attacked();
+        unsafe { board.unmake_move_unchecked(mv, u) };
+        if attacked {
+            return None;
+        }
+    }
+    Some(mv)
+}
+
+fn see_slow(board: &mut Board, mv: Move) -> i32 {
+    let mut gain = match mv.kind() {
+        MoveKind::Enpassant => see_value(Piece::Pawn),
+        _ => board.get(mv.dst()).piece().map_or(0, see_value),
+    };
+    if let Some(p) = mv.kind().promote() {
+        gain += see_value(p) - see_value(Piece::Pawn);
+    }
+    let u = unsafe { board.make_move_unchecked(mv) };
+    if let Some(reply) = see_recapture(board, mv.dst()) {
+        gain -= see_slow(board, reply).max(0);
+    }
+    unsafe { board.unmake_move_unchecked(mv, u) };
+    gain
+}
+
 struct DepthCtx<'a> {
     spec: &'a DepthSpec,
     hash: u64,
@@ -214,6 +270,16 @@ impl<'a, W: Write> Tester<'a, W> {
             }
         }
 
+        if self.options.verify_see {
+            for mv in &moves {
+                let is_capture =
+                    mv.kind() == MoveKind::Enpassant || board.get(mv.dst()) != Cell::None;
+                if is_capture && unsafe { mv.is_legal_unchecked(&board) } {
+                    assert_eq!(board.see(*mv), see_slow(&mut board, *mv), "move {}", mv);
+                }
+            }
+        }
+
         if self.options.run_self_test {
             for mv in &moves {
                 if let Some(u) = unsafe { board.try_make_move_unchecked(*mv) } {
@@ -1,34 +1,12 @@
-use pawnyowl_board::{Board, Color, MoveGen, MoveList};
+use pawnyowl_board::{
+    Board, Color, MoveGen, MoveList,
+    perft::{self, PerftTable},
+};
 use std::str::FromStr;
 
 const HPERFT_WHITE: u64 = 142867;
 const HPERFT_BLACK: u64 = 285709;
 
-fn do_perft(b: &mut Board, depth: usize) -> u64 {
-    if depth == 0 {
-        return 1;
-    }
-    let move_gen = MoveGen::new(b);
-    let mut moves = MoveList::new();
-    move_gen.gen_all(&mut moves);
-    moves.retain(|m| unsafe { m.is_legal_unchecked(b) });
-    if depth == 1 {
-        moves.len() as u64
-    } else {
-        moves
-            .into_iter()
-            .map(|mv| {
-                let u = unsafe { b.make_move_unchecked(mv) };
-                let res = do_perft(b, depth - 1);
-                unsafe {
-                    b.unmake_move_unchecked(mv, u);
-                }
-                res
-            })
-            .sum()
-    }
-}
-
 fn do_hperft(b: &mut Board, depth: usize) -> u64 {
     if depth == 0 {
         let white: u64 = b.color(Color::White).flipped_rank().into();
@@ -64,7 +42,7 @@ pub struct Case {
 impl Case {
     pub fn run_perft(&self) {
         let mut b = Board::from_str(self.fen).unwrap();
-        assert_eq!(do_perft(&mut b, self.depth), self.perft);
+        assert_eq!(perft::perft(&mut b, self.depth), self.perft);
     }
 
     pub fn run_hperft(&self) {
@@ -331,6 +309,56 @@ pub const CASES: [Case; 36] = [
     },
 ];
 
+// Chess960 positions are set up from FENs with the rook files given explicitly (Shredder-FEN).
+const CASES_960: [(&str, usize, u64); 8] = [
+    (
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        5,
+        8146062,
+    ),
+    (
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        5,
+        16253601,
+    ),
+    (
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        5,
+        6417013,
+    ),
+    (
+        "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+        5,
+        9183776,
+    ),
+    (
+        "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+        4,
+        1171749,
+    ),
+    (
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w HAha - 0 1",
+        4,
+        4085603,
+    ),
+    (
+        "rk4r1/pppppppp/8/8/8/8/PPPPPPPP/RK4R1 w GAga - 0 1",
+        4,
+        316079,
+    ),
+    ("6kr/pppppppp/8/8/8/8/PPPPPPPP/6KR w Hh - 0 1", 4, 103853),
+];
+
+#[test]
+fn test_perft_960() {
+    for (fen, depth, count) in CASES_960 {
+        let mut b = Board::from_str(fen).unwrap();
+        assert!(b.raw().chess960);
+        assert_eq!(b.to_string(), fen);
+        assert_eq!(perft::perft(&mut b, depth), count, "{}", fen);
+    }
+}
+
 #[test]
 fn test_perft() {
     for case in &CASES {
@@ -338,6 +366,24 @@ fn test_perft() {
     }
 }
 
+#[test]
+fn test_perft_hashed() {
+    let mut tt = PerftTable::new(1 << 16);
+    for case in &CASES {
+        let mut b = Board::from_str(case.fen).unwrap();
+        assert_eq!(
+            perft::perft_hashed(&mut b, case.depth, &mut tt),
+            case.perft,
+            "{}",
+            case.name
+        );
+    }
+
+    let mut b =
+        Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    assert_eq!(perft::perft_hashed(&mut b, 6, &mut tt), 119060324);
+}
+
 #[test]
 fn test_hperft() {
     for case in &CASES {
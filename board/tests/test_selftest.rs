@@ -1,12 +1,15 @@
 mod selftest;
 
 use hex_literal::hex;
-use selftest::Tester;
+use selftest::{Options, Tester};
 use sha2::{Digest, Sha256};
+use std::io;
 
 const INPUT_DATA: &str = include_str!("boards.fen");
 const OUTPUT_HASH: [u8; 32] =
     hex!("1ac232af9c1ede66b0cf423c87838324b09d178a5721b2c4ded7d87540a96318");
+/// Number of the positions checked by the SEE test which is not ignored.
+const SEE_SAMPLE: usize = 1000;
 
 #[ignore]
 #[test]
@@ -16,3 +19,32 @@ fn test_selftest() {
     tester.run_many(&mut INPUT_DATA.as_bytes());
     assert_eq!(&hasher.finalize()[..], &OUTPUT_HASH[..]);
 }
+
+fn see_tester(sink: &mut io::Sink) -> Tester<'_, io::Sink> {
+    let options = Options {
+        big_depth: false,
+        dump_trace_chains: false,
+        run_self_test: false,
+        attack_heatmaps: false,
+        verify_see: true,
+    };
+    Tester::new(options, sink)
+}
+
+#[ignore]
+#[test]
+fn test_see() {
+    let mut sink = io::sink();
+    see_tester(&mut sink).run_many(&mut INPUT_DATA.as_bytes());
+}
+
+#[test]
+fn test_see_sample() {
+    let mut sink = io::sink();
+    let sample = INPUT_DATA
+        .lines()
+        .take(SEE_SAMPLE)
+        .collect::<Vec<_>>()
+        .join("\n");
+    see_tester(&mut sink).run_many(&mut sample.as_bytes());
+}
@@ -0,0 +1,94 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use pawnyowl::engine::movepick::{StagedMoveGen, capture_order_key};
+use pawnyowl_board::{Board, Move, MoveGen, MoveList};
+use std::str::FromStr;
+
+const BOARDS: [(&str, &str); 6] = [
+    (
+        "initial",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        "sicilian",
+        "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq - 0 11",
+    ),
+    (
+        "middle",
+        "1rq1r1k1/1p3ppp/pB3n2/3ppP2/Pbb1P3/1PN2B2/2P2QPP/R1R4K w - - 1 21",
+    ),
+    (
+        "open_position",
+        "4r1k1/3R1ppp/8/5P2/p7/6PP/4pK2/1rN1B3 w - - 4 43",
+    ),
+    ("pawn_attack", "4k3/8/8/pppppppp/PPPPPPPP/8/8/4K3 w - - 0 1"),
+    // Lots of captures on the same squares with batteries behind them, to stress SEE.
+    (
+        "tactical",
+        "2rq1rk1/1b1nbppp/pp1ppn2/4N3/2PP1B2/1PNBPQ2/P4PPP/2RR2K1 w - - 0 1",
+    ),
+];
+
+fn boards() -> impl Iterator<Item = (&'static str, Board)> {
+    BOARDS
+        .iter()
+        .map(|&(name, fen)| (name, Board::from_str(fen).unwrap()))
+}
+
+fn bench_see(c: &mut Criterion) {
+    let mut group = c.benchmark_group("see");
+    for (name, board) in boards() {
+        let mut moves = MoveList::new();
+        MoveGen::new(&board).gen_capture(&mut moves);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for mv in &moves {
+                    black_box(board.see(*mv));
+                }
+            })
+        });
+    }
+}
+
+fn bench_order_captures(c: &mut Criterion) {
+    let mut group = c.benchmark_group("order_captures");
+    for (name, board) in boards() {
+        let mut captures = MoveList::new();
+        MoveGen::new(&board).gen_capture(&mut captures);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut moves = captures.clone();
+                moves.sort_by_cached_key(|&mv| -capture_order_key(&board, mv));
+                black_box(moves.first().copied());
+            })
+        });
+    }
+}
+
+fn bench_staged_movegen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("staged_movegen");
+    for (name, board) in boards() {
+        group.bench_function(format!("{}_first", name), |b| {
+            b.iter(|| {
+                let mut staged = StagedMoveGen::new(Move::NULL, [Move::NULL; 2]);
+                black_box(staged.next(&board));
+            })
+        });
+        group.bench_function(format!("{}_all", name), |b| {
+            b.iter(|| {
+                let mut staged = StagedMoveGen::new(Move::NULL, [Move::NULL; 2]);
+                while let Some(mv) = staged.next(&board) {
+                    black_box(mv);
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(
+    movepick,
+    bench_see,
+    bench_order_captures,
+    bench_staged_movegen,
+);
+
+criterion_main!(movepick);
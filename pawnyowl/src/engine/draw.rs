@@ -0,0 +1,85 @@
+use pawnyowl_board::Board;
+
+/// Repetition policy over the position history tracked by the board.
+///
+/// A position repeated inside the search tree (i.e. its earlier occurrence is the root or
+/// comes after it) is scored as a draw on its second occurrence. Repetitions that reach back
+/// into the game history need the third occurrence, as the threefold rule requires.
+#[derive(Debug, Copy, Clone)]
+pub struct DrawDetector {
+    root: usize,
+}
+
+impl DrawDetector {
+    /// Creates the detector with `b` as the root of the search. The board must track its
+    /// history, see [`Board::track_history`].
+    pub fn new(b: &Board) -> Self {
+        DrawDetector {
+            root: b.history().map_or(0, <[u64]>::len),
+        }
+    }
+
+    pub fn is_draw(&self, b: &Board) -> bool {
+        let mut count = 0;
+        for i in b.repetitions() {
+            if i >= self.root {
+                return true;
+            }
+            count += 1;
+            if count == 2 {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHUFFLE: [&str; 4] = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+    fn play(b: &mut Board, ms: &[&str]) {
+        for mv in ms {
+            b.make_uci_move(mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_tree_vs_root() {
+        let mut b = Board::start();
+        b.track_history();
+        let d = DrawDetector::new(&b);
+        for mv in SHUFFLE {
+            assert!(!d.is_draw(&b));
+            b.make_uci_move(mv).unwrap();
+        }
+        assert!(d.is_draw(&b));
+        assert!(!b.is_repetition(3));
+
+        // The same twofold repetition in the game history is not a draw yet.
+        let d = DrawDetector::new(&b);
+        assert!(!d.is_draw(&b));
+
+        play(&mut b, &SHUFFLE);
+        let d = DrawDetector::new(&b);
+        assert!(d.is_draw(&b));
+        assert!(b.is_repetition(3));
+    }
+
+    #[test]
+    fn test_irreversible() {
+        let mut b = Board::start();
+        b.track_history();
+        play(&mut b, &["g1f3", "g8f6", "f3g1", "f6g8", "e2e3"]);
+        let d = DrawDetector::new(&b);
+        play(&mut b, &["g8f6", "g1f3", "f6g8"]);
+        assert!(!d.is_draw(&b));
+        let mv = pawnyowl_board::Move::from_uci_legal("f3g1", &b).unwrap();
+        let u = unsafe { b.make_move_unchecked(mv) };
+        assert!(d.is_draw(&b));
+        unsafe { b.unmake_move_unchecked(mv, u) };
+        assert!(!d.is_draw(&b));
+    }
+}
@@ -1,23 +1,147 @@
+pub mod draw;
+pub mod movepick;
+pub mod search;
+#[cfg(feature = "syzygy")]
+pub mod syzygy;
+pub mod time;
+pub mod tt;
+
+use crate::eval::{
+    model::{DynModel, Model, PsqModel},
+    nnue::{self, NnueModel},
+    score::{Score as EvalScore, Wdl},
+};
 use crate::intf::{
-    self, EngineMeta, Monitor, SearchConstraint, SearchResult,
-    opts::{Name, NameBuf, Opt, Val},
+    self, EngineMeta, Monitor, SearchConstraint, SearchParams, SearchResult,
+    opts::{Name, NameBuf, OPT_CHESS960, OPT_SHOW_SAN_PV, Opt, Val},
     score::{Bound, BoundedScore, Score},
 };
-use pawnyowl_board::{Board, File, Move, MoveKind, Rank, Sq};
+use anyhow::{Context, Result, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use pawnyowl_board::{Board, Move, MoveGen, MoveList};
+use search::{MAX_PLY, Searcher};
 use std::{
     collections::HashMap,
-    sync::mpsc::{self, RecvTimeoutError},
-    time::Duration,
+    time::{Duration, Instant},
 };
+#[cfg(feature = "syzygy")]
+use syzygy::Tablebase;
+use tt::TranspositionTable;
+
+const OPT_MOVE_OVERHEAD: &str = "Move Overhead";
+const OPT_HASH: &str = "Hash";
+const OPT_THREADS: &str = "Threads";
+const OPT_CONTEMPT: &str = "Contempt";
+const OPT_EVAL_DATA: &str = "EvalData";
+#[cfg(feature = "syzygy")]
+const OPT_SYZYGY_PATH: &str = "SyzygyPath";
+const OPT_RESET: &str = "Reset";
+
+const DEFAULT_HASH_MB: usize = 16;
 
 pub struct Engine {
     opts: HashMap<NameBuf, Opt>,
+    board: Board,
+    model: Box<dyn DynModel>,
+    tt: TranspositionTable,
+    #[cfg(feature = "syzygy")]
+    tablebase: Option<Tablebase>,
 }
 
 impl Engine {
     pub fn new() -> Engine {
+        let mut opts = HashMap::new();
+        opts.insert(
+            OPT_MOVE_OVERHEAD.into(),
+            Opt::Int {
+                val: 30,
+                min: Some(0),
+                max: Some(5000),
+            },
+        );
+        opts.insert(
+            OPT_HASH.into(),
+            Opt::Int {
+                val: DEFAULT_HASH_MB as i64,
+                min: Some(1),
+                max: Some(65536),
+            },
+        );
+        // The search is single-threaded for now, the value is only stored.
+        opts.insert(
+            OPT_THREADS.into(),
+            Opt::Int {
+                val: 1,
+                min: Some(1),
+                max: Some(1024),
+            },
+        );
+        opts.insert(
+            OPT_CONTEMPT.into(),
+            Opt::Int {
+                val: 0,
+                min: Some(-100),
+                max: Some(100),
+            },
+        );
+        opts.insert(OPT_CHESS960.into(), Opt::Bool { val: false });
+        opts.insert(OPT_SHOW_SAN_PV.into(), Opt::Bool { val: false });
+        opts.insert(OPT_EVAL_DATA.into(), Opt::Str { val: String::new() });
+        #[cfg(feature = "syzygy")]
+        opts.insert(OPT_SYZYGY_PATH.into(), Opt::Str { val: String::new() });
+        opts.insert(OPT_RESET.into(), Opt::Action);
         Engine {
-            opts: HashMap::new(),
+            opts,
+            board: Board::start(),
+            model: Box::new(PsqModel::new().with_pawn_cache()),
+            tt: TranspositionTable::new(DEFAULT_HASH_MB),
+            #[cfg(feature = "syzygy")]
+            tablebase: None,
+        }
+    }
+
+    /// Picks the move to ponder on from the transposition table, if the principal variation is
+    /// too short to contain one.
+    fn ponder_move(&self, best: Move) -> Move {
+        let mut board = self.board.clone();
+        if board.make_move(best).is_err() {
+            return Move::NULL;
+        }
+        match self.tt.probe(board.zobrist_hash(), 0) {
+            Some(e) if Move::from(e.best).validate(&board).is_ok() => e.best.into(),
+            _ => Move::NULL,
+        }
+    }
+
+    fn move_overhead(&self) -> Duration {
+        match self.opts[<&Name>::from(OPT_MOVE_OVERHEAD)] {
+            Opt::Int { val, .. } => Duration::from_millis(val as u64),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Time budget of the search, with the move overhead already subtracted.
+    fn search_time(&self, c: &SearchConstraint) -> Option<Duration> {
+        time::search_time(c, self.board.side(), self.move_overhead())
+    }
+
+    /// Keeps only the root moves that preserve the tablebase outcome, and returns this outcome.
+    #[cfg(feature = "syzygy")]
+    fn probe_root(&self, moves: &mut MoveList) -> Option<Wdl> {
+        self.tablebase
+            .as_ref()?
+            .filter_root_moves(&self.board, moves)
+    }
+
+    #[cfg(not(feature = "syzygy"))]
+    fn probe_root(&self, _moves: &mut MoveList) -> Option<Wdl> {
+        None
+    }
+
+    fn contempt(&self) -> EvalScore {
+        match self.opts[<&Name>::from(OPT_CONTEMPT)] {
+            Opt::Int { val, .. } => EvalScore::new(val as i16),
+            _ => unreachable!(),
         }
     }
 }
@@ -40,53 +164,607 @@ impl intf::Engine for Engine {
         &self.opts
     }
 
-    fn set_opt(&mut self, name: &Name, val: Val) {
-        self.opts.get_mut(name).unwrap().set(val).unwrap();
+    fn set_opt(&mut self, name: &Name, val: Val) -> Result<()> {
+        if !self.opts.contains_key(name) {
+            bail!("unknown option \"{}\"", name.as_str());
+        }
+        if name == <&Name>::from(OPT_RESET) {
+            self.on_new_game();
+        }
+        if name == <&Name>::from(OPT_HASH) {
+            // Validate the size before allocating the table.
+            self.opts.get_mut(name).unwrap().set(val)?;
+            let Opt::Int { val: size, .. } = self.opts[name] else {
+                unreachable!();
+            };
+            self.tt = TranspositionTable::new(size as usize);
+            return Ok(());
+        }
+        if name == <&Name>::from(OPT_EVAL_DATA) {
+            let Val::Str(data) = &val else {
+                unreachable!();
+            };
+            self.model = if data.is_empty() {
+                Box::new(PsqModel::new().with_pawn_cache())
+            } else {
+                let data = BASE64.decode(data).context("decoding base64")?;
+                if data.starts_with(nnue::NNUE_MAGIC) {
+                    Box::new(NnueModel::load(&data).context("loading network")?)
+                } else {
+                    Box::new(
+                        PsqModel::load(&data)
+                            .context("loading model")?
+                            .with_pawn_cache(),
+                    )
+                }
+            };
+        }
+        #[cfg(feature = "syzygy")]
+        if name == <&Name>::from(OPT_SYZYGY_PATH) {
+            let Val::Str(path) = &val else {
+                unreachable!();
+            };
+            self.tablebase = if path.is_empty() {
+                None
+            } else {
+                Some(Tablebase::new(path).context("loading tablebases")?)
+            };
+        }
+        self.opts.get_mut(name).unwrap().set(val)
     }
 
     fn set_debug(&mut self, _value: bool) {}
 
-    fn on_new_game(&mut self) {}
+    fn on_new_game(&mut self) {
+        self.tt.clear();
+        self.model.clear_cache();
+        // Drops the history of the previous game.
+        self.set_position(&Board::start(), &[]);
+    }
 
     fn set_position(&mut self, b: &Board, ms: &[Move]) {
-        (_, _) = (b, ms);
-    }
-
-    fn search(&mut self, c: SearchConstraint, mon: &dyn Monitor) -> SearchResult {
-        _ = c;
-        let mv = Move::new(
-            MoveKind::PawnDouble,
-            Sq::make(File::E, Rank::R2),
-            Sq::make(File::E, Rank::R4),
-        )
-        .unwrap();
-        let (stop_send, stop) = mpsc::channel();
-        mon.register_on_stop(Box::new(move || {
-            let _ = stop_send.send(());
-        }));
-        for i in 1..=5 {
-            match stop.recv_timeout(Duration::from_secs(2)) {
-                Ok(_) => break,
-                Err(RecvTimeoutError::Timeout) => {}
-                Err(RecvTimeoutError::Disconnected) => panic!("must not happen"),
+        // The search detects the repetitions by the history of the board.
+        self.board = b.clone();
+        self.board.track_history();
+        for mv in ms {
+            self.board.make_move(*mv).unwrap();
+        }
+    }
+
+    fn search(&mut self, p: &SearchParams, mon: &dyn Monitor) -> SearchResult {
+        let search_time = self.search_time(&p.constraint);
+        let deadline = search_time.map(|t| Instant::now() + t);
+        let contempt = self.contempt();
+        let max_depth = match p.constraint {
+            SearchConstraint::FixedDepth(depth) => depth.clamp(1, MAX_PLY),
+            // Mate in N moves is found by searching 2N - 1 plies.
+            SearchConstraint::Mate(moves) => {
+                (2 * moves as usize).saturating_sub(1).clamp(1, MAX_PLY)
             }
+            _ => MAX_PLY,
+        };
+        let mut moves = MoveList::new();
+        MoveGen::new(&self.board).gen_legal(&mut moves);
+        if moves.iter().any(|mv| p.search_moves.contains(mv)) {
+            moves.retain(|mv| p.search_moves.contains(mv));
+        }
+        let tb_wdl = self.probe_root(&mut moves);
+        let mut res = SearchResult {
+            best: moves.first().copied().unwrap_or(Move::NULL),
+            ponder: Move::NULL,
+        };
+        if moves.is_empty() {
+            return res;
+        }
+
+        let mut searcher =
+            Searcher::new(&self.board, &*self.model, &mut self.tt, Some(mon), deadline);
+        searcher.set_root_moves(&moves);
+        searcher.set_contempt(contempt);
+        if p.ponder {
+            searcher.set_ponder(search_time);
+        }
+        if let SearchConstraint::FixedNodes(nodes) = p.constraint {
+            searcher.set_node_limit(Some(nodes));
+        }
+        for depth in 1..=max_depth {
+            let Some((score, pv)) = searcher.search_root(depth) else {
+                break;
+            };
+            let mut score = search::to_uci_score(score);
+            // The tablebase outcome is known beyond the search horizon, but a found mate is more
+            // precise.
+            if let Some(wdl) = tb_wdl
+                && !matches!(score, Score::Mate { .. })
+            {
+                score = search::to_uci_score(EvalScore::from_wdl(wdl, 0));
+            }
+            res.best = pv[0];
+            res.ponder = pv.get(1).copied().unwrap_or(Move::NULL);
+            mon.report_best(res.best);
             mon.report_info(&intf::SearchInfo {
-                depth: i,
-                pv: vec![mv],
+                depth,
+                seldepth: searcher.seldepth().max(depth),
+                pv,
                 score: BoundedScore {
-                    score: Score::Cp(42),
+                    score,
                     bound: Bound::Exact,
                 },
-                nodes: None,
+                nodes: Some(searcher.nodes()),
+                hashfull: Some(searcher.hashfull()),
             });
+            if let SearchConstraint::Mate(max_moves) = p.constraint
+                && let Score::Mate { moves, win: true } = score
+                && moves <= max_moves
+            {
+                break;
+            }
         }
-        SearchResult {
-            best: mv,
-            ponder: Move::NULL,
+        mon.report_nodes(searcher.nodes());
+        drop(searcher);
+        // While pondering, the best move must not be reported until `ponderhit` or `stop`.
+        if p.ponder {
+            mon.wait_ponder();
+        }
+        if res.ponder == Move::NULL {
+            res.ponder = self.ponder_move(res.best);
         }
+        res
+    }
+
+    fn eval(&mut self) -> Vec<String> {
+        let tag = self.model.build_tag(&self.board);
+        let mut res: Vec<_> = self
+            .model
+            .explain(&tag, &self.board)
+            .into_iter()
+            .map(|(name, val)| format!("{} {}", name, val.value()))
+            .collect();
+        // The model scores the position from White's point of view.
+        let score = self.model.apply(&tag, &self.board);
+        res.push(format!("total {} (white side)", score.value()));
+        res
     }
 
     fn q_search(&mut self) -> Score {
-        Score::Cp(42)
+        let mut searcher = Searcher::new(&self.board, &*self.model, &mut self.tt, None, None);
+        search::to_uci_score(searcher.quiesce_root())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intf::{Engine as _, SearchInfo, StopCallback};
+    use std::{
+        str::FromStr,
+        sync::{
+            Condvar, Mutex,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread,
+    };
+
+    #[derive(Default)]
+    struct PonderState {
+        pondering: bool,
+        /// Set while the search waits for the ponder hit.
+        waiting: bool,
+    }
+
+    #[derive(Default)]
+    struct TestMonitor {
+        callbacks: Mutex<Vec<StopCallback>>,
+        best: Mutex<Vec<Move>>,
+        infos: Mutex<Vec<SearchInfo>>,
+        nodes: Mutex<Option<u64>>,
+        ponder: Mutex<PonderState>,
+        ponder_cond: Condvar,
+    }
+
+    impl TestMonitor {
+        fn ponder_hit(&self) {
+            self.ponder.lock().unwrap().pondering = false;
+            self.ponder_cond.notify_all();
+        }
+
+        /// Blocks until the search starts waiting for the ponder hit.
+        fn wait_until_waiting(&self) {
+            let state = self.ponder.lock().unwrap();
+            drop(self.ponder_cond.wait_while(state, |s| !s.waiting).unwrap());
+        }
+    }
+
+    impl Monitor for TestMonitor {
+        fn is_stopped(&self) -> bool {
+            false
+        }
+
+        fn is_pondering(&self) -> bool {
+            self.ponder.lock().unwrap().pondering
+        }
+
+        fn wait_ponder(&self) {
+            let mut state = self.ponder.lock().unwrap();
+            state.waiting = true;
+            self.ponder_cond.notify_all();
+            let mut state = self.ponder_cond.wait_while(state, |s| s.pondering).unwrap();
+            state.waiting = false;
+        }
+
+        fn register_on_stop(&self, callback: StopCallback) {
+            self.callbacks.lock().unwrap().push(callback);
+        }
+
+        fn report_str(&self, _s: &str) {}
+        fn report_info(&self, i: &SearchInfo) {
+            self.infos.lock().unwrap().push(i.clone());
+        }
+
+        fn report_nodes(&self, nodes: u64) {
+            *self.nodes.lock().unwrap() = Some(nodes);
+        }
+        fn report_cur_move(&self, _m: Move, _num: usize) {}
+
+        fn report_best(&self, m: Move) {
+            self.best.lock().unwrap().push(m);
+        }
+    }
+
+    #[test]
+    fn test_move_overhead() {
+        let mut engine = Engine::new();
+        let c = SearchConstraint::FixedTime(Duration::from_millis(1000));
+        assert_eq!(engine.search_time(&c), Some(Duration::from_millis(970)));
+        engine
+            .set_opt(OPT_MOVE_OVERHEAD.into(), Val::Int(100))
+            .unwrap();
+        assert_eq!(engine.search_time(&c), Some(Duration::from_millis(900)));
+        assert_eq!(engine.search_time(&SearchConstraint::Infinite), None);
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.tt.capacity(), (DEFAULT_HASH_MB << 20) / 16);
+        engine.set_opt(OPT_HASH.into(), Val::Int(64)).unwrap();
+        assert_eq!(engine.tt.capacity(), (64 << 20) / 16);
+        assert!(engine.set_opt(OPT_HASH.into(), Val::Int(0)).is_err());
+        assert_eq!(engine.tt.capacity(), (64 << 20) / 16);
+        engine.set_opt(OPT_THREADS.into(), Val::Int(4)).unwrap();
+        assert!(engine.set_opt("NoSuchOption".into(), Val::Int(1)).is_err());
+    }
+
+    #[cfg(feature = "syzygy")]
+    #[test]
+    fn test_syzygy_path() {
+        let mut engine = Engine::new();
+        assert!(
+            engine
+                .set_opt(
+                    OPT_SYZYGY_PATH.into(),
+                    Val::Str("/nonexistent/pawnyowl-syzygy".into())
+                )
+                .is_err()
+        );
+        assert!(engine.tablebase.is_none());
+
+        // Without the tables, the search is not affected.
+        let dir = std::env::temp_dir().join(format!("pawnyowl-engine-tb-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        engine
+            .set_opt(
+                OPT_SYZYGY_PATH.into(),
+                Val::Str(dir.to_str().unwrap().into()),
+            )
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(engine.tablebase.as_ref().unwrap().max_pieces(), 0);
+        engine.set_position(
+            &Board::from_str("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").unwrap(),
+            &[],
+        );
+        let res = engine.search(
+            &SearchConstraint::FixedDepth(4).into(),
+            &TestMonitor::default(),
+        );
+        assert_ne!(res.best, Move::NULL);
+
+        engine
+            .set_opt(OPT_SYZYGY_PATH.into(), Val::Str(String::new()))
+            .unwrap();
+        assert!(engine.tablebase.is_none());
+    }
+
+    #[test]
+    fn test_eval_data() {
+        use crate::eval::{layers::feature::ScorePair, score::Score as EvalScore};
+        use pawnyowl_board::{Cell, Sq};
+
+        let model = PsqModel::psq_only(|cell, _| match cell {
+            Cell::WhitePawn => ScorePair::new(EvalScore::new(10), EvalScore::new(10)),
+            _ => ScorePair::default(),
+        });
+        let data = BASE64.encode(model.to_bytes().unwrap());
+
+        let mut engine = Engine::new();
+        let default_score = engine.q_search();
+        engine
+            .set_opt(OPT_EVAL_DATA.into(), Val::Str(data.clone()))
+            .unwrap();
+        assert_eq!(engine.q_search(), Score::Cp(80));
+
+        assert!(
+            engine
+                .set_opt(OPT_EVAL_DATA.into(), Val::Str("not base64!".into()))
+                .is_err()
+        );
+        let mut bad = model.to_bytes().unwrap();
+        bad[0] = b'X';
+        assert!(
+            engine
+                .set_opt(OPT_EVAL_DATA.into(), Val::Str(BASE64.encode(bad)))
+                .is_err()
+        );
+        assert_eq!(engine.q_search(), Score::Cp(80));
+        assert_eq!(
+            engine.opts()[<&Name>::from(OPT_EVAL_DATA)],
+            Opt::Str { val: data }
+        );
+
+        let mut weights = vec![0; nnue::INPUTS * nnue::HIDDEN];
+        for sq in Sq::iter() {
+            weights[NnueModel::input_index(Cell::WhitePawn, sq) * nnue::HIDDEN] = 10;
+        }
+        let mut output = vec![0; nnue::HIDDEN];
+        output[0] = 64;
+        let network = NnueModel::from_weights(weights, vec![0; nnue::HIDDEN], output, 0).unwrap();
+        engine
+            .set_opt(
+                OPT_EVAL_DATA.into(),
+                Val::Str(BASE64.encode(network.to_bytes().unwrap())),
+            )
+            .unwrap();
+        assert_eq!(engine.q_search(), Score::Cp(125));
+
+        engine
+            .set_opt(OPT_EVAL_DATA.into(), Val::Str(String::new()))
+            .unwrap();
+        assert_eq!(engine.q_search(), default_score);
+    }
+
+    #[test]
+    fn test_q_search() {
+        use crate::eval::{layers::feature::ScorePair, score::Score as EvalScore};
+        use pawnyowl_board::Cell;
+
+        let pawn = ScorePair::new(EvalScore::new(100), EvalScore::new(100));
+        let mut engine = Engine::new();
+        engine.model = Box::new(PsqModel::psq_only(|cell, _| match cell {
+            Cell::WhitePawn => pawn,
+            Cell::BlackPawn => pawn * -1,
+            _ => ScorePair::default(),
+        }));
+        for fen in [
+            "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1",
+            "4k3/8/8/3p4/4P3/8/8/4K3 b - - 0 1",
+        ] {
+            engine.set_position(&Board::from_str(fen).unwrap(), &[]);
+            assert_eq!(engine.q_search(), Score::Cp(100), "{}", fen);
+        }
+        for (fen, score) in [
+            ("4k3/8/8/2p1p3/3P4/8/8/4K3 w - - 0 1", 0),
+            ("4k3/8/2p5/3p4/4P3/8/8/4K3 w - - 0 1", -100),
+        ] {
+            engine.set_position(&Board::from_str(fen).unwrap(), &[]);
+            assert_eq!(engine.q_search(), Score::Cp(score), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut fresh = Engine::new();
+        let expected = fresh.search(
+            &SearchConstraint::FixedDepth(1).into(),
+            &TestMonitor::default(),
+        );
+
+        let mut engine = Engine::new();
+        let b = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let mut ms = Vec::new();
+        let mut cur = b.clone();
+        for s in ["a1a2", "e8d8", "a2a1", "d8e8"] {
+            let mv = Move::from_uci_legal(s, &cur).unwrap();
+            cur.make_move(mv).unwrap();
+            ms.push(mv);
+        }
+        engine.set_position(&b, &ms);
+        engine.search(
+            &SearchConstraint::FixedDepth(3).into(),
+            &TestMonitor::default(),
+        );
+        assert!(engine.tt.probe(cur.zobrist_hash(), 0).is_some());
+        engine.set_opt(OPT_RESET.into(), Val::Action).unwrap();
+        assert!(engine.tt.probe(cur.zobrist_hash(), 0).is_none());
+        assert_eq!(engine.board.history(), Some(&[][..]));
+        assert_eq!(engine.q_search(), fresh.q_search());
+        let res = engine.search(
+            &SearchConstraint::FixedDepth(1).into(),
+            &TestMonitor::default(),
+        );
+        assert_eq!(res.best, expected.best);
+    }
+
+    #[test]
+    fn test_report_best() {
+        let mut engine = Engine::new();
+        let mon = TestMonitor::default();
+        let res = engine.search(&SearchConstraint::FixedDepth(3).into(), &mon);
+        let best = mon.best.into_inner().unwrap();
+        assert!(best.len() >= 3);
+        assert_eq!(best.last(), Some(&res.best));
+    }
+
+    #[test]
+    fn test_ponder() {
+        let mut engine = Engine::new();
+        let mon = TestMonitor {
+            ponder: Mutex::new(PonderState {
+                pondering: true,
+                waiting: false,
+            }),
+            ..Default::default()
+        };
+        let params = SearchParams {
+            ponder: true,
+            ..SearchConstraint::FixedDepth(2).into()
+        };
+        let returned = AtomicBool::new(false);
+        let res = thread::scope(|s| {
+            s.spawn(|| {
+                // The search is complete, but it must not return before the ponder hit.
+                mon.wait_until_waiting();
+                assert_eq!(mon.infos.lock().unwrap().len(), 2);
+                assert!(!returned.load(Ordering::SeqCst));
+                mon.ponder_hit();
+            });
+            let res = engine.search(&params, &mon);
+            returned.store(true, Ordering::SeqCst);
+            res
+        });
+        assert!(!mon.is_pondering());
+        let mut b = Board::start();
+        b.make_move(res.best).unwrap();
+        assert!(res.ponder.validate(&b).is_ok());
+    }
+
+    #[test]
+    fn test_fixed_nodes() {
+        let mut engine = Engine::new();
+        let mon = TestMonitor::default();
+        let res = engine.search(&SearchConstraint::FixedNodes(20000).into(), &mon);
+        let nodes = mon.nodes.into_inner().unwrap().unwrap();
+        assert!((20000..20000 + 1024).contains(&nodes), "{}", nodes);
+        let infos = mon.infos.into_inner().unwrap();
+        assert!(infos.len() >= 2);
+        assert!(infos.iter().all(|i| i.nodes.unwrap() < 20000));
+        assert_eq!(infos.last().unwrap().pv[0], res.best);
+    }
+
+    #[test]
+    fn test_mate() {
+        let mut engine = Engine::new();
+        let b = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        engine.set_position(&b, &[]);
+        let mon = TestMonitor::default();
+        let res = engine.search(&SearchConstraint::FixedDepth(3).into(), &mon);
+        assert_eq!(res.best.to_string(), "a1a8");
+        let infos = mon.infos.into_inner().unwrap();
+        assert_eq!(infos.len(), 3);
+        for (depth, info) in infos.iter().enumerate() {
+            assert_eq!(info.depth, depth + 1);
+            assert!(info.seldepth >= info.depth);
+            assert_eq!(info.pv[0], res.best);
+            assert_eq!(
+                info.score.score,
+                Score::Mate {
+                    moves: 1,
+                    win: true
+                }
+            );
+            assert!(info.nodes.unwrap() > 0);
+        }
+    }
+
+    #[test]
+    fn test_contempt() {
+        use crate::eval::layers::feature::ScorePair;
+        use pawnyowl_board::Cell;
+
+        let queen = ScorePair::new(EvalScore::new(900), EvalScore::new(900));
+        let model = PsqModel::psq_only(|cell, _| match cell {
+            Cell::WhiteQueen => queen,
+            Cell::BlackQueen => queen * -1,
+            _ => ScorePair::default(),
+        });
+
+        // Black is two queens up, but White has a perpetual check.
+        let b = Board::from_str("7k/6p1/8/8/8/8/qq4PP/3Q3K w - - 0 1").unwrap();
+        for (contempt, score) in [(0, 0), (30, -30), (-30, 30)] {
+            let mut engine = Engine::new();
+            engine.model = Box::new(model.clone());
+            engine
+                .set_opt(OPT_CONTEMPT.into(), Val::Int(contempt))
+                .unwrap();
+            engine.set_position(&b, &[]);
+            let mon = TestMonitor::default();
+            let res = engine.search(&SearchConstraint::FixedDepth(6).into(), &mon);
+            assert_eq!(res.best.to_string(), "d1h5");
+            let infos = mon.infos.into_inner().unwrap();
+            assert_eq!(infos.last().unwrap().score.score, Score::Cp(score));
+        }
+    }
+
+    #[test]
+    fn test_mate_search() {
+        let mut engine = Engine::new();
+        let b = Board::from_str("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1").unwrap();
+        engine.set_position(&b, &[]);
+        let mon = TestMonitor::default();
+        engine.search(&SearchConstraint::Mate(3).into(), &mon);
+        let infos = mon.infos.into_inner().unwrap();
+        let last = infos.last().unwrap();
+        assert!(last.depth <= 5);
+        let mate = Score::Mate {
+            moves: 3,
+            win: true,
+        };
+        assert_eq!(last.score.score, mate);
+
+        // There is no mate in two, so the search stops at depth 3.
+        let mon = TestMonitor::default();
+        engine.search(&SearchConstraint::Mate(2).into(), &mon);
+        let infos = mon.infos.into_inner().unwrap();
+        assert_eq!(infos.len(), 3);
+        assert!(!matches!(
+            infos[2].score.score,
+            Score::Mate {
+                moves: ..=2,
+                win: true
+            }
+        ));
+    }
+
+    #[test]
+    fn test_switch_model() {
+        use crate::eval::score::Score as EvalScore;
+        use pawnyowl_board::moves::RawUndo;
+
+        struct ConstModel;
+
+        impl Model for ConstModel {
+            type Tag = ();
+
+            fn new() -> Self {
+                ConstModel
+            }
+
+            fn build_tag(&self, _board: &Board) {}
+
+            unsafe fn after_move(&self, _tag: &mut (), _board: &Board, _mv: Move, _u: &RawUndo) {}
+
+            fn apply(&self, _tag: &(), _board: &Board) -> EvalScore {
+                EvalScore::new(123)
+            }
+        }
+
+        let mut engine = Engine::new();
+        let default_score = engine.q_search();
+        engine.model = Box::new(ConstModel::new());
+        assert_eq!(engine.q_search(), Score::Cp(123));
+        engine
+            .set_opt(OPT_EVAL_DATA.into(), Val::Str(String::new()))
+            .unwrap();
+        assert_eq!(engine.q_search(), default_score);
     }
 }
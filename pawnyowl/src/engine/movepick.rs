@@ -0,0 +1,206 @@
+use pawnyowl_board::{Board, Move, MoveGen, MoveList, board::PIECE_VALUES};
+
+/// Ordering key for captures and promotions: most valuable victim first, then least valuable
+/// attacker. Quiet moves get zero.
+pub fn capture_order_key(b: &Board, mv: Move) -> i32 {
+    let victim = mv.captured(b).map_or(0, |p| PIECE_VALUES[p.index()]);
+    let promote = mv.kind().promote().map_or(0, |p| PIECE_VALUES[p.index()]);
+    if victim == 0 && promote == 0 {
+        return 0;
+    }
+    let attacker = b
+        .get(mv.src())
+        .piece()
+        .map_or(0, |p| PIECE_VALUES[p.index()]);
+    16 * (victim + promote) - attacker / 100
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenCaptures,
+    GoodCaptures,
+    Killers,
+    GenQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Move generator which yields the moves in stages: the TT move, the captures and promotions
+/// which do not lose material, the killers, the remaining quiet moves, and finally the losing
+/// captures. Each stage is generated only when the previous ones are exhausted, so a cutoff on
+/// an early move saves the work on the later ones.
+///
+/// The moves are pseudo-legal, and each move is yielded at most once. The board passed to
+/// [`StagedMoveGen::next`] must be the same on each call.
+pub struct StagedMoveGen {
+    stage: Stage,
+    tt_move: Move,
+    killers: [Move; 2],
+    moves: MoveList,
+    bad_captures: MoveList,
+    idx: usize,
+}
+
+impl StagedMoveGen {
+    pub fn new(tt_move: Move, killers: [Move; 2]) -> Self {
+        StagedMoveGen {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            moves: MoveList::new(),
+            bad_captures: MoveList::new(),
+            idx: 0,
+        }
+    }
+
+    fn is_killer(&self, mv: Move) -> bool {
+        self.killers.contains(&mv)
+    }
+
+    pub fn next(&mut self, b: &Board) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenCaptures;
+                    if self.tt_move != Move::NULL && self.tt_move.is_semilegal(b) {
+                        return Some(self.tt_move);
+                    }
+                    self.tt_move = Move::NULL;
+                }
+                Stage::GenCaptures => {
+                    let movegen = MoveGen::new(b);
+                    movegen.gen_capture(&mut self.moves);
+                    movegen.gen_simple_promote(&mut self.moves);
+                    self.moves
+                        .sort_by_cached_key(|&mv| -capture_order_key(b, mv));
+                    self.idx = 0;
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    let Some(&mv) = self.moves.get(self.idx) else {
+                        self.stage = Stage::Killers;
+                        self.idx = 0;
+                        continue;
+                    };
+                    self.idx += 1;
+                    if mv == self.tt_move {
+                        continue;
+                    }
+                    if b.see(mv) < 0 {
+                        self.bad_captures.push(mv);
+                        continue;
+                    }
+                    return Some(mv);
+                }
+                Stage::Killers => {
+                    let Some(&mv) = self.killers.get(self.idx) else {
+                        self.stage = Stage::GenQuiets;
+                        continue;
+                    };
+                    self.idx += 1;
+                    if mv == Move::NULL
+                        || mv == self.tt_move
+                        || self.killers[..self.idx - 1].contains(&mv)
+                        || !mv.is_semilegal(b)
+                        || !mv.is_quiet(b)
+                    {
+                        continue;
+                    }
+                    return Some(mv);
+                }
+                Stage::GenQuiets => {
+                    self.moves.clear();
+                    MoveGen::new(b).gen_simple_no_promote(&mut self.moves);
+                    self.idx = 0;
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    let Some(&mv) = self.moves.get(self.idx) else {
+                        self.stage = Stage::BadCaptures;
+                        self.idx = 0;
+                        continue;
+                    };
+                    self.idx += 1;
+                    if mv == self.tt_move || self.is_killer(mv) {
+                        continue;
+                    }
+                    return Some(mv);
+                }
+                Stage::BadCaptures => {
+                    let Some(&mv) = self.bad_captures.get(self.idx) else {
+                        self.stage = Stage::Done;
+                        continue;
+                    };
+                    self.idx += 1;
+                    return Some(mv);
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn collect(b: &Board, tt_move: Move, killers: [Move; 2]) -> Vec<Move> {
+        let mut staged = StagedMoveGen::new(tt_move, killers);
+        let mut moves = Vec::new();
+        while let Some(mv) = staged.next(b) {
+            moves.push(mv);
+        }
+        moves
+    }
+
+    fn sorted(mut moves: Vec<Move>) -> Vec<Move> {
+        moves.sort_by_key(|m| (m.src().index(), m.dst().index(), m.kind() as u8));
+        moves
+    }
+
+    #[test]
+    fn test_all_moves_once() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1r5k/2P5/8/8/8/8/8/K7 w - - 0 1",
+            "4k3/4r3/8/2n5/8/8/4R3/4K3 w - - 0 1",
+            "8/8/8/KPp4r/8/8/8/6k1 w - c6 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mut all = MoveList::new();
+            MoveGen::new(&b).gen_all(&mut all);
+            let expected = sorted(all.to_vec());
+
+            assert_eq!(sorted(collect(&b, Move::NULL, [Move::NULL; 2])), expected);
+            for &tt_move in &all {
+                let killers = [all[0], all[all.len() - 1]];
+                let moves = collect(&b, tt_move, killers);
+                assert_eq!(moves[0], tt_move, "{}", fen);
+                assert_eq!(sorted(moves), expected, "{} {}", fen, tt_move);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stages() {
+        let b = Board::from_str("4k3/4p3/3p4/4n3/2q5/1P3N2/8/R2RK3 w - - 0 1").unwrap();
+        let mv = |s| Move::from_uci_legal(s, &b).unwrap();
+        let (tt_move, killer) = (mv("a1a7"), mv("e1d2"));
+        // A killer from another position is skipped.
+        let foreign = Move::from_uci_legal("e2e4", &Board::start()).unwrap();
+        let moves = collect(&b, tt_move, [foreign, killer]);
+
+        assert_eq!(moves[0], tt_move);
+        assert_eq!(&moves[1..3], &[mv("b3c4"), mv("f3e5")]);
+        assert_eq!(moves[3], killer);
+        // The rook takes a protected pawn, which loses material.
+        assert_eq!(moves.last(), Some(&mv("d1d6")));
+        assert!(!moves.contains(&foreign));
+        assert_eq!(moves.iter().filter(|&&m| m == tt_move).count(), 1);
+        assert_eq!(moves.iter().filter(|&&m| m == killer).count(), 1);
+    }
+}
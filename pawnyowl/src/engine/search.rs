@@ -0,0 +1,528 @@
+use super::{
+    draw::DrawDetector,
+    movepick::{StagedMoveGen, capture_order_key},
+    tt::TranspositionTable,
+};
+use crate::eval::{
+    model::{DynModel, DynTag},
+    score::Score,
+};
+use crate::intf::{
+    Monitor,
+    score::{Bound, Score as UciScore},
+};
+use pawnyowl_board::{
+    Board, Color, Move, MoveGen, MoveList, Piece, board::PIECE_VALUES, moves::RawUndo,
+};
+use std::time::{Duration, Instant};
+
+pub const MAX_PLY: usize = 64;
+
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Null move search is not tried at smaller depths.
+const NULL_MOVE_MIN_DEPTH: usize = 3;
+
+/// Captures that leave the score this much below alpha are not searched in quiescence.
+const DELTA_MARGIN: i32 = 200;
+
+/// Converts the search score into the UCI one, turning mate distances from plies into moves.
+pub fn to_uci_score(score: Score) -> UciScore {
+    let (val, bound) = (score.value() as i32, -Score::mate_bound().value() as i32);
+    let max = Score::max().value() as i32;
+    if val > bound {
+        let plies = (max - 1 - val) as u32;
+        UciScore::Mate {
+            moves: plies.div_ceil(2),
+            win: true,
+        }
+    } else if val < -bound {
+        let plies = (val + max - 1) as u32;
+        UciScore::Mate {
+            moves: plies / 2,
+            win: false,
+        }
+    } else {
+        UciScore::Cp(val)
+    }
+}
+
+/// Negamax alpha-beta search with quiescence over a single root position.
+///
+/// The searcher owns copies of the board and the repetition detector, so an aborted search
+/// leaves the engine state untouched. Evaluation tags are kept per ply and updated
+/// incrementally on each move.
+pub struct Searcher<'a> {
+    board: Board,
+    draws: DrawDetector,
+    model: &'a dyn DynModel,
+    tt: &'a mut TranspositionTable,
+    mon: Option<&'a dyn Monitor>,
+    deadline: Option<Instant>,
+    pondering: bool,
+    ponder_time: Option<Duration>,
+    tags: Vec<DynTag>,
+    null_move: bool,
+    root_moves: Vec<Move>,
+    node_limit: Option<u64>,
+    contempt: Score,
+    nodes: u64,
+    seldepth: usize,
+    stopped: bool,
+    prev_pv: Vec<Move>,
+    killers: [[Move; 2]; MAX_PLY],
+    pv: Vec<[Move; MAX_PLY]>,
+    pv_len: [usize; MAX_PLY + 1],
+}
+
+impl<'a> Searcher<'a> {
+    pub fn new(
+        board: &Board,
+        model: &'a dyn DynModel,
+        tt: &'a mut TranspositionTable,
+        mon: Option<&'a dyn Monitor>,
+        deadline: Option<Instant>,
+    ) -> Self {
+        let mut own_board = board.clone();
+        own_board.track_history();
+        Searcher {
+            draws: DrawDetector::new(&own_board),
+            board: own_board,
+            model,
+            tt,
+            mon,
+            deadline,
+            pondering: false,
+            ponder_time: None,
+            tags: (0..=MAX_PLY).map(|_| model.build_tag(board)).collect(),
+            null_move: true,
+            root_moves: Vec::new(),
+            node_limit: None,
+            contempt: Score::new(0),
+            nodes: 0,
+            seldepth: 0,
+            stopped: false,
+            prev_pv: Vec::new(),
+            killers: [[Move::NULL; 2]; MAX_PLY],
+            pv: vec![[Move::NULL; MAX_PLY]; MAX_PLY],
+            pv_len: [0; MAX_PLY + 1],
+        }
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Maximum ply reached during the last iteration.
+    pub fn seldepth(&self) -> usize {
+        self.seldepth
+    }
+
+    pub fn hashfull(&self) -> u16 {
+        self.tt.hashfull()
+    }
+
+    /// Restricts the moves searched at the root. An empty list means all the legal moves.
+    pub fn set_root_moves(&mut self, moves: &[Move]) {
+        self.root_moves = moves.to_vec();
+    }
+
+    /// Stops the search after visiting roughly the given number of nodes. The limit is checked
+    /// together with the time, so it may be exceeded by less than `CHECK_INTERVAL` nodes.
+    pub fn set_node_limit(&mut self, limit: Option<u64>) {
+        self.node_limit = limit;
+    }
+
+    /// Makes the side to move at the root treat a draw as worse than equal by `contempt`.
+    pub fn set_contempt(&mut self, contempt: Score) {
+        self.contempt = contempt;
+    }
+
+    /// Starts the search in pondering mode, without any deadline. Once the monitor reports that
+    /// pondering is over, the search is limited by `time`, counted from that moment.
+    pub fn set_ponder(&mut self, time: Option<Duration>) {
+        self.pondering = true;
+        self.ponder_time = time;
+        self.deadline = None;
+    }
+
+    /// Enables or disables null move pruning, which is enabled by default.
+    pub fn set_null_move(&mut self, enabled: bool) {
+        self.null_move = enabled;
+    }
+
+    /// Searches the root position to the given depth. The moves of the principal variation from
+    /// the previous iteration are tried first.
+    ///
+    /// Returns `None` if the search was stopped before the iteration completed.
+    pub fn search_root(&mut self, depth: usize) -> Option<(Score, Vec<Move>)> {
+        self.seldepth = 0;
+        let score = self.search(
+            depth.min(MAX_PLY),
+            0,
+            Score::min(),
+            Score::max(),
+            true,
+            false,
+        );
+        if self.stopped {
+            return None;
+        }
+        let pv = self.pv[0][..self.pv_len[0]].to_vec();
+        self.prev_pv = pv.clone();
+        Some((score, pv))
+    }
+
+    /// Runs the quiescence search from the root position.
+    pub fn quiesce_root(&mut self) -> Score {
+        self.quiesce(0, Score::min(), Score::max(), true)
+    }
+
+    fn poll_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            if self.pondering && !self.mon.is_some_and(|m| m.is_pondering()) {
+                self.pondering = false;
+                self.deadline = self.ponder_time.map(|t| Instant::now() + t);
+            }
+            self.stopped = self.mon.is_some_and(|m| m.is_stopped())
+                || self.node_limit.is_some_and(|l| self.nodes >= l)
+                || self.deadline.is_some_and(|d| Instant::now() >= d);
+        }
+        self.stopped
+    }
+
+    fn evaluate(&self, ply: usize) -> Score {
+        // The model scores the position from White's point of view.
+        let score = self.model.apply(&self.tags[ply], &self.board);
+        debug_assert_eq!(
+            score,
+            self.model
+                .apply(&self.model.build_tag(&self.board), &self.board)
+        );
+        match self.board.side() {
+            Color::White => score,
+            Color::Black => -score,
+        }
+    }
+
+    /// Draw score for the side to move at the given ply.
+    #[inline]
+    fn draw_score(&self, ply: usize) -> Score {
+        if ply.is_multiple_of(2) {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    fn is_draw(&self) -> bool {
+        self.draws.is_draw(&self.board)
+            || self.board.raw().move_counter >= 100
+            || self.board.is_insufficient_material()
+    }
+
+    fn victim_value(&self, mv: Move) -> i32 {
+        mv.captured(&self.board)
+            .map_or(0, |p| PIECE_VALUES[p.index()])
+    }
+
+    /// Remembers a quiet move which caused a cutoff, to try it early in the sibling nodes.
+    fn update_killers(&mut self, ply: usize, mv: Move) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != mv {
+            killers[1] = killers[0];
+            killers[0] = mv;
+        }
+    }
+
+    fn sorted_moves(&self, gen_all: bool) -> MoveList {
+        let mut moves = MoveList::new();
+        let movegen = MoveGen::new(&self.board);
+        if gen_all {
+            movegen.gen_all(&mut moves);
+        } else {
+            movegen.gen_capture(&mut moves);
+            movegen.gen_simple_promote(&mut moves);
+        }
+        moves.sort_by_cached_key(|&mv| -capture_order_key(&self.board, mv));
+        moves
+    }
+
+    fn search(
+        &mut self,
+        depth: usize,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+        on_pv: bool,
+        allow_null: bool,
+    ) -> Score {
+        self.pv_len[ply] = 0;
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiesce(ply, alpha, beta, true);
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.poll_stop() {
+            return Score::new(0);
+        }
+        if ply > 0 && self.is_draw() {
+            return self.draw_score(ply);
+        }
+
+        let key = self.board.zobrist_hash();
+        let mut hint = match self.prev_pv.get(ply) {
+            Some(&mv) if on_pv => mv,
+            _ => Move::NULL,
+        };
+        if let Some(e) = self.tt.probe(key, ply) {
+            if hint == Move::NULL {
+                hint = e.best.into();
+            }
+            let cutoff = match e.bound {
+                Bound::Exact => true,
+                Bound::Lower => e.score >= beta,
+                Bound::Upper => e.score <= alpha,
+            };
+            if ply > 0 && e.depth as usize >= depth && cutoff {
+                return e.score;
+            }
+        }
+
+        // Null move pruning: if passing the move still fails high with reduced depth, a real move
+        // will almost surely do so too. This is wrong in zugzwang, so the side must have pieces
+        // other than pawns.
+        if self.null_move
+            && allow_null
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && !self.board.is_check()
+            && self.board.non_pawn_material(self.board.side()) > 0
+            && self.evaluate(ply) >= beta
+        {
+            let reduction = 2 + depth / 6;
+            let u = self.make_null_move(ply);
+            let score = -self.search(
+                depth.saturating_sub(1 + reduction),
+                ply + 1,
+                -beta,
+                -beta + Score::new(1),
+                false,
+                false,
+            );
+            self.unmake_null_move(u);
+            if self.stopped {
+                return Score::new(0);
+            }
+            if score >= beta {
+                // Do not return unproven mates.
+                return if score > -Score::mate_bound() {
+                    beta
+                } else {
+                    score
+                };
+            }
+        }
+
+        let orig_alpha = alpha;
+        let mut best = Score::min();
+        let mut best_mv = Move::NULL;
+        let mut staged = StagedMoveGen::new(hint, self.killers[ply]);
+        while let Some(mv) = staged.next(&self.board) {
+            if ply == 0 && !self.root_moves.is_empty() && !self.root_moves.contains(&mv) {
+                continue;
+            }
+            let Some(u) = self.make_move(ply, mv) else {
+                continue;
+            };
+            let score = -self.search(depth - 1, ply + 1, -beta, -alpha, on_pv && mv == hint, true);
+            self.unmake_move(mv, u);
+            if self.stopped {
+                return Score::new(0);
+            }
+            if score > best {
+                best = score;
+                best_mv = mv;
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, mv);
+            }
+            if alpha >= beta {
+                if mv.is_quiet(&self.board) {
+                    self.update_killers(ply, mv);
+                }
+                break;
+            }
+        }
+
+        if best == Score::min() {
+            return if self.board.is_check() {
+                Score::mate(ply)
+            } else {
+                self.draw_score(ply)
+            };
+        }
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > orig_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(key, depth, ply, best, bound, best_mv);
+        best
+    }
+
+    /// Searches captures and promotions until the position is quiet. Check evasions are only
+    /// searched on the first ply, as doing so deeper makes the tree explode in the positions with
+    /// many checks.
+    fn quiesce(&mut self, ply: usize, mut alpha: Score, beta: Score, evasions: bool) -> Score {
+        self.pv_len[ply] = 0;
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.poll_stop() {
+            return Score::new(0);
+        }
+        if self.is_draw() {
+            return self.draw_score(ply);
+        }
+        if ply >= MAX_PLY {
+            return self.evaluate(ply);
+        }
+
+        let in_check = evasions && self.board.is_check();
+        let mut best = Score::min();
+        let mut stand_pat = Score::min();
+        if !in_check {
+            stand_pat = self.evaluate(ply);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            best = stand_pat;
+            if best > alpha {
+                alpha = best;
+            }
+        }
+
+        for mv in self.sorted_moves(in_check) {
+            if !in_check && mv.kind().promote().is_some_and(|p| p != Piece::Queen) {
+                continue;
+            }
+            if !in_check
+                && mv.kind().promote().is_none()
+                && i32::from(stand_pat) + self.victim_value(mv) + DELTA_MARGIN <= i32::from(alpha)
+            {
+                continue;
+            }
+            let Some(u) = self.make_move(ply, mv) else {
+                continue;
+            };
+            let score = -self.quiesce(ply + 1, -beta, -alpha, false);
+            self.unmake_move(mv, u);
+            if self.stopped {
+                return Score::new(0);
+            }
+            if score > best {
+                best = score;
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, mv);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if in_check && best == Score::min() {
+            return Score::mate(ply);
+        }
+        best
+    }
+
+    fn make_move(&mut self, ply: usize, mv: Move) -> Option<RawUndo> {
+        let u = unsafe { self.board.try_make_move_unchecked(mv) }?;
+        let (cur, next) = self.tags.split_at_mut(ply + 1);
+        self.model.copy_tag(&mut next[0], &cur[ply]);
+        unsafe { self.model.after_move(&mut next[0], &self.board, mv, &u) };
+        Some(u)
+    }
+
+    fn unmake_move(&mut self, mv: Move, u: RawUndo) {
+        unsafe { self.board.unmake_move_unchecked(mv, u) };
+    }
+
+    fn make_null_move(&mut self, ply: usize) -> RawUndo {
+        let u = unsafe { self.board.make_null_move_unchecked() };
+        let (cur, next) = self.tags.split_at_mut(ply + 1);
+        self.model.copy_tag(&mut next[0], &cur[ply]);
+        u
+    }
+
+    fn unmake_null_move(&mut self, u: RawUndo) {
+        unsafe { self.board.unmake_null_move_unchecked(u) };
+    }
+
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let len = if ply + 1 < MAX_PLY {
+            self.pv_len[ply + 1]
+        } else {
+            0
+        };
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        head[ply][0] = mv;
+        if len != 0 {
+            head[ply][1..=len].copy_from_slice(&tail[0][..len]);
+        }
+        self.pv_len[ply] = len + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::model::{Model, PsqModel};
+    use std::str::FromStr;
+
+    fn search_fixed(fen: &str, depth: usize, null_move: bool) -> (Score, Vec<Move>) {
+        let b = Board::from_str(fen).unwrap();
+        let model = <PsqModel as Model>::new();
+        let mut tt = TranspositionTable::new(1);
+        let mut searcher = Searcher::new(&b, &model, &mut tt, None, None);
+        searcher.set_null_move(null_move);
+        let mut res = None;
+        for d in 1..=depth {
+            res = searcher.search_root(d);
+        }
+        res.unwrap()
+    }
+
+    #[test]
+    fn test_null_move() {
+        for (fen, plies) in [
+            ("k7/8/2K5/8/8/8/8/7R w - - 0 1", 3),
+            ("2r3k1/p4p2/3Rp2p/1p2P1pK/8/1P4P1/P3Q2P/1q6 b - - 0 1", 5),
+            ("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1", 5),
+        ] {
+            let (score, pv) = search_fixed(fen, 6, true);
+            assert_eq!(score, -Score::mate(plies), "{}", fen);
+            assert_eq!(search_fixed(fen, 6, false), (score, pv), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_uci_score() {
+        assert_eq!(to_uci_score(Score::new(42)), UciScore::Cp(42));
+        assert_eq!(to_uci_score(-Score::new(42)), UciScore::Cp(-42));
+        for (ply, moves) in [(0, 0), (2, 1), (4, 2)] {
+            let score = Score::mate(ply);
+            let loss = UciScore::Mate { moves, win: false };
+            assert_eq!(to_uci_score(score), loss);
+        }
+        for (ply, moves) in [(1, 1), (3, 2), (5, 3)] {
+            let score = -Score::mate(ply);
+            let win = UciScore::Mate { moves, win: true };
+            assert_eq!(to_uci_score(score), win);
+        }
+    }
+}
@@ -0,0 +1,1123 @@
+use crate::eval::score::Wdl;
+use anyhow::{Context, Result, bail, ensure};
+use memmap2::Mmap;
+use pawnyowl_board::{Bitboard, Board, CastlingRights, Cell, Color, MoveGen, MoveList, Piece};
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    ops::Deref,
+    path::PathBuf,
+    sync::{LazyLock, OnceLock},
+};
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const WDL_SUFFIX: &str = ".rtbw";
+
+/// Maximum number of pieces, kings included, in a Syzygy table.
+const MAX_PIECES: usize = 7;
+
+const FLAG_SPLIT: u8 = 1;
+const FLAG_HAS_PAWNS: u8 = 2;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// Index maps of the Syzygy position encoding.
+///
+/// Squares here are numbered from a1 to h8, so a square of the board is converted with `^ 56`.
+struct Maps {
+    binomial: [[u64; 64]; MAX_PIECES - 1],
+    pawns: [usize; 64],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES - 1],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES - 1],
+    b1h1h7: [u64; 64],
+    a1d1d4: [u64; 64],
+    kk: [[u64; 64]; 10],
+}
+
+static MAPS: LazyLock<Maps> = LazyLock::new(Maps::new);
+
+/// Signed distance of the square from the a1-h8 diagonal, positive above it.
+#[inline]
+fn off_diag(s: usize) -> i32 {
+    (s / 8) as i32 - (s % 8) as i32
+}
+
+impl Maps {
+    fn new() -> Self {
+        let mut m = Maps {
+            binomial: [[0; 64]; MAX_PIECES - 1],
+            pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES - 1],
+            lead_pawns_size: [[0; 4]; MAX_PIECES - 1],
+            b1h1h7: [0; 64],
+            a1d1d4: [0; 64],
+            kk: [[0; 64]; 10],
+        };
+
+        let mut code = 0;
+        for s in 0..64 {
+            if off_diag(s) < 0 {
+                m.b1h1h7[s] = code;
+                code += 1;
+            }
+        }
+
+        // The squares of the a1-d1-d4 triangle, the ones on the diagonal encoded last.
+        let mut diagonal = Vec::new();
+        code = 0;
+        for s in (0..4).flat_map(|r| (0..4).map(move |f| 8 * r + f)) {
+            if off_diag(s) < 0 {
+                m.a1d1d4[s] = code;
+                code += 1;
+            } else if off_diag(s) == 0 {
+                diagonal.push(s);
+            }
+        }
+        for s in diagonal {
+            m.a1d1d4[s] = code;
+            code += 1;
+        }
+
+        // The 462 legal placements of two kings, the first one in the triangle. The placements
+        // with both kings on the diagonal are encoded last.
+        let mut both = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..28 {
+                if m.a1d1d4[s1] != idx as u64 || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64 {
+                    let touch = (s1 / 8).abs_diff(s2 / 8) <= 1 && (s1 % 8).abs_diff(s2 % 8) <= 1;
+                    if touch || (off_diag(s1) == 0 && off_diag(s2) > 0) {
+                        continue;
+                    }
+                    if off_diag(s1) == 0 && off_diag(s2) == 0 {
+                        both.push((idx, s2));
+                    } else {
+                        m.kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both {
+            m.kk[idx][s2] = code;
+            code += 1;
+        }
+
+        m.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..=n.min(MAX_PIECES - 2) {
+                m.binomial[k][n] = if k > 0 { m.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { m.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // Pawn squares are numbered from 47 down, so the leading pawn is the one with the largest
+        // number.
+        let mut available = 47_i32;
+        for lead in 1..MAX_PIECES - 1 {
+            for f in 0..4 {
+                let mut idx = 0;
+                for r in 1..7 {
+                    let s = 8 * r + f;
+                    if lead == 1 {
+                        m.pawns[s] = available as usize;
+                        m.pawns[s ^ 7] = (available - 1) as usize;
+                        available -= 2;
+                    }
+                    m.lead_pawn_idx[lead][s] = idx;
+                    idx += m.binomial[lead - 1][m.pawns[s]];
+                }
+                m.lead_pawns_size[lead][f] = idx;
+            }
+        }
+        m
+    }
+}
+
+/// Piece code used in the table files.
+#[inline]
+fn tb_code(cell: Cell) -> u8 {
+    let code = match cell.piece() {
+        Some(Piece::Pawn) => 1,
+        Some(Piece::Knight) => 2,
+        Some(Piece::Bishop) => 3,
+        Some(Piece::Rook) => 4,
+        Some(Piece::Queen) => 5,
+        Some(Piece::King) => 6,
+        None => 0,
+    };
+    match cell.color() {
+        Some(Color::Black) => code | 8,
+        _ => code,
+    }
+}
+
+/// Piece counts of both sides from a table name like `KRPvKR`.
+fn parse_material(name: &str) -> Option<[[usize; Piece::COUNT]; 2]> {
+    let (white, black) = name.split_once('v')?;
+    let mut counts = [[0; Piece::COUNT]; 2];
+    for (side, pieces) in counts.iter_mut().zip([white, black]) {
+        for ch in pieces.chars() {
+            let p = Cell::from_char(ch)
+                .filter(|c| c.color() == Some(Color::White))?
+                .piece()?;
+            side[p.index()] += 1;
+        }
+        if side[Piece::King.index()] != 1 {
+            return None;
+        }
+    }
+    let total: usize = counts.iter().flatten().sum();
+    (total <= MAX_PIECES).then_some(counts)
+}
+
+#[inline]
+fn read<const N: usize>(data: &[u8], pos: usize) -> Result<[u8; N]> {
+    data.get(pos..pos + N)
+        .and_then(|s| s.try_into().ok())
+        .context("table is truncated")
+}
+
+#[inline]
+fn read_u16(data: &[u8], pos: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(read(data, pos)?))
+}
+
+/// Compressed values of one side to move, for one file of the leading pawn.
+#[derive(Default, Clone)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; MAX_PIECES],
+    group_len: [usize; MAX_PIECES + 1],
+    group_idx: [u64; MAX_PIECES + 1],
+    block_size: usize,
+    span: u64,
+    num_blocks: usize,
+    block_length_size: usize,
+    sparse_index_size: usize,
+    min_sym_len: u8,
+    base64: Vec<u64>,
+    sym_len: Vec<u8>,
+    // Offsets of the sections in the table data.
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+}
+
+impl PairsData {
+    /// Number of the encoded positions.
+    #[inline]
+    fn size(&self) -> u64 {
+        self.group_idx[self.group_len.iter().position(|&l| l == 0).unwrap()]
+    }
+
+    /// Reads the left and the right child of a symbol from the Huffman tree.
+    #[inline]
+    fn children(&self, data: &[u8], sym: usize) -> Result<(usize, usize)> {
+        let [a, b, c] = read(data, self.btree + 3 * sym)?;
+        let left = ((b as usize & 0xf) << 8) | a as usize;
+        let right = ((c as usize) << 4) | (b as usize >> 4);
+        Ok((left, right))
+    }
+
+    fn set_sizes(&mut self, data: &[u8], mut pos: usize) -> Result<usize> {
+        self.flags = read::<1>(data, pos)?[0];
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            self.min_sym_len = read::<1>(data, pos + 1)?[0];
+            return Ok(pos + 2);
+        }
+        let tb_size = self.size();
+        let [block, span, padding] = read(data, pos + 1)?;
+        ensure!(block < 32 && span < 64, "bad block size");
+        self.block_size = 1 << block;
+        self.span = 1 << span;
+        self.sparse_index_size = tb_size.div_ceil(self.span) as usize;
+        self.num_blocks = u32::from_le_bytes(read(data, pos + 4)?) as usize;
+        self.block_length_size = self.num_blocks + padding as usize;
+        let [max_sym_len, min_sym_len] = read(data, pos + 8)?;
+        ensure!(
+            0 < min_sym_len && min_sym_len <= max_sym_len && max_sym_len < 64,
+            "bad symbol lengths"
+        );
+        self.min_sym_len = min_sym_len;
+        pos += 10;
+
+        // Canonical Huffman code: longer codes have smaller values.
+        self.lowest_sym = pos;
+        let len = (max_sym_len - min_sym_len + 1) as usize;
+        self.base64 = vec![0; len];
+        for i in (0..len - 1).rev() {
+            let (lo, next) = (
+                read_u16(data, pos + 2 * i)?,
+                read_u16(data, pos + 2 * i + 2)?,
+            );
+            self.base64[i] = self.base64[i + 1]
+                .wrapping_add(u64::from(lo))
+                .wrapping_sub(u64::from(next))
+                / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base <<= 64 - i - min_sym_len as usize;
+        }
+        pos += 2 * len;
+
+        let sym_count = read_u16(data, pos)? as usize;
+        pos += 2;
+        self.btree = pos;
+        self.sym_len = vec![0; sym_count];
+        let mut visited = vec![false; sym_count];
+        for sym in 0..sym_count {
+            self.set_sym_len(data, sym, &mut visited)?;
+        }
+        Ok(pos + 3 * sym_count + (sym_count & 1))
+    }
+
+    /// Computes the number of values, minus one, to which the symbol expands.
+    fn set_sym_len(&mut self, data: &[u8], sym: usize, visited: &mut [bool]) -> Result<()> {
+        if visited[sym] {
+            return Ok(());
+        }
+        visited[sym] = true;
+        let (left, right) = self.children(data, sym)?;
+        if right == 0xfff {
+            return Ok(());
+        }
+        ensure!(
+            left < self.sym_len.len() && right < self.sym_len.len() && left != sym && right != sym,
+            "bad symbol tree"
+        );
+        self.set_sym_len(data, left, visited)?;
+        self.set_sym_len(data, right, visited)?;
+        self.sym_len[sym] = (self.sym_len[left] as usize + self.sym_len[right] as usize + 1) as u8;
+        Ok(())
+    }
+
+    /// Decodes the value with index `idx`.
+    fn decompress(&self, data: &[u8], idx: u64) -> Result<u8> {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return Ok(self.min_sym_len);
+        }
+
+        // The sparse index gives the position of each `span`-th value, from which we move to the
+        // block containing the requested one.
+        let entry = self.sparse_index + 6 * (idx / self.span) as usize;
+        let mut block = u32::from_le_bytes(read(data, entry)?) as usize;
+        let mut offset = i64::from(read_u16(data, entry + 4)?) + (idx % self.span) as i64
+            - (self.span / 2) as i64;
+        let block_len = |b: usize| -> Result<i64> {
+            ensure!(b < self.block_length_size, "bad block index");
+            Ok(i64::from(read_u16(data, self.block_length + 2 * b)?))
+        };
+        while offset < 0 {
+            block = block.checked_sub(1).context("bad block index")?;
+            offset += block_len(block)? + 1;
+        }
+        while offset > block_len(block)? {
+            offset -= block_len(block)? + 1;
+            block += 1;
+        }
+
+        // Read the symbols of the block until the one which contains the value.
+        let mut ptr = self.data + block * self.block_size;
+        let mut buf = u64::from_be_bytes(read(data, ptr)?);
+        ptr += 8;
+        let mut buf_size = 64;
+        let min_len = self.min_sym_len as usize;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf < self.base64[len] {
+                len += 1;
+            }
+            sym = ((buf - self.base64[len]) >> (64 - len - min_len)) as usize
+                + read_u16(data, self.lowest_sym + 2 * len)? as usize;
+            let sym_len = i64::from(*self.sym_len.get(sym).context("bad symbol")?);
+            if offset < sym_len + 1 {
+                break;
+            }
+            offset -= sym_len + 1;
+            len += min_len;
+            buf <<= len;
+            buf_size -= len;
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= u64::from(u32::from_be_bytes(read(data, ptr)?)) << (64 - buf_size);
+                ptr += 4;
+            }
+        }
+
+        // Descend the tree of pairs to the value.
+        while self.sym_len[sym] != 0 {
+            let (left, right) = self.children(data, sym)?;
+            let left_len = i64::from(self.sym_len[left]);
+            if offset < left_len + 1 {
+                sym = left;
+            } else {
+                offset -= left_len + 1;
+                sym = right;
+            }
+        }
+        Ok(self.children(data, sym)?.0 as u8)
+    }
+}
+
+/// WDL table of one material configuration.
+///
+/// The table data is usually memory-mapped, so only the parts needed by the probes are read.
+struct Table<D = Mmap> {
+    data: D,
+    /// Both sides have the same pieces, so only one side to move is stored.
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    piece_count: usize,
+    /// Pawns of the leading side, then of the other one.
+    pawn_count: [usize; 2],
+    /// Indexed by the file of the leading pawn, then by the side to move.
+    pairs: Vec<Vec<PairsData>>,
+}
+
+impl<D: Deref<Target = [u8]>> Table<D> {
+    fn new(data: D, counts: &[[usize; Piece::COUNT]; 2]) -> Result<Self> {
+        ensure!(data.starts_with(&WDL_MAGIC), "bad magic");
+        let (white_pawns, black_pawns) = (
+            counts[0][Piece::Pawn.index()],
+            counts[1][Piece::Pawn.index()],
+        );
+        let lead_white = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let mut table = Self {
+            data,
+            symmetric: counts[0] == counts[1],
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: counts
+                .iter()
+                .any(|side| Piece::iter().any(|p| p != Piece::King && side[p.index()] == 1)),
+            piece_count: counts.iter().flatten().sum(),
+            pawn_count: if lead_white {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            pairs: Vec::new(),
+        };
+        table.pairs = table.parse(counts)?;
+        Ok(table)
+    }
+
+    fn parse(&self, counts: &[[usize; Piece::COUNT]; 2]) -> Result<Vec<Vec<PairsData>>> {
+        let data = &self.data[..];
+        let flags = read::<1>(data, 4)?[0];
+        ensure!(
+            (flags & FLAG_HAS_PAWNS != 0) == self.has_pawns
+                && (flags & FLAG_SPLIT != 0) != self.symmetric,
+            "table does not match its material"
+        );
+        let mut expected: Vec<u8> = [Color::White, Color::Black]
+            .into_iter()
+            .flat_map(|c| {
+                Piece::iter().flat_map(move |p| {
+                    let count = counts[c as usize][p.index()];
+                    std::iter::repeat_n(tb_code(Cell::make(c, p)), count)
+                })
+            })
+            .collect();
+        expected.sort_unstable();
+
+        let sides = if self.symmetric { 1 } else { 2 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut pairs = vec![vec![PairsData::default(); sides]; files];
+        let mut pos = 5;
+        for (f, file) in pairs.iter_mut().enumerate() {
+            let [lo, hi] = read(data, pos)?;
+            let hi = if both_pawns { hi } else { 0xff };
+            let order = [[lo & 0xf, hi & 0xf], [lo >> 4, hi >> 4]];
+            pos += 1 + usize::from(both_pawns);
+            for k in 0..self.piece_count {
+                let b = read::<1>(data, pos + k)?[0];
+                for (i, d) in file.iter_mut().enumerate() {
+                    d.pieces[k] = if i == 0 { b & 0xf } else { b >> 4 };
+                }
+            }
+            pos += self.piece_count;
+            for (d, order) in file.iter_mut().zip(order) {
+                let mut pieces = d.pieces[..self.piece_count].to_vec();
+                pieces.sort_unstable();
+                ensure!(pieces == expected, "table does not match its material");
+                self.set_groups(d, order, f);
+            }
+        }
+        pos += pos & 1;
+
+        for d in pairs.iter_mut().flatten() {
+            pos = d.set_sizes(data, pos)?;
+        }
+        for d in pairs.iter_mut().flatten() {
+            d.sparse_index = pos;
+            pos += 6 * d.sparse_index_size;
+        }
+        for d in pairs.iter_mut().flatten() {
+            d.block_length = pos;
+            pos += 2 * d.block_length_size;
+        }
+        for d in pairs.iter_mut().flatten() {
+            pos = (pos + 0x3f) & !0x3f;
+            d.data = pos;
+            pos += d.num_blocks * d.block_size;
+        }
+        ensure!(pos <= data.len(), "table is truncated");
+        Ok(pairs)
+    }
+
+    /// Splits the pieces into groups of the same kind, and computes the index multiplier of each
+    /// group. `order` tells when to encode the leading group and the group of the other pawns.
+    fn set_groups(&self, d: &mut PairsData, order: [u8; 2], file: usize) {
+        let maps = &*MAPS;
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        d.group_len[0] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+        let mut idx = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                d.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    maps.lead_pawns_size[d.group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                d.group_idx[1] = idx;
+                idx *= maps.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= maps.binomial[d.group_len[next]][free];
+                free -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    /// Computes the index of the position, which must have the material of the table. Returns the
+    /// file of the leading pawn and the side to move, as indices into `pairs`, along with it. If
+    /// `black_stronger` is set, the colors are swapped relative to the table name.
+    fn encode(&self, b: &Board, black_stronger: bool) -> Result<(usize, usize, u64)> {
+        let maps = &*MAPS;
+        // The tables are stored with the stronger side as White. The squares are flipped along
+        // with the colors, which for our a8-based numbering cancels the conversion to a1-based.
+        let flip = black_stronger || (self.symmetric && b.side() == Color::Black);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_sq = if flip { 0 } else { 56 };
+        let stm = usize::from(flip != (b.side() == Color::Black));
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces = [0; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = Bitboard::EMPTY;
+        let mut file = 0;
+        if self.has_pawns {
+            let lead = self.pairs[0][0].pieces[0] ^ flip_color;
+            let c = if lead & 8 != 0 {
+                Color::Black
+            } else {
+                Color::White
+            };
+            lead_pawns = b.piece(c, Piece::Pawn);
+            for sq in lead_pawns {
+                squares[size] = sq.index() ^ flip_sq;
+                size += 1;
+            }
+            let best = (0..size).max_by_key(|&i| maps.pawns[squares[i]]).unwrap();
+            squares.swap(0, best);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+        let lead_count = size;
+        for sq in b.all() & !lead_pawns {
+            squares[size] = sq.index() ^ flip_sq;
+            pieces[size] = tb_code(b.get(sq)) ^ flip_color;
+            size += 1;
+        }
+        ensure!(
+            size == self.piece_count,
+            "position does not match the table"
+        );
+
+        let d = &self.pairs[file][stm];
+        // Put the pieces in the order of the table.
+        for i in lead_count..size - 1 {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // Use the symmetries of the board to move the first piece into the a1-d1-d4 triangle, or
+        // the leading pawn to files a-d.
+        if squares[0] % 8 > 3 {
+            for s in &mut squares[..size] {
+                *s ^= 7;
+            }
+        }
+        let mut idx;
+        if self.has_pawns {
+            idx = maps.lead_pawn_idx[lead_count][squares[0]];
+            squares[1..lead_count].sort_by_key(|&s| maps.pawns[s]);
+            for (i, &s) in squares.iter().enumerate().take(lead_count).skip(1) {
+                idx += maps.binomial[i][maps.pawns[s]];
+            }
+        } else {
+            if squares[0] / 8 > 3 {
+                for s in &mut squares[..size] {
+                    *s ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                let off = off_diag(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for s in &mut squares[i..size] {
+                        *s = ((*s >> 3) | (*s << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            if self.has_unique_pieces {
+                let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+                let adjust1 = u64::from(s1 > s0);
+                let adjust2 = u64::from(s2 > s0) + u64::from(s2 > s1);
+                let rank = |s: usize| (s / 8) as u64;
+                idx = if off_diag(s0) != 0 {
+                    (maps.a1d1d4[s0] * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64 - adjust2
+                } else if off_diag(s1) != 0 {
+                    (6 * 63 + rank(s0) * 28 + maps.b1h1h7[s1]) * 62 + s2 as u64 - adjust2
+                } else if off_diag(s2) != 0 {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank(s0) * 7 * 28
+                        + (rank(s1) - adjust1) * 28
+                        + maps.b1h1h7[s2]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank(s0) * 7 * 6
+                        + (rank(s1) - adjust1) * 6
+                        + (rank(s2) - adjust2)
+                };
+            } else {
+                idx = maps.kk[maps.a1d1d4[squares[0]] as usize][squares[1]];
+            }
+        }
+        idx *= d.group_idx[0];
+
+        // Encode the remaining groups as combinations of the squares not taken by the previous
+        // groups. The pawns of the other side cannot be on the first and the last rank.
+        let mut start = d.group_len[0];
+        let mut other_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let s = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&p| s > p).count();
+                n += maps.binomial[i + 1][s - adjust - if other_pawns { 8 } else { 0 }];
+            }
+            other_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        Ok((file, stm, idx))
+    }
+
+    fn probe(&self, b: &Board, black_stronger: bool) -> Result<Wdl> {
+        let (file, stm, idx) = self.encode(b, black_stronger)?;
+        Ok(
+            match self.pairs[file][stm].decompress(&self.data[..], idx)? {
+                0 => Wdl::Loss,
+                1 => Wdl::BlessedLoss,
+                2 => Wdl::Draw,
+                3 => Wdl::CursedWin,
+                4 => Wdl::Win,
+                v => bail!("bad value {}", v),
+            },
+        )
+    }
+}
+
+struct TableEntry {
+    path: PathBuf,
+    counts: [[usize; Piece::COUNT]; 2],
+    table: OnceLock<Option<Table>>,
+}
+
+/// Syzygy endgame tablebases, which give the win/draw/loss outcome of the positions with few
+/// pieces.
+///
+/// Only the WDL tables (`*.rtbw`) are used. The tables are mapped into memory on first use, and
+/// the ones that are missing or fail to load make the probes return `None`.
+pub struct Tablebase {
+    tables: HashMap<String, TableEntry>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    /// Finds the tables in the directories listed in `path`, separated as in the `PATH` variable.
+    pub fn new(path: &str) -> Result<Self> {
+        let mut tb = Self {
+            tables: HashMap::new(),
+            max_pieces: 0,
+        };
+        for dir in env::split_paths(path) {
+            let entries =
+                fs::read_dir(&dir).with_context(|| format!("reading {}", dir.display()))?;
+            for entry in entries {
+                let path = entry?.path();
+                let Some(name) = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_suffix(WDL_SUFFIX))
+                else {
+                    continue;
+                };
+                let Some(counts) = parse_material(name) else {
+                    continue;
+                };
+                tb.max_pieces = tb.max_pieces.max(counts.iter().flatten().sum());
+                tb.tables.insert(
+                    name.to_string(),
+                    TableEntry {
+                        path,
+                        counts,
+                        table: OnceLock::new(),
+                    },
+                );
+            }
+        }
+        Ok(tb)
+    }
+
+    /// Largest number of pieces, kings included, covered by the found tables.
+    #[inline]
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    fn table(&self, name: &str) -> Option<&Table> {
+        let entry = self.tables.get(name)?;
+        entry
+            .table
+            .get_or_init(|| {
+                let file = File::open(&entry.path).ok()?;
+                // The tables are not expected to change while they are mapped.
+                let data = unsafe { Mmap::map(&file) }.ok()?;
+                Table::new(data, &entry.counts).ok()
+            })
+            .as_ref()
+    }
+
+    /// Probes the table of the position, ignoring the captures.
+    fn probe_table(&self, b: &Board) -> Option<Wdl> {
+        if b.all().len() == 2 {
+            return Some(Wdl::Draw);
+        }
+        let name = b.material_signature();
+        if let Some(table) = self.table(&name) {
+            return table.probe(b, false).ok();
+        }
+        let (white, black) = name.split_once('v')?;
+        let table = self.table(&format!("{}v{}", black, white))?;
+        table.probe(b, true).ok()
+    }
+
+    /// Resolves the captures before probing, as the tables may hold any value for the positions
+    /// where the best move is a capture.
+    fn search(&self, b: &mut Board) -> Option<Wdl> {
+        let mut moves = MoveList::new();
+        MoveGen::new(b).gen_legal(&mut moves);
+        let mut best = Wdl::Loss;
+        let mut captures = 0;
+        for &mv in moves.iter() {
+            if !mv.is_capture(b) {
+                continue;
+            }
+            captures += 1;
+            let u = unsafe { b.make_move_unchecked(mv) };
+            let res = self.search(b);
+            unsafe { b.unmake_move_unchecked(mv, u) };
+            best = best.max(-res?);
+            if best == Wdl::Win {
+                return Some(best);
+            }
+        }
+        if captures > 0 && captures == moves.len() {
+            return Some(best);
+        }
+        Some(best.max(self.probe_table(b)?))
+    }
+
+    #[inline]
+    fn can_probe(&self, b: &Board) -> bool {
+        b.raw().castling == CastlingRights::EMPTY && b.all().len() as usize <= self.max_pieces
+    }
+
+    /// Returns the outcome for the side to move, or `None` if the position is not covered by the
+    /// tables. The tables do not know about castling, so the positions with castling rights are
+    /// never probed.
+    pub fn probe_wdl(&self, b: &Board) -> Option<Wdl> {
+        if !self.can_probe(b) {
+            return None;
+        }
+        self.search(&mut b.clone())
+    }
+
+    /// Keeps only the root moves that preserve the best outcome, and returns this outcome. If any
+    /// of the moves cannot be probed, the moves are left untouched.
+    pub fn filter_root_moves(&self, b: &Board, moves: &mut MoveList) -> Option<Wdl> {
+        if !self.can_probe(b) {
+            return None;
+        }
+        let mut b = b.clone();
+        let mut outcomes = Vec::with_capacity(moves.len());
+        for &mv in moves.iter() {
+            let u = unsafe { b.make_move_unchecked(mv) };
+            let res = self.search(&mut b);
+            unsafe { b.unmake_move_unchecked(mv, u) };
+            outcomes.push(-res?);
+        }
+        let best = *outcomes.iter().max()?;
+        let mut outcomes = outcomes.into_iter();
+        moves.retain(|_| outcomes.next() == Some(best));
+        Some(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestRng;
+    use pawnyowl_board::{RawBoard, Sq};
+    use std::{collections::HashSet, str::FromStr};
+
+    /// Builds a table which stores the same value for all the positions with the given side to
+    /// move. The pieces are given in the order of the encoding.
+    fn single_value_table(name: &str, pieces: &[Cell], values: [u8; 2]) -> Table<Vec<u8>> {
+        let counts = parse_material(name).unwrap();
+        let symmetric = counts[0] == counts[1];
+        let has_pawns = counts.iter().any(|side| side[Piece::Pawn.index()] > 0);
+        let mut data = WDL_MAGIC.to_vec();
+        data.push(
+            if symmetric { 0 } else { FLAG_SPLIT } | if has_pawns { FLAG_HAS_PAWNS } else { 0 },
+        );
+        let (files, sides) = (if has_pawns { 4 } else { 1 }, if symmetric { 1 } else { 2 });
+        for _ in 0..files {
+            data.push(0);
+            data.extend(pieces.iter().map(|&p| tb_code(p) | (tb_code(p) << 4)));
+        }
+        data.resize(data.len().next_multiple_of(2), 0);
+        for _ in 0..files {
+            for &value in &values[..sides] {
+                data.extend([FLAG_SINGLE_VALUE, value]);
+            }
+        }
+        data.resize(data.len().next_multiple_of(64), 0);
+        Table::new(data, &counts).unwrap()
+    }
+
+    /// Builds a position from the pieces on the squares. Returns `None` if the squares overlap or
+    /// the position is invalid.
+    fn board(pieces: &[(Cell, usize)], side: Color) -> Option<Board> {
+        let mut raw = RawBoard::empty();
+        for &(cell, sq) in pieces {
+            let sq = Sq::from_index(sq);
+            if raw.get(sq) != Cell::None {
+                return None;
+            }
+            raw.put(sq, cell);
+        }
+        raw.side = side;
+        Board::try_from(raw).ok()
+    }
+
+    #[test]
+    fn test_maps() {
+        let maps = &*MAPS;
+        assert_eq!(maps.kk.iter().flatten().max(), Some(&461));
+        assert_eq!(maps.a1d1d4.iter().max(), Some(&9));
+        assert_eq!(maps.b1h1h7.iter().max(), Some(&27));
+        assert_eq!(maps.binomial[2][5], 10);
+        assert_eq!(maps.binomial[5][63], 7028847);
+        let pawns: HashSet<_> = (8..56).map(|s| maps.pawns[s]).collect();
+        assert_eq!(pawns, (0..48).collect());
+        assert_eq!(maps.lead_pawns_size[1], [6; 4]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let (wk, wr, wp, bk) = (
+            Cell::WhiteKing,
+            Cell::WhiteRook,
+            Cell::WhitePawn,
+            Cell::BlackKing,
+        );
+
+        // Without pawns, the positions equal up to the symmetries of the board share the index.
+        let table = single_value_table("KRvK", &[wk, wr, bk], [4, 0]);
+        let mut orbits = HashSet::new();
+        let mut indices = HashSet::new();
+        for (k, r, e) in
+            (0..64).flat_map(|k| (0..64).flat_map(move |r| (0..64).map(move |e| (k, r, e))))
+        {
+            let Some(b) = board(&[(wk, k), (wr, r), (bk, e)], Color::White) else {
+                continue;
+            };
+            let (file, stm, idx) = table.encode(&b, false).unwrap();
+            assert_eq!((file, stm), (0, 0));
+            assert!(idx < table.pairs[0][0].size());
+            indices.insert(idx);
+            let symmetries = (0..8).map(|t| {
+                let f = |s: usize| {
+                    let (mut rank, mut file) = (s / 8, s % 8);
+                    if t & 1 != 0 {
+                        file = 7 - file;
+                    }
+                    if t & 2 != 0 {
+                        rank = 7 - rank;
+                    }
+                    if t & 4 != 0 {
+                        (rank, file) = (file, rank);
+                    }
+                    8 * rank + file
+                };
+                (f(k), f(r), f(e))
+            });
+            orbits.insert(symmetries.min().unwrap());
+        }
+        assert_eq!(indices.len(), orbits.len());
+
+        // With pawns, only the files can be flipped.
+        let table = single_value_table("KPvK", &[wp, wk, bk], [4, 0]);
+        let mut orbits = HashSet::new();
+        let mut indices = HashSet::new();
+        for (p, k, e) in
+            (8..56).flat_map(|p| (0..64).flat_map(move |k| (0..64).map(move |e| (p, k, e))))
+        {
+            let Some(b) = board(&[(wp, p), (wk, k), (bk, e)], Color::White) else {
+                continue;
+            };
+            let (file, stm, idx) = table.encode(&b, false).unwrap();
+            assert_eq!(stm, 0);
+            assert!(idx < table.pairs[file][0].size());
+            indices.insert((file, idx));
+            orbits.insert((p, k, e).min((p ^ 7, k ^ 7, e ^ 7)));
+        }
+        assert_eq!(indices.len(), orbits.len());
+    }
+
+    #[test]
+    fn test_decompress() {
+        // Huffman codes: 000 and 001 for the symbols 0 and 1, 01, 10 and 11 for the symbols 2, 3
+        // and 4. The symbols 3 and 4 are pairs.
+        const CODES: [(u64, usize); 5] = [(0b000, 3), (0b001, 3), (0b01, 2), (0b10, 2), (0b11, 2)];
+        const TREE: [(usize, usize); 5] = [(4, 0xfff), (0, 0xfff), (2, 0xfff), (2, 0), (3, 1)];
+        let expand = |sym: usize| match sym {
+            0 => vec![4],
+            1 => vec![0],
+            2 => vec![2],
+            3 => vec![2, 4],
+            _ => vec![2, 4, 0],
+        };
+        const BLOCK_BITS: usize = 4;
+        const SPAN_BITS: usize = 5;
+        const SIZE: usize = 1000;
+
+        // Fill the blocks with pseudo-random symbols, leaving space for the bit buffer refills.
+        let mut rng = TestRng::new(42);
+        let mut values = Vec::new();
+        let mut blocks = Vec::new();
+        let mut block_lengths = Vec::new();
+        while values.len() < SIZE {
+            let (mut bits, mut len, mut count) = (0_u128, 0, 0);
+            while len < 96 && values.len() < SIZE {
+                let sym = usize::from(rng.next_u16()) % CODES.len();
+                let (code, code_len) = CODES[sym];
+                bits |= u128::from(code) << (128 - len - code_len);
+                len += code_len;
+                let expanded = expand(sym);
+                count += expanded.len();
+                values.extend(expanded);
+            }
+            blocks.extend(bits.to_be_bytes());
+            block_lengths.push(count as u16 - 1);
+        }
+        values.truncate(SIZE);
+
+        let mut data = vec![0, BLOCK_BITS as u8, SPAN_BITS as u8, 0];
+        data.extend((block_lengths.len() as u32).to_le_bytes());
+        data.extend([3, 2]);
+        data.extend([2_u16, 0].iter().flat_map(|s| s.to_le_bytes()));
+        data.extend((TREE.len() as u16).to_le_bytes());
+        for (left, right) in TREE {
+            data.extend([
+                left as u8,
+                ((left >> 8) | ((right & 0xf) << 4)) as u8,
+                (right >> 4) as u8,
+            ]);
+        }
+
+        let mut d = PairsData::default();
+        d.group_len[0] = 1;
+        d.group_idx[1] = SIZE as u64;
+        let mut pos = d.set_sizes(&data, 0).unwrap();
+        assert_eq!(pos, data.len() + 1);
+        data.push(0);
+
+        // Each sparse index entry points to the middle of its span.
+        d.sparse_index = pos;
+        let span = 1 << SPAN_BITS;
+        for k in 0..d.sparse_index_size {
+            let target = (k * span + span / 2).min(SIZE - 1);
+            let (mut block, mut start) = (0, 0);
+            while start + (block_lengths[block] as usize) < target {
+                start += block_lengths[block] as usize + 1;
+                block += 1;
+            }
+            data.extend((block as u32).to_le_bytes());
+            data.extend(((k * span + span / 2 - start) as u16).to_le_bytes());
+        }
+        pos = data.len();
+        d.block_length = pos;
+        data.extend(block_lengths.iter().flat_map(|l| l.to_le_bytes()));
+        d.data = data.len();
+        data.extend(blocks);
+        data.resize(data.len() + 16, 0);
+
+        for (idx, &value) in values.iter().enumerate() {
+            assert_eq!(d.decompress(&data, idx as u64).unwrap(), value, "{}", idx);
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        let table = single_value_table(
+            "KQvK",
+            &[Cell::WhiteKing, Cell::WhiteQueen, Cell::BlackKing],
+            [4, 0],
+        );
+        let dir = env::temp_dir().join(format!("pawnyowl-syzygy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("KQvK.rtbw"), &table.data).unwrap();
+        fs::write(dir.join("README.txt"), "not a table").unwrap();
+        let tb = Tablebase::new(dir.to_str().unwrap()).unwrap();
+        assert_eq!(tb.max_pieces(), 3);
+
+        for (fen, wdl) in [
+            ("8/8/8/4k3/8/8/8/3QK3 w - - 0 1", Some(Wdl::Win)),
+            ("8/8/8/4k3/8/8/8/3QK3 b - - 0 1", Some(Wdl::Loss)),
+            // The queen is captured.
+            ("8/8/8/8/3k4/8/6q1/7K w - - 0 1", Some(Wdl::Draw)),
+            ("8/8/8/8/8/8/4kQ2/7K b - - 0 1", Some(Wdl::Draw)),
+            ("8/8/8/8/8/3q4/4k3/7K w - - 0 1", Some(Wdl::Loss)),
+            ("8/8/8/8/8/3q4/4k3/7K b - - 0 1", Some(Wdl::Win)),
+            ("8/8/8/4k3/8/8/8/4K3 w - - 0 1", Some(Wdl::Draw)),
+            // No table for these.
+            ("8/8/8/4k3/8/8/8/3RK3 w - - 0 1", None),
+            ("8/8/8/4k3/8/8/8/2RQK3 w - - 0 1", None),
+            ("8/8/8/4k3/8/8/8/R2QK3 w Q - 0 1", None),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert_eq!(tb.probe_wdl(&b), wdl, "{}", fen);
+        }
+
+        // Only the moves that keep the queen win.
+        let b = Board::from_str("8/8/8/8/8/8/3k4/Q6K w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        MoveGen::new(&b).gen_legal(&mut moves);
+        let count = moves.len();
+        assert_eq!(tb.filter_root_moves(&b, &mut moves), Some(Wdl::Win));
+        assert!(moves.len() < count);
+        assert!(!moves.iter().any(|mv| mv.to_string() == "a1c1"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(Tablebase::new("/nonexistent/pawnyowl-syzygy").is_err());
+    }
+
+    /// Checks the probes against the real tables, which are too large to be kept in the
+    /// repository. Run it with `PAWNYOWL_SYZYGY_PATH` pointing to the 3-5 piece WDL tables.
+    #[ignore]
+    #[test]
+    fn test_real_tables() {
+        let path = env::var("PAWNYOWL_SYZYGY_PATH").expect("PAWNYOWL_SYZYGY_PATH is not set");
+        let tb = Tablebase::new(&path).unwrap();
+        assert_eq!(tb.max_pieces(), 5);
+
+        for (fen, wdl) in [
+            ("8/8/8/4k3/8/8/8/3QK3 w - - 0 1", Wdl::Win),
+            ("8/8/8/4k3/8/8/8/3QK3 b - - 0 1", Wdl::Loss),
+            ("8/8/8/4k3/8/8/8/R3K3 w - - 0 1", Wdl::Win),
+            ("8/8/8/4k3/8/8/8/2B1K3 w - - 0 1", Wdl::Draw),
+            ("8/8/8/4k3/8/8/8/1N2K3 b - - 0 1", Wdl::Draw),
+            ("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", Wdl::Win),
+            ("4k3/8/8/8/8/K7/4p3/8 b - - 0 1", Wdl::Win),
+            ("k7/8/8/8/8/8/P7/K7 w - - 0 1", Wdl::Draw),
+            // Stalemate.
+            ("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1", Wdl::Draw),
+            ("8/8/8/4k3/8/8/8/1N2KN2 w - - 0 1", Wdl::Draw),
+            ("8/8/8/4k3/8/4B3/8/2B1K3 w - - 0 1", Wdl::Draw),
+            ("8/8/8/4k3/8/8/8/2BNK3 w - - 0 1", Wdl::Win),
+            ("8/8/8/4k3/8/8/8/R3K2R b - - 0 1", Wdl::Loss),
+            ("8/8/8/4k3/8/8/8/R2QK2R w - - 0 1", Wdl::Win),
+            // The queen is captured.
+            ("8/8/8/8/8/3q4/4k3/7K w - - 0 1", Wdl::Loss),
+            ("8/8/8/8/8/8/4kQ2/7K b - - 0 1", Wdl::Draw),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert_eq!(tb.probe_wdl(&b), Some(wdl), "{}", fen);
+        }
+
+        // Only the moves that keep the queen win.
+        let b = Board::from_str("8/8/8/8/8/8/3k4/Q6K w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        MoveGen::new(&b).gen_legal(&mut moves);
+        assert_eq!(tb.filter_root_moves(&b, &mut moves), Some(Wdl::Win));
+        assert!(!moves.iter().any(|mv| mv.to_string() == "a1c1"));
+    }
+}
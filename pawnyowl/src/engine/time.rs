@@ -0,0 +1,77 @@
+use crate::intf::SearchConstraint;
+use pawnyowl_board::Color;
+use std::time::Duration;
+
+pub const MIN_TIME: Duration = Duration::from_millis(10);
+
+const DEFAULT_MOVES_TO_GO: u32 = 40;
+
+pub fn search_time(c: &SearchConstraint, side: Color, overhead: Duration) -> Option<Duration> {
+    let time = match c {
+        SearchConstraint::Infinite
+        | SearchConstraint::FixedDepth(_)
+        | SearchConstraint::FixedNodes(_)
+        | SearchConstraint::Mate(_) => return None,
+        SearchConstraint::FixedTime(time) => *time,
+        SearchConstraint::TimeControl(tc) => {
+            let s = match side {
+                Color::White => tc.white,
+                Color::Black => tc.black,
+            };
+            let moves_to_go = tc.moves_to_go.map_or(DEFAULT_MOVES_TO_GO, |m| m.get());
+            (s.time / moves_to_go + s.inc).min(s.time)
+        }
+    };
+    Some(time.saturating_sub(overhead).max(MIN_TIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intf::{TimeControl, TimeControlSide};
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn test_fixed_time() {
+        let c = SearchConstraint::FixedTime(Duration::from_millis(1000));
+        assert_eq!(
+            search_time(&c, Color::White, Duration::from_millis(100)),
+            Some(Duration::from_millis(900))
+        );
+        assert_eq!(
+            search_time(&c, Color::Black, Duration::ZERO),
+            Some(Duration::from_millis(1000))
+        );
+        assert_eq!(
+            search_time(&c, Color::White, Duration::from_millis(5000)),
+            Some(MIN_TIME)
+        );
+        assert_eq!(
+            search_time(&SearchConstraint::Infinite, Color::White, Duration::ZERO),
+            None
+        );
+    }
+
+    #[test]
+    fn test_time_control() {
+        let c = SearchConstraint::TimeControl(TimeControl {
+            white: TimeControlSide {
+                time: Duration::from_secs(10),
+                inc: Duration::from_millis(100),
+            },
+            black: TimeControlSide {
+                time: Duration::from_secs(40),
+                inc: Duration::ZERO,
+            },
+            moves_to_go: NonZeroU32::new(20),
+        });
+        assert_eq!(
+            search_time(&c, Color::White, Duration::from_millis(100)),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            search_time(&c, Color::Black, Duration::from_millis(100)),
+            Some(Duration::from_millis(1900))
+        );
+    }
+}
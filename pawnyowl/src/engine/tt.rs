@@ -0,0 +1,220 @@
+use crate::eval::score::Score;
+use crate::intf::score::Bound;
+use pawnyowl_board::{Move, moves::PackedMove};
+use std::mem;
+
+const BUCKET_SIZE: usize = 4;
+const HASHFULL_SAMPLE: usize = 1000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: u8,
+    pub score: Score,
+    pub bound: Bound,
+    pub best: PackedMove,
+}
+
+impl Entry {
+    #[inline]
+    fn empty() -> Self {
+        Entry {
+            key: 0,
+            depth: 0,
+            score: Score::new(0),
+            bound: Bound::Exact,
+            best: PackedMove::NULL,
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.depth == 0
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(align(64))]
+struct Bucket([Entry; BUCKET_SIZE]);
+
+/// Mate scores are relative to the root, while the same position may be reached at different
+/// plies. So they are stored relative to the position itself.
+#[inline]
+fn score_to_tt(score: Score, ply: usize) -> Score {
+    let (val, ply) = (score.value(), ply as i16);
+    if val > -Score::mate_bound().value() {
+        Score::new(val + ply)
+    } else if val < Score::mate_bound().value() {
+        Score::new(val - ply)
+    } else {
+        score
+    }
+}
+
+#[inline]
+fn score_from_tt(score: Score, ply: usize) -> Score {
+    let (val, ply) = (score.value(), ply as i16);
+    if val > -Score::mate_bound().value() {
+        Score::new(val - ply)
+    } else if val < Score::mate_bound().value() {
+        Score::new(val + ply)
+    } else {
+        score
+    }
+}
+
+/// Fixed-size hash table of search results keyed by zobrist hash.
+///
+/// Entries are grouped in cache-line-sized buckets. On store, the entry for the same position is
+/// overwritten, or the shallowest one in the bucket if there is none. Only the entries with
+/// positive depth are stored, so zero depth marks an empty slot.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let len = (size_mb << 20) / mem::size_of::<Bucket>();
+        TranspositionTable {
+            buckets: vec![Bucket([Entry::empty(); BUCKET_SIZE]); len.max(1)],
+        }
+    }
+
+    /// Number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.fill(Bucket([Entry::empty(); BUCKET_SIZE]));
+    }
+
+    #[inline]
+    fn index(&self, key: u64) -> usize {
+        ((key as u128 * self.buckets.len() as u128) >> 64) as usize
+    }
+
+    /// Looks up the position, with the mate scores adjusted to be relative to the root.
+    pub fn probe(&self, key: u64, ply: usize) -> Option<Entry> {
+        let e = self.buckets[self.index(key)]
+            .0
+            .iter()
+            .find(|e| !e.is_empty() && e.key == key)?;
+        Some(Entry {
+            score: score_from_tt(e.score, ply),
+            ..*e
+        })
+    }
+
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: usize,
+        ply: usize,
+        score: Score,
+        bound: Bound,
+        best: Move,
+    ) {
+        assert!(depth > 0);
+        let idx = self.index(key);
+        let bucket = &mut self.buckets[idx];
+        let slot = match bucket.0.iter().position(|e| e.key == key && !e.is_empty()) {
+            Some(pos) => pos,
+            None => (0..BUCKET_SIZE).min_by_key(|&i| bucket.0[i].depth).unwrap(),
+        };
+        bucket.0[slot] = Entry {
+            key,
+            depth: depth.min(u8::MAX as usize) as u8,
+            score: score_to_tt(score, ply),
+            bound,
+            best: best.into(),
+        };
+    }
+
+    /// Table occupancy in permille, as reported by UCI `hashfull`. Estimated from the first
+    /// `HASHFULL_SAMPLE` entries, as the keys are spread uniformly over the table.
+    pub fn hashfull(&self) -> u16 {
+        let sample = self
+            .buckets
+            .iter()
+            .flat_map(|b| b.0.iter())
+            .take(HASHFULL_SAMPLE);
+        let (total, used) = sample.fold((0, 0), |(total, used), e| {
+            (total + 1, used + usize::from(!e.is_empty()))
+        });
+        (used * 1000 / total) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_probe() {
+        let mut tt = TranspositionTable::new(1);
+        assert_eq!(mem::size_of::<Bucket>(), 64);
+        assert_eq!(tt.hashfull(), 0);
+        assert_eq!(tt.probe(42, 0), None);
+
+        let mv = Move::from_uci("e2e4", &pawnyowl_board::Board::start()).unwrap();
+        tt.store(42, 3, 0, Score::new(15), Bound::Lower, mv);
+        let e = tt.probe(42, 0).unwrap();
+        assert_eq!(
+            (e.depth, e.score, e.bound),
+            (3, Score::new(15), Bound::Lower)
+        );
+        assert_eq!(Move::from(e.best), mv);
+
+        tt.store(42, 5, 0, Score::new(-7), Bound::Exact, Move::NULL);
+        assert_eq!(tt.probe(42, 0).unwrap().depth, 5);
+
+        // The mate found 5 plies from the root at ply 2 is the mate in 3 plies from there.
+        let mate = -Score::mate(5);
+        tt.store(7, 1, 2, mate, Bound::Exact, Move::NULL);
+        assert_eq!(tt.probe(7, 2).unwrap().score, mate);
+        assert_eq!(tt.probe(7, 4).unwrap().score, -Score::mate(7));
+        tt.store(8, 1, 2, -mate, Bound::Exact, Move::NULL);
+        assert_eq!(tt.probe(8, 0).unwrap().score, Score::mate(3));
+
+        for i in 1..20000_u64 {
+            let key = i.wrapping_mul(0x9e3779b97f4a7c15);
+            tt.store(key, 1, 0, Score::new(0), Bound::Upper, Move::NULL);
+        }
+        assert!((250..=350).contains(&tt.hashfull()), "{}", tt.hashfull());
+        tt.clear();
+        assert_eq!(tt.hashfull(), 0);
+        assert_eq!(tt.probe(42, 0), None);
+    }
+
+    /// Stores `count` entries in the bucket `idx`.
+    fn fill(tt: &mut TranspositionTable, idx: usize, count: usize) {
+        let first = ((idx as u128) << 64).div_ceil(tt.buckets.len() as u128) as u64;
+        for key in first..first + count as u64 {
+            assert_eq!(tt.index(key), idx);
+            tt.store(key, 1, 0, Score::new(0), Bound::Exact, Move::NULL);
+        }
+    }
+
+    #[test]
+    fn test_hashfull() {
+        let mut tt = TranspositionTable::new(1);
+        fill(&mut tt, 0, 1);
+        assert_eq!(tt.hashfull(), 1);
+        fill(&mut tt, 0, BUCKET_SIZE);
+        assert_eq!(tt.hashfull(), 4);
+        let sampled = HASHFULL_SAMPLE / BUCKET_SIZE;
+        for idx in 0..sampled / 2 {
+            fill(&mut tt, idx, BUCKET_SIZE);
+        }
+        assert_eq!(tt.hashfull(), 500);
+        // The buckets outside the sample are not counted.
+        fill(&mut tt, sampled, BUCKET_SIZE);
+        fill(&mut tt, sampled + 1, 1);
+        assert_eq!(tt.hashfull(), 500);
+        for idx in 0..sampled {
+            fill(&mut tt, idx, BUCKET_SIZE);
+        }
+        assert_eq!(tt.hashfull(), 1000);
+    }
+}
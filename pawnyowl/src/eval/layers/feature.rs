@@ -29,6 +29,16 @@ impl ScorePair {
         ScorePair(f.value() as i32 + (s.value() as i32) * (1 << 16))
     }
 
+    #[inline]
+    pub fn from_raw(val: i32) -> Self {
+        ScorePair(val)
+    }
+
+    #[inline]
+    pub fn as_raw(self) -> i32 {
+        self.0
+    }
+
     #[inline]
     pub fn first(self) -> Score {
         Score::new(self.0 as i16)
@@ -36,9 +46,10 @@ impl ScorePair {
 
     #[inline]
     pub fn second(self) -> Score {
+        // Negative first half borrows one from the second half, so give it back.
         let mut res = self.0 >> 16;
         if self.first().value() < 0 {
-            res -= 1;
+            res += 1;
         }
         Score::new(res as i16)
     }
@@ -53,7 +64,7 @@ impl Mul<i32> for ScorePair {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct PsqFeatureSlice {
     pub score: ScorePair,
     pub stage: Stage,
@@ -74,11 +85,27 @@ impl PsqFeatureLayer {
         Self { weights }
     }
 
+    /// Builds the layer with the weights given by `weight` for each piece and square.
+    pub fn from_fn(weight: impl Fn(Cell, Sq) -> ScorePair) -> Self {
+        let mut weights = [ScorePair::default(); 64 * Cell::COUNT];
+        for cell in Cell::iter().filter(|&c| c != Cell::None) {
+            for sq in Sq::iter() {
+                weights[Self::input_index(cell, sq)] = weight(cell, sq);
+            }
+        }
+        Self::new(weights)
+    }
+
     #[inline]
     pub fn input_index(cell: Cell, sq: Sq) -> usize {
         cell.index() * 64 + sq.index()
     }
 
+    #[inline]
+    pub fn weight(&self, cell: Cell, sq: Sq) -> ScorePair {
+        self.weights[Self::input_index(cell, sq)]
+    }
+
     #[inline]
     pub fn init_feature_slice(&self) -> PsqFeatureSlice {
         PsqFeatureSlice {
@@ -96,6 +123,41 @@ impl PsqFeatureLayer {
         delta: i32,
     ) {
         features.score += self.weights[Self::input_index(cell, sq)] * delta;
-        features.stage += ((Self::STAGE_WEIGHTS[cell.index()] as i32) * delta) as u8;
+        // Removing a piece gives a negative delta, so compute the stage as signed.
+        let stage =
+            i32::from(features.stage) + i32::from(Self::STAGE_WEIGHTS[cell.index()]) * delta;
+        debug_assert!((0..=i32::from(Stage::MAX)).contains(&stage));
+        features.stage = stage as Stage;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_pair() {
+        for (f, s) in [
+            (0, 0),
+            (-1, 0),
+            (-1, 5),
+            (-300, -20),
+            (-300, 20),
+            (150, -75),
+            (i16::MIN + 1, i16::MAX),
+            (-42, i16::MIN + 1),
+        ] {
+            let pair = ScorePair::new(Score::new(f), Score::new(s));
+            assert_eq!(pair.first(), Score::new(f), "{} {}", f, s);
+            assert_eq!(pair.second(), Score::new(s), "{} {}", f, s);
+        }
+
+        // A negative first half stays separate after the arithmetic, too.
+        let a = ScorePair::new(Score::new(-10), Score::new(7));
+        let b = ScorePair::new(Score::new(-5), Score::new(-3));
+        assert_eq!((a + b).first(), Score::new(-15));
+        assert_eq!((a + b).second(), Score::new(4));
+        assert_eq!((a * -3).second(), Score::new(-21));
+        assert_eq!((a - b * 2).second(), Score::new(13));
     }
 }
@@ -0,0 +1,151 @@
+use crate::eval::{
+    self,
+    layers::feature::ScorePair,
+    score::{Score, Stage},
+};
+use pawnyowl_board::{
+    Board, Color, Piece,
+    board::PIECE_VALUES,
+    geometry::bitboard::{DARK, LIGHT},
+};
+use serde::{Deserialize, Serialize};
+
+/// Draw scale factor that leaves the score unchanged.
+pub const SCALE_ONE: i32 = 64;
+
+/// Material corrections not covered by the piece-square tables.
+///
+/// Grants a bonus for the bishop pair, and scales the final score towards zero in material
+/// configurations that are hard to win.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MaterialLayer {
+    bishop_pair: ScorePair,
+}
+
+impl MaterialLayer {
+    #[inline]
+    pub fn new(bishop_pair: ScorePair) -> Self {
+        Self { bishop_pair }
+    }
+
+    /// The layer that scores nothing. The draw scaling does not depend on the weights.
+    #[inline]
+    pub fn zero() -> Self {
+        Self::new(ScorePair::default())
+    }
+
+    #[inline]
+    fn has_bishop_pair(board: &Board, c: Color) -> bool {
+        let bishops = board.piece(c, Piece::Bishop);
+        (bishops & LIGHT).is_nonempty() && (bishops & DARK).is_nonempty()
+    }
+
+    #[inline]
+    pub fn apply(&self, board: &Board, stage: Stage) -> Score {
+        let mut score = ScorePair::default();
+        if Self::has_bishop_pair(board, Color::White) {
+            score += self.bishop_pair;
+        }
+        if Self::has_bishop_pair(board, Color::Black) {
+            score -= self.bishop_pair;
+        }
+        eval::tapered(score.first(), score.second(), stage)
+    }
+
+    /// Returns the scale factor for the score, out of `SCALE_ONE`, if the side `strong` is
+    /// winning.
+    pub fn draw_scale(board: &Board, strong: Color) -> i32 {
+        if board.is_insufficient_material() {
+            return 0;
+        }
+        let weak = strong.inv();
+        let count = |p| board.piece_count(Color::White, p) + board.piece_count(Color::Black, p);
+        let (strong_pawns, weak_pawns) = (
+            board.piece_count(strong, Piece::Pawn),
+            board.piece_count(weak, Piece::Pawn),
+        );
+        if [Piece::Knight, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .all(|p| count(p) == 0)
+            && board.has_opposite_colored_bishops()
+        {
+            // Opposite-colored bishops are drawish even with a pawn or two more.
+            return if strong_pawns <= weak_pawns + 2 {
+                16
+            } else {
+                32
+            };
+        }
+        let advantage = board.material(strong) - board.material(weak);
+        if strong_pawns == 0 && advantage <= PIECE_VALUES[Piece::Bishop.index()] {
+            // Without pawns, being a minor piece up is usually not enough to win.
+            return 16;
+        }
+        SCALE_ONE
+    }
+
+    /// Scales the combined score of all the layers.
+    #[inline]
+    pub fn scale(board: &Board, score: Score) -> Score {
+        let strong = if score.value() >= 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Score::from(i32::from(score) * Self::draw_scale(board, strong) / SCALE_ONE)
+    }
+}
+
+impl Default for MaterialLayer {
+    fn default() -> Self {
+        Self::new(ScorePair::new(Score::new(30), Score::new(50)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::layers::feature::PsqFeatureLayer;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_bishop_pair() {
+        let layer = MaterialLayer::default();
+        let stage = PsqFeatureLayer::INIT_STAGE;
+        assert_eq!(layer.apply(&Board::start(), stage), Score::new(0));
+
+        let pair = Board::from_str("2b1k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+        assert_eq!(layer.apply(&pair, stage), Score::new(30));
+        assert_eq!(layer.apply(&pair, 0), Score::new(50));
+        assert_eq!(layer.apply(&pair.mirror(), 0), Score::new(-50));
+
+        // Two bishops of the same color are not a pair.
+        let same = Board::from_str("4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1").unwrap();
+        assert_eq!(layer.apply(&same, stage), Score::new(0));
+    }
+
+    #[test]
+    fn test_draw_scale() {
+        for (fen, strong, scale) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", Color::White, 0),
+            ("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::White, 0),
+            ("4k3/p7/2b5/8/8/8/PPP5/2B1K3 w - - 0 1", Color::White, 16),
+            ("4k3/8/2b5/8/8/8/PPPP4/2B1K3 w - - 0 1", Color::White, 32),
+            (
+                "4k3/p7/1b6/8/8/8/PPP5/2B1K3 w - - 0 1",
+                Color::White,
+                SCALE_ONE,
+            ),
+            ("4k3/8/1b6/8/8/8/8/R3K3 w - - 0 1", Color::White, 16),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White, SCALE_ONE),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::Black, 16),
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert_eq!(MaterialLayer::draw_scale(&b, strong), scale, "{}", fen);
+        }
+
+        let b = Board::from_str("4k3/p7/2b5/8/8/8/PPP5/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(MaterialLayer::scale(&b, Score::new(100)), Score::new(25));
+        assert_eq!(MaterialLayer::scale(&b, Score::new(-100)), Score::new(-25));
+    }
+}
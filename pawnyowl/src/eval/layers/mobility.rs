@@ -0,0 +1,111 @@
+use crate::eval::{
+    self,
+    layers::feature::ScorePair,
+    score::{Score, Stage},
+};
+use pawnyowl_board::{Bitboard, Board, Color, Piece, Sq, attack};
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of squares a piece can attack, reached by a queen in the center.
+pub const MAX_MOBILITY: usize = 27;
+
+/// Pieces scored by mobility, in the order of the weight curves.
+pub const PIECES: [Piece; 5] = [
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+/// Scores each piece by the number of squares it attacks that are not occupied by its own pieces.
+///
+/// Each kind of piece has its own curve of weights indexed by the number of such squares. The
+/// attacks depend on the whole board, so the layer is recomputed for each position.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MobilityLayer {
+    weights: [[ScorePair; MAX_MOBILITY + 1]; PIECES.len()],
+}
+
+impl MobilityLayer {
+    #[inline]
+    pub fn new(weights: [[ScorePair; MAX_MOBILITY + 1]; PIECES.len()]) -> Self {
+        Self { weights }
+    }
+
+    /// The layer that scores nothing.
+    #[inline]
+    pub fn zero() -> Self {
+        Self::new([[ScorePair::default(); MAX_MOBILITY + 1]; PIECES.len()])
+    }
+
+    #[inline]
+    fn attacks(p: Piece, sq: Sq, occupied: Bitboard) -> Bitboard {
+        match p {
+            Piece::Knight => attack::knight(sq),
+            Piece::Bishop => attack::bishop(sq, occupied),
+            Piece::Rook => attack::rook(sq, occupied),
+            Piece::Queen => attack::bishop(sq, occupied) | attack::rook(sq, occupied),
+            Piece::King => attack::king(sq),
+            Piece::Pawn => unreachable!(),
+        }
+    }
+
+    fn side_score(&self, board: &Board, c: Color) -> ScorePair {
+        let (own, occupied) = (board.color(c), board.all());
+        let mut res = ScorePair::default();
+        for (curve, p) in self.weights.iter().zip(PIECES) {
+            for sq in board.piece(c, p) {
+                let mobility = (Self::attacks(p, sq, occupied) & !own).len();
+                res += curve[mobility as usize];
+            }
+        }
+        res
+    }
+
+    #[inline]
+    pub fn apply(&self, board: &Board, stage: Stage) -> Score {
+        let score = self.side_score(board, Color::White) - self.side_score(board, Color::Black);
+        eval::tapered(score.first(), score.second(), stage)
+    }
+}
+
+impl Default for MobilityLayer {
+    /// Linear curves around the typical mobility of each piece. The king is not scored.
+    fn default() -> Self {
+        // Typical mobility, then midgame and endgame weights per square.
+        const CURVES: [(i16, i16, i16); PIECES.len()] =
+            [(4, 4, 4), (6, 5, 5), (7, 2, 4), (13, 1, 2), (0, 0, 0)];
+        let mut weights = [[ScorePair::default(); MAX_MOBILITY + 1]; PIECES.len()];
+        for (curve, (avg, mg, eg)) in weights.iter_mut().zip(CURVES) {
+            for (n, pair) in curve.iter_mut().enumerate() {
+                let delta = n as i16 - avg;
+                *pair = ScorePair::new(Score::new(delta * mg), Score::new(delta * eg));
+            }
+        }
+        Self::new(weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::layers::feature::PsqFeatureLayer;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_mobility() {
+        let layer = MobilityLayer::default();
+        let stage = PsqFeatureLayer::INIT_STAGE;
+        assert_eq!(layer.apply(&Board::start(), stage), Score::new(0));
+
+        // The same bishop is worth more on an open diagonal than behind its own pawns.
+        let open = Board::from_str("4k3/8/8/8/8/8/3P4/2B1K3 w - - 0 1").unwrap();
+        let closed = Board::from_str("4k3/8/8/8/8/8/1P1P4/2B1K3 w - - 0 1").unwrap();
+        assert!(layer.apply(&open, stage) > layer.apply(&closed, stage));
+        assert_eq!(
+            layer.apply(&open, stage),
+            -layer.apply(&open.mirror(), stage)
+        );
+    }
+}
@@ -1 +1,5 @@
 pub mod feature;
+pub mod material;
+pub mod mobility;
+pub mod pawns;
+pub mod tropism;
@@ -0,0 +1,240 @@
+use crate::eval::{
+    self,
+    layers::feature::ScorePair,
+    score::{Score, Stage},
+};
+use pawnyowl_board::{Bitboard, Board, Color, Piece, pawns};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const CACHE_BITS: usize = 14;
+
+/// Cache of pawn structure scores keyed by the pawn bitboards.
+///
+/// Each entry stores the key xored with the value, so a torn entry written concurrently is
+/// detected as a miss.
+pub struct PawnCache {
+    entries: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl PawnCache {
+    pub fn new() -> Self {
+        Self {
+            entries: (0..1 << CACHE_BITS)
+                .map(|_| (AtomicU64::new(0), AtomicU64::new(0)))
+                .collect(),
+        }
+    }
+
+    #[inline]
+    fn key(white: Bitboard, black: Bitboard) -> u64 {
+        let key = white.as_raw().wrapping_mul(0x9e3779b97f4a7c15)
+            ^ black
+                .as_raw()
+                .rotate_left(32)
+                .wrapping_mul(0xc2b2ae3d27d4eb4f);
+        // Zero marks an empty entry.
+        key | 1
+    }
+
+    #[inline]
+    fn get(&self, key: u64) -> Option<ScorePair> {
+        let (k, v) = &self.entries[(key >> (64 - CACHE_BITS)) as usize];
+        let val = v.load(Ordering::Relaxed);
+        (k.load(Ordering::Relaxed) ^ val == key).then_some(ScorePair::from_raw(val as u32 as i32))
+    }
+
+    #[inline]
+    fn put(&self, key: u64, score: ScorePair) {
+        let (k, v) = &self.entries[(key >> (64 - CACHE_BITS)) as usize];
+        let val = score.as_raw() as u32 as u64;
+        k.store(key ^ val, Ordering::Relaxed);
+        v.store(val, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        for (k, v) in &self.entries {
+            k.store(0, Ordering::Relaxed);
+            v.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for PawnCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for PawnCache {
+    /// The cached scores depend on the weights, so the copy starts empty.
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+/// Scores passed, doubled and isolated pawns.
+///
+/// Pawn structure changes rarely, so the layer may cache the scores by the pawn bitboards. The
+/// cache is off by default, see [`PawnLayer::with_cache`].
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PawnLayer {
+    /// Passed pawn weights, indexed by the rank relative to the pawn's side.
+    passed: [ScorePair; 8],
+    doubled: ScorePair,
+    isolated: ScorePair,
+    #[serde(skip)]
+    cache: Option<PawnCache>,
+}
+
+impl PawnLayer {
+    #[inline]
+    pub fn new(passed: [ScorePair; 8], doubled: ScorePair, isolated: ScorePair) -> Self {
+        Self {
+            passed,
+            doubled,
+            isolated,
+            cache: None,
+        }
+    }
+
+    /// Enables the cache of the pawn structure scores.
+    #[inline]
+    pub fn with_cache(mut self) -> Self {
+        self.cache = Some(PawnCache::new());
+        self
+    }
+
+    /// Drops the cached scores, if the cache is enabled.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// The layer that scores nothing.
+    #[inline]
+    pub fn zero() -> Self {
+        Self::new(
+            [ScorePair::default(); 8],
+            ScorePair::default(),
+            ScorePair::default(),
+        )
+    }
+
+    fn side_score(&self, c: Color, own: Bitboard, enemy: Bitboard) -> ScorePair {
+        let mut res = ScorePair::default();
+        for sq in pawns::passed(c, own, enemy) {
+            let rank = match c {
+                Color::White => 7 - sq.rank().index(),
+                Color::Black => sq.rank().index(),
+            };
+            res += self.passed[rank];
+        }
+        res += self.doubled * pawns::doubled(c, own).len() as i32;
+        res += self.isolated * pawns::isolated(own).len() as i32;
+        res
+    }
+
+    fn compute(&self, white: Bitboard, black: Bitboard) -> ScorePair {
+        self.side_score(Color::White, white, black) - self.side_score(Color::Black, black, white)
+    }
+
+    fn score(&self, white: Bitboard, black: Bitboard) -> ScorePair {
+        let Some(cache) = &self.cache else {
+            return self.compute(white, black);
+        };
+        let key = PawnCache::key(white, black);
+        if let Some(score) = cache.get(key) {
+            return score;
+        }
+        let score = self.compute(white, black);
+        cache.put(key, score);
+        score
+    }
+
+    #[inline]
+    pub fn apply(&self, board: &Board, stage: Stage) -> Score {
+        let score = self.score(
+            board.piece(Color::White, Piece::Pawn),
+            board.piece(Color::Black, Piece::Pawn),
+        );
+        eval::tapered(score.first(), score.second(), stage)
+    }
+}
+
+impl Default for PawnLayer {
+    fn default() -> Self {
+        const PASSED: [(i16, i16); 8] = [
+            (0, 0),
+            (5, 10),
+            (10, 20),
+            (20, 35),
+            (35, 60),
+            (60, 100),
+            (100, 150),
+            (0, 0),
+        ];
+        let pair = |(mg, eg): (i16, i16)| ScorePair::new(Score::new(mg), Score::new(eg));
+        Self::new(PASSED.map(pair), pair((-10, -20)), pair((-10, -15)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::layers::feature::PsqFeatureLayer;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_pawns() {
+        let layer = PawnLayer::default();
+        let stage = PsqFeatureLayer::INIT_STAGE;
+        assert_eq!(layer.apply(&Board::start(), stage), Score::new(0));
+
+        // A passed pawn is worth more the farther it is advanced, and more in the endgame.
+        let b = Board::from_str("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1").unwrap();
+        let far = Board::from_str("4k3/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(layer.apply(&far, stage) > layer.apply(&b, stage));
+        assert!(layer.apply(&b, 0) > layer.apply(&b, stage));
+        assert_eq!(layer.apply(&b, 0), -layer.apply(&b.mirror(), 0));
+
+        // Doubled and isolated pawns are penalized.
+        let good = Board::from_str("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1").unwrap();
+        let bad = Board::from_str("4k3/pp6/8/8/8/P7/P7/4K3 w - - 0 1").unwrap();
+        assert!(layer.apply(&good, stage) > layer.apply(&bad, stage));
+    }
+
+    #[test]
+    fn test_cache() {
+        let layer = PawnLayer::default().with_cache();
+        for fen in [
+            "4k3/pp6/8/8/8/P7/P7/4K3 w - - 0 1",
+            "4k3/8/8/8/8/4P3/8/4K3 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let (white, black) = (
+                b.piece(Color::White, Piece::Pawn),
+                b.piece(Color::Black, Piece::Pawn),
+            );
+            let direct = layer.side_score(Color::White, white, black)
+                - layer.side_score(Color::Black, black, white);
+            let key = PawnCache::key(white, black);
+
+            assert_eq!(layer.score(white, black), direct, "{}", fen);
+            let cache = layer.cache.as_ref().unwrap();
+            assert_eq!(cache.get(key), Some(direct), "{}", fen);
+            assert_eq!(layer.score(white, black), direct, "{}", fen);
+
+            // The clone starts with an empty cache.
+            let fresh = layer.clone();
+            assert_eq!(fresh.cache.as_ref().unwrap().get(key), None, "{}", fen);
+            assert_eq!(fresh.score(white, black), direct, "{}", fen);
+
+            layer.clear_cache();
+            assert_eq!(cache.get(key), None, "{}", fen);
+        }
+        assert!(PawnLayer::default().cache.is_none());
+    }
+}
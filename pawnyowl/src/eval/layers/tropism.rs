@@ -0,0 +1,48 @@
+use crate::eval::{
+    self,
+    score::{Score, Stage},
+};
+use pawnyowl_board::{Board, Color, Piece};
+
+pub struct TropismLayer;
+
+impl TropismLayer {
+    pub const WEIGHTS: [i32; Piece::COUNT] = [0, 0, 3, 2, 2, 5];
+
+    fn side_score(board: &Board, c: Color) -> i32 {
+        let king = board.king_pos(c.inv());
+        let mut res = 0;
+        for p in Piece::iter() {
+            let weight = Self::WEIGHTS[p.index()];
+            if weight == 0 {
+                continue;
+            }
+            for sq in board.piece(c, p) {
+                res += weight * (7 - i32::from(sq.king_distance(king)));
+            }
+        }
+        res
+    }
+
+    #[inline]
+    pub fn apply(board: &Board, stage: Stage) -> Score {
+        let mg = Self::side_score(board, Color::White) - Self::side_score(board, Color::Black);
+        eval::tapered(Score::from(mg), Score::new(0), stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::layers::feature::PsqFeatureLayer;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_queen_near_king() {
+        let stage = PsqFeatureLayer::INIT_STAGE;
+        let far = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/Q5K1 w - - 0 1").unwrap();
+        let near = Board::from_str("6k1/5ppp/5Q2/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert!(TropismLayer::apply(&near, stage) > TropismLayer::apply(&far, stage));
+        assert_eq!(TropismLayer::apply(&near, 0), Score::new(0));
+    }
+}
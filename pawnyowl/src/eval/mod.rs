@@ -1,3 +1,51 @@
 pub mod layers;
 pub mod model;
+pub mod nnue;
 pub mod score;
+
+use layers::feature::PsqFeatureLayer;
+use model::Model;
+use pawnyowl_board::Board;
+use score::{Score, Stage};
+
+#[inline]
+pub fn tapered(mg: Score, eg: Score, phase: Stage) -> Score {
+    let init = PsqFeatureLayer::INIT_STAGE as i32;
+    let phase = (phase as i32).min(init);
+    Score::from((i32::from(mg) * phase + i32::from(eg) * (init - phase)) / init)
+}
+
+/// Scores the position from White's point of view.
+///
+/// Builds the tag from scratch, so it suits one-shot scoring. The search updates the tags
+/// incrementally instead.
+pub fn evaluate<M: Model>(board: &Board, model: &M) -> Score {
+    model.apply(&model.build_tag(board), board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::PsqModel;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_tapered() {
+        let (mg, eg) = (Score::new(120), Score::new(-40));
+        assert_eq!(tapered(mg, eg, 0), eg);
+        assert_eq!(tapered(mg, eg, PsqFeatureLayer::INIT_STAGE), mg);
+        assert_eq!(tapered(mg, eg, 30), mg);
+        assert_eq!(tapered(mg, eg, 12), Score::new(40));
+        assert_eq!(tapered(mg, eg, 6), Score::new(0));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let model = PsqModel::new();
+        let b = Board::from_str("8/8/4k3/3r4/8/3RP3/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(evaluate(&b, &model), model.apply(&model.build_tag(&b), &b));
+        // The score does not depend on the side to move.
+        let other = Board::from_str("8/8/4k3/3r4/8/3RP3/4K3/8 b - - 0 1").unwrap();
+        assert_eq!(evaluate(&b, &model), evaluate(&other, &model));
+    }
+}
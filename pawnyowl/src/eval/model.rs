@@ -1,28 +1,134 @@
 use crate::eval::{
-    layers::feature::{PsqFeatureLayer, PsqFeatureSlice},
-    score::{Score, Stage},
+    self,
+    layers::{
+        feature::{PsqFeatureLayer, PsqFeatureSlice},
+        material::MaterialLayer,
+        mobility::MobilityLayer,
+        pawns::PawnLayer,
+        tropism::TropismLayer,
+    },
+    score::Score,
 };
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
 use pawnyowl_board::{
-    Board, Cell, Color, Move, Sq,
+    Board, Cell, Move, Sq,
     diff::{self, DiffListener},
     moves::RawUndo,
 };
-use serde::{Deserialize, Serialize};
-use std::{cmp, fs::File, io::Write};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{any::Any, fs::File, io::Write};
+
+const MODEL_MAGIC: &[u8; 4] = b"PWOL";
+const MODEL_VERSION: u32 = 6;
 
 pub trait Model: Sized {
-    type Tag;
+    type Tag: Clone;
 
     fn new() -> Self;
     fn build_tag(&self, board: &Board) -> Self::Tag;
     unsafe fn after_move(&self, tag: &mut Self::Tag, board: &Board, mv: Move, u: &RawUndo);
-    fn apply(&self, tag: &Self::Tag, move_side: Color) -> Score;
+    fn apply(&self, tag: &Self::Tag, board: &Board) -> Score;
+
+    /// Breaks the evaluation down into named components, which add up to the score of `apply`.
+    fn explain(&self, _tag: &Self::Tag, _board: &Board) -> Vec<(&'static str, Score)> {
+        Vec::new()
+    }
+
+    /// Drops the scores cached by the model, if any.
+    fn clear_cache(&self) {}
+}
+
+pub type DynTag = Box<dyn Any>;
+
+/// Decodes the model weights stored after the magic and the format version.
+pub(crate) fn decode<T: DeserializeOwned>(data: &[u8], magic: &[u8; 4], version: u32) -> Result<T> {
+    let data = data
+        .strip_prefix(magic)
+        .ok_or_else(|| anyhow!("bad model magic"))?;
+    let (ver, data) = data
+        .split_first_chunk()
+        .ok_or_else(|| anyhow!("model header is truncated"))?;
+    let ver = u32::from_le_bytes(*ver);
+    if ver != version {
+        bail!("unsupported model version {}", ver);
+    }
+    bincode::deserialize(data).context("decoding model")
+}
+
+pub(crate) fn encode<T: Serialize>(model: &T, magic: &[u8; 4], version: u32) -> Result<Vec<u8>> {
+    let mut data = magic.to_vec();
+    data.extend_from_slice(&version.to_le_bytes());
+    bincode::serialize_into(&mut data, model)?;
+    Ok(data)
+}
+
+pub(crate) fn store(data: &[u8], path: &str) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+/// Object-safe counterpart of [`Model`], so the model can be chosen at runtime.
+///
+/// Tags are type-erased, and passing a tag built by another model panics.
+pub trait DynModel: Send + Sync {
+    fn build_tag(&self, board: &Board) -> DynTag;
+    fn copy_tag(&self, dst: &mut DynTag, src: &DynTag);
+    unsafe fn after_move(&self, tag: &mut DynTag, board: &Board, mv: Move, u: &RawUndo);
+    fn apply(&self, tag: &DynTag, board: &Board) -> Score;
+    fn explain(&self, tag: &DynTag, board: &Board) -> Vec<(&'static str, Score)>;
+    fn clear_cache(&self);
+}
+
+impl<M: Model + Send + Sync> DynModel for M
+where
+    M::Tag: 'static,
+{
+    #[inline]
+    fn build_tag(&self, board: &Board) -> DynTag {
+        Box::new(Model::build_tag(self, board))
+    }
+
+    #[inline]
+    fn copy_tag(&self, dst: &mut DynTag, src: &DynTag) {
+        let src: &M::Tag = src.downcast_ref().expect("tag belongs to another model");
+        let dst: &mut M::Tag = dst.downcast_mut().expect("tag belongs to another model");
+        dst.clone_from(src);
+    }
+
+    #[inline]
+    unsafe fn after_move(&self, tag: &mut DynTag, board: &Board, mv: Move, u: &RawUndo) {
+        let tag = tag.downcast_mut().expect("tag belongs to another model");
+        unsafe { Model::after_move(self, tag, board, mv, u) };
+    }
+
+    #[inline]
+    fn apply(&self, tag: &DynTag, board: &Board) -> Score {
+        let tag = tag.downcast_ref().expect("tag belongs to another model");
+        Model::apply(self, tag, board)
+    }
+
+    #[inline]
+    fn explain(&self, tag: &DynTag, board: &Board) -> Vec<(&'static str, Score)> {
+        let tag = tag.downcast_ref().expect("tag belongs to another model");
+        Model::explain(self, tag, board)
+    }
+
+    #[inline]
+    fn clear_cache(&self) {
+        Model::clear_cache(self)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PsqModel {
     feature_layer: PsqFeatureLayer,
+    mobility_layer: MobilityLayer,
+    pawn_layer: PawnLayer,
+    material_layer: MaterialLayer,
+    /// Raw weight of one centipawn of the feature layer in the learner, so that the training can
+    /// be resumed from the stored weights.
+    weight_scale: f32,
 }
 
 struct PsqListener<'a> {
@@ -61,19 +167,15 @@ impl Model for PsqModel {
 
     #[inline]
     fn new() -> Self {
-        let bytes = include_bytes!("../../data/model.paw");
-        bincode::deserialize(bytes).unwrap()
+        Self::load(include_bytes!("../../data/model.paw")).unwrap()
     }
 
     #[inline]
     fn build_tag(&self, board: &Board) -> Self::Tag {
         let mut feature_slice = self.feature_layer.init_feature_slice();
-        for sq in Sq::iter() {
-            let cell = board.get(sq);
-            if cell != Cell::None {
-                self.feature_layer
-                    .update_feature_slice(&mut feature_slice, cell, sq, 1);
-            }
+        for (sq, cell) in board.iter_pieces() {
+            self.feature_layer
+                .update_feature_slice(&mut feature_slice, cell, sq, 1);
         }
         feature_slice
     }
@@ -94,27 +196,250 @@ impl Model for PsqModel {
     }
 
     #[inline]
-    fn apply(&self, feature_slice: &PsqFeatureSlice, _move_side: Color) -> Score {
-        let clipped_stage =
-            cmp::min(feature_slice.stage, PsqFeatureLayer::INIT_STAGE as Stage) as i32;
-        Score::from(
-            i32::from(feature_slice.score.first()) * clipped_stage
-                + i32::from(feature_slice.score.second())
-                    * (PsqFeatureLayer::INIT_STAGE as i32 - clipped_stage),
-        )
+    fn apply(&self, feature_slice: &PsqFeatureSlice, board: &Board) -> Score {
+        let psq = eval::tapered(
+            feature_slice.score.first(),
+            feature_slice.score.second(),
+            feature_slice.stage,
+        );
+        let stage = feature_slice.stage;
+        let score = psq
+            + self.mobility_layer.apply(board, stage)
+            + TropismLayer::apply(board, stage)
+            + self.pawn_layer.apply(board, stage)
+            + self.material_layer.apply(board, stage);
+        MaterialLayer::scale(board, score)
+    }
+
+    fn explain(
+        &self,
+        feature_slice: &PsqFeatureSlice,
+        board: &Board,
+    ) -> Vec<(&'static str, Score)> {
+        let stage = feature_slice.stage;
+        let psq = eval::tapered(
+            feature_slice.score.first(),
+            feature_slice.score.second(),
+            stage,
+        );
+        let mut res = vec![
+            ("psq", psq),
+            ("mobility", self.mobility_layer.apply(board, stage)),
+            ("king safety", TropismLayer::apply(board, stage)),
+            ("pawns", self.pawn_layer.apply(board, stage)),
+            ("material", self.material_layer.apply(board, stage)),
+        ];
+        let score = res.iter().fold(Score::new(0), |acc, &(_, s)| acc + s);
+        res.push(("draw scale", MaterialLayer::scale(board, score) - score));
+        res
+    }
+
+    fn clear_cache(&self) {
+        self.pawn_layer.clear_cache();
     }
 }
 
 impl PsqModel {
     #[inline]
-    pub fn from_layers(feature_layer: PsqFeatureLayer) -> Self {
-        Self { feature_layer }
+    pub fn from_layers(
+        feature_layer: PsqFeatureLayer,
+        mobility_layer: MobilityLayer,
+        pawn_layer: PawnLayer,
+        material_layer: MaterialLayer,
+    ) -> Self {
+        Self {
+            feature_layer,
+            mobility_layer,
+            pawn_layer,
+            material_layer,
+            weight_scale: 0.01,
+        }
+    }
+
+    /// Enables the cache of the pawn structure scores, which pays off in the search.
+    #[inline]
+    pub fn with_pawn_cache(mut self) -> Self {
+        self.pawn_layer = self.pawn_layer.with_cache();
+        self
+    }
+
+    #[inline]
+    pub fn feature_layer(&self) -> &PsqFeatureLayer {
+        &self.feature_layer
+    }
+
+    #[inline]
+    pub fn weight_scale(&self) -> f32 {
+        self.weight_scale
+    }
+
+    #[inline]
+    pub fn set_feature_layer(&mut self, feature_layer: PsqFeatureLayer, weight_scale: f32) {
+        self.feature_layer = feature_layer;
+        self.weight_scale = weight_scale;
+    }
+
+    pub fn load(data: &[u8]) -> Result<Self> {
+        decode(data, MODEL_MAGIC, MODEL_VERSION)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        encode(self, MODEL_MAGIC, MODEL_VERSION)
     }
 
     pub fn store(&self, path: &str) -> Result<()> {
-        let data = bincode::serialize(&self)?;
-        let mut file = File::create(path)?;
-        file.write_all(data.as_slice())?;
-        Ok(())
+        store(&self.to_bytes()?, path)
+    }
+
+    /// Builds the model which uses only the piece-square table, with the weights given by
+    /// `weight`.
+    #[cfg(test)]
+    pub(crate) fn psq_only(
+        weight: impl Fn(Cell, Sq) -> crate::eval::layers::feature::ScorePair,
+    ) -> Self {
+        Self::from_layers(
+            PsqFeatureLayer::from_fn(weight),
+            MobilityLayer::zero(),
+            PawnLayer::zero(),
+            MaterialLayer::zero(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let data = include_bytes!("../../data/model.paw");
+        let model = PsqModel::load(data).unwrap();
+        assert_eq!(&model.to_bytes().unwrap()[..], &data[..]);
+
+        let mut bad_magic = data.to_vec();
+        bad_magic[0] = b'X';
+        assert!(PsqModel::load(&bad_magic).is_err());
+
+        let mut bad_version = data.to_vec();
+        bad_version[4] = 42;
+        assert!(PsqModel::load(&bad_version).is_err());
+
+        assert!(PsqModel::load(&data[..6]).is_err());
+        assert!(PsqModel::load(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn test_symmetry() {
+        use crate::eval::layers::feature::{PsqFeatureLayer, ScorePair};
+        use pawnyowl_board::{Color, Piece};
+        use std::str::FromStr;
+
+        // Black weights mirror white ones, so the evaluation must flip its sign on mirroring.
+        let order = [
+            Piece::Pawn,
+            Piece::King,
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Rook,
+            Piece::Queen,
+        ];
+        let feature_layer = PsqFeatureLayer::from_fn(|cell, sq| {
+            let (sq, sign) = match cell.color().unwrap() {
+                Color::White => (sq, 1),
+                Color::Black => (sq.flipped_rank(), -1),
+            };
+            let i = order.iter().position(|&p| Some(p) == cell.piece()).unwrap();
+            let mg = (100 * i + 7 * sq.index()) as i16 % 300;
+            let eg = (50 * i + 13 * sq.index()) as i16 % 250;
+            ScorePair::new(Score::new(mg), Score::new(eg)) * sign
+        });
+        let model = PsqModel::from_layers(
+            feature_layer,
+            MobilityLayer::default(),
+            PawnLayer::default(),
+            MaterialLayer::default(),
+        );
+        let eval = |b: &Board| eval::evaluate(b, &model);
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1rq1r1k1/1p3ppp/pB3n2/3ppP2/Pbb1P3/1PN2B2/2P2QPP/R1R4K w - - 1 21",
+            "8/8/4k3/3r4/8/3RP3/4K3/8 w - - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            assert_ne!(eval(&b).value(), 0, "{}", fen);
+            assert_eq!(eval(&b).value(), -eval(&b.mirror()).value(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_make_unmake() {
+        use std::str::FromStr;
+
+        let model = PsqModel::new();
+        let layer = model.feature_layer();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let mut orig = layer.init_feature_slice();
+            for sq in b.all() {
+                layer.update_feature_slice(&mut orig, b.get(sq), sq, 1);
+            }
+
+            // Capturing a piece and putting it back restores the slice exactly.
+            for sq in b.all() {
+                let cell = b.get(sq);
+                let mut slice = orig;
+                layer.update_feature_slice(&mut slice, cell, sq, -1);
+                assert!(slice.stage <= orig.stage, "{} {}", fen, sq);
+                layer.update_feature_slice(&mut slice, cell, sq, 1);
+                assert_eq!(slice, orig, "{} {}", fen, sq);
+            }
+        }
+
+        // Adding a piece and removing it again also restores the slice.
+        let mut orig = layer.init_feature_slice();
+        layer.update_feature_slice(&mut orig, Cell::WhiteKing, Sq::from_str("e1").unwrap(), 1);
+        layer.update_feature_slice(&mut orig, Cell::BlackKing, Sq::from_str("e8").unwrap(), 1);
+        let sq = Sq::from_str("d4").unwrap();
+        for cell in Cell::iter().filter(|&c| c != Cell::None) {
+            let mut slice = orig;
+            layer.update_feature_slice(&mut slice, cell, sq, 1);
+            layer.update_feature_slice(&mut slice, cell, sq, -1);
+            assert_eq!(slice, orig, "{}", cell);
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        use std::str::FromStr;
+
+        let model = PsqModel::new();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "1rq1r1k1/1p3ppp/pB3n2/3ppP2/Pbb1P3/1PN2B2/2P2QPP/R1R4K w - - 1 21",
+            "4k3/p7/2b5/8/8/8/PPP5/2B1K3 w - - 0 1",
+        ] {
+            let b = Board::from_str(fen).unwrap();
+            let tag = Model::build_tag(&model, &b);
+            let items = Model::explain(&model, &tag, &b);
+            let names: Vec<_> = items.iter().map(|&(name, _)| name).collect();
+            assert_eq!(
+                names,
+                [
+                    "psq",
+                    "mobility",
+                    "king safety",
+                    "pawns",
+                    "material",
+                    "draw scale"
+                ]
+            );
+            let total = items.iter().fold(Score::new(0), |acc, &(_, s)| acc + s);
+            assert_eq!(total, Model::apply(&model, &tag, &b), "{}", fen);
+        }
     }
 }
@@ -0,0 +1,264 @@
+use crate::eval::{
+    model::{self, Model},
+    score::Score,
+};
+use anyhow::{Result, bail};
+use pawnyowl_board::{
+    Board, Cell, Move, Sq,
+    diff::{self, DiffListener},
+    moves::RawUndo,
+};
+use serde::{Deserialize, Serialize};
+
+pub const NNUE_MAGIC: &[u8; 4] = b"PWNN";
+const NNUE_VERSION: u32 = 1;
+
+/// One input per piece on each square.
+pub const INPUTS: usize = 64 * (Cell::COUNT - 1);
+pub const HIDDEN: usize = 32;
+
+/// Clipping bound of the hidden layer activations.
+const QA: i64 = 255;
+/// Quantization factor of the output weights.
+const QB: i64 = 64;
+/// Network output which is mapped to one pawn.
+const OUTPUT_SCALE: i64 = 400;
+/// Evaluations are kept far from the mate and tablebase scores.
+const EVAL_LIMIT: i64 = 20000;
+
+/// Hidden layer values, updated incrementally as the pieces move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accumulator([i32; HIDDEN]);
+
+/// Network with one hidden layer over the piece-square inputs.
+///
+/// The hidden layer is kept in the [`Accumulator`], so a move updates only the columns of the
+/// changed squares. The output is a clipped ReLU of the hidden layer followed by a single neuron.
+/// The score is from White's point of view, as in [`PsqModel`](super::model::PsqModel).
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NnueModel {
+    /// Hidden layer weights, `HIDDEN` consecutive values per input.
+    input_weights: Vec<i16>,
+    input_bias: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+struct NnueListener<'a> {
+    model: &'a NnueModel,
+    acc: &'a mut Accumulator,
+}
+
+impl DiffListener for NnueListener<'_> {
+    #[inline]
+    fn upd(&mut self, sq: Sq, old: Cell, new: Cell) {
+        self.model.update(self.acc, old, sq, -1);
+        self.model.update(self.acc, new, sq, 1);
+    }
+
+    #[inline]
+    fn add(&mut self, sq: Sq, new: Cell) {
+        self.model.update(self.acc, new, sq, 1);
+    }
+
+    #[inline]
+    fn del(&mut self, sq: Sq, old: Cell) {
+        self.model.update(self.acc, old, sq, -1);
+    }
+}
+
+impl NnueModel {
+    pub fn from_weights(
+        input_weights: Vec<i16>,
+        input_bias: Vec<i16>,
+        output_weights: Vec<i16>,
+        output_bias: i32,
+    ) -> Result<Self> {
+        let model = Self {
+            input_weights,
+            input_bias,
+            output_weights,
+            output_bias,
+        };
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// The network that scores nothing.
+    pub fn zero() -> Self {
+        Self {
+            input_weights: vec![0; INPUTS * HIDDEN],
+            input_bias: vec![0; HIDDEN],
+            output_weights: vec![0; HIDDEN],
+            output_bias: 0,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.input_weights.len() != INPUTS * HIDDEN
+            || self.input_bias.len() != HIDDEN
+            || self.output_weights.len() != HIDDEN
+        {
+            bail!("network does not have {} hidden neurons", HIDDEN);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn input_index(cell: Cell, sq: Sq) -> usize {
+        (cell.index() - 1) * 64 + sq.index()
+    }
+
+    #[inline]
+    fn update(&self, acc: &mut Accumulator, cell: Cell, sq: Sq, delta: i32) {
+        if cell == Cell::None {
+            return;
+        }
+        let start = Self::input_index(cell, sq) * HIDDEN;
+        for (v, &w) in acc
+            .0
+            .iter_mut()
+            .zip(&self.input_weights[start..start + HIDDEN])
+        {
+            *v += i32::from(w) * delta;
+        }
+    }
+
+    pub fn load(data: &[u8]) -> Result<Self> {
+        let model: Self = model::decode(data, NNUE_MAGIC, NNUE_VERSION)?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        model::encode(self, NNUE_MAGIC, NNUE_VERSION)
+    }
+
+    pub fn store(&self, path: &str) -> Result<()> {
+        model::store(&self.to_bytes()?, path)
+    }
+}
+
+impl Model for NnueModel {
+    type Tag = Accumulator;
+
+    /// There is no bundled network, so this one scores nothing until the weights are loaded.
+    #[inline]
+    fn new() -> Self {
+        Self::zero()
+    }
+
+    #[inline]
+    fn build_tag(&self, board: &Board) -> Accumulator {
+        let mut acc = Accumulator([0; HIDDEN]);
+        for (v, &b) in acc.0.iter_mut().zip(&self.input_bias) {
+            *v = b.into();
+        }
+        for (sq, cell) in board.iter_pieces() {
+            self.update(&mut acc, cell, sq, 1);
+        }
+        acc
+    }
+
+    #[inline]
+    unsafe fn after_move(&self, acc: &mut Accumulator, board: &Board, mv: Move, u: &RawUndo) {
+        unsafe { diff::after_move(board, mv, u, NnueListener { model: self, acc }) };
+    }
+
+    #[inline]
+    fn apply(&self, acc: &Accumulator, _board: &Board) -> Score {
+        let mut sum = i64::from(self.output_bias);
+        for (&v, &w) in acc.0.iter().zip(&self.output_weights) {
+            sum += i64::from(v).clamp(0, QA) * i64::from(w);
+        }
+        let score = sum * OUTPUT_SCALE / (QA * QB);
+        Score::new(score.clamp(-EVAL_LIMIT, EVAL_LIMIT) as i16)
+    }
+
+    fn explain(&self, acc: &Accumulator, board: &Board) -> Vec<(&'static str, Score)> {
+        vec![("network", self.apply(acc, board))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestRng;
+    use pawnyowl_board::{MoveGen, MoveList};
+    use std::str::FromStr;
+
+    fn test_model() -> NnueModel {
+        let mut rng = TestRng::new(42);
+        let mut next =
+            move |range: i32| (i32::from(rng.next_u16()) % (2 * range + 1) - range) as i16;
+        NnueModel::from_weights(
+            (0..INPUTS * HIDDEN).map(|_| next(60)).collect(),
+            (0..HIDDEN).map(|_| next(100)).collect(),
+            (0..HIDDEN).map(|_| next(64)).collect(),
+            1000,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_incremental() {
+        let model = test_model();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let mut b = Board::from_str(fen).unwrap();
+            let tag = model.build_tag(&b);
+            let mut moves = MoveList::new();
+            MoveGen::new(&b).gen_legal(&mut moves);
+            for mv in moves {
+                let mut next = tag;
+                let u = unsafe { b.make_move_unchecked(mv) };
+                unsafe { model.after_move(&mut next, &b, mv, &u) };
+                assert_eq!(next, model.build_tag(&b), "{} {}", fen, mv);
+                assert_eq!(
+                    model.apply(&next, &b),
+                    model.apply(&model.build_tag(&b), &b)
+                );
+                unsafe { b.unmake_move_unchecked(mv, u) };
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let b = Board::start();
+        let zero = NnueModel::zero();
+        assert_eq!(zero.apply(&zero.build_tag(&b), &b), Score::new(0));
+
+        // With no inputs active, the output is the bias of the output neuron.
+        let mut model = NnueModel::zero();
+        model.output_bias = (QA * QB) as i32;
+        assert_eq!(model.apply(&model.build_tag(&b), &b), Score::new(400));
+
+        // Negative hidden values are clipped to zero, and large ones to `QA`.
+        model.input_bias = vec![-1000; HIDDEN];
+        model.input_bias[0] = 1000;
+        model.output_weights[0] = QB as i16;
+        model.output_weights[1] = QB as i16;
+        assert_eq!(model.apply(&model.build_tag(&b), &b), Score::new(800));
+    }
+
+    #[test]
+    fn test_load() {
+        let model = test_model();
+        let data = model.to_bytes().unwrap();
+        let loaded = NnueModel::load(&data).unwrap();
+        assert_eq!(&loaded.to_bytes().unwrap()[..], &data[..]);
+
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        assert!(NnueModel::load(&bad_magic).is_err());
+
+        let mut small = NnueModel::zero();
+        small.output_weights.pop();
+        assert!(NnueModel::load(&small.to_bytes().unwrap()).is_err());
+    }
+}
@@ -1,8 +1,37 @@
 use derive_more::{Add, AddAssign, Sub, SubAssign};
-use std::ops::Mul;
+use std::ops::{Mul, Neg};
 
 pub type Stage = u8;
 
+/// Game outcome as reported by endgame tablebases.
+///
+/// Cursed wins and blessed losses are the positions which are won or lost with perfect play, but
+/// drawn under the fifty-move rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Neg for Wdl {
+    type Output = Self;
+
+    /// The outcome for the opponent.
+    #[inline]
+    fn neg(self) -> Self::Output {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Add, AddAssign, Sub, SubAssign)]
 pub struct Score(i16);
 
@@ -32,6 +61,31 @@ impl Score {
         Score(-25000)
     }
 
+    /// Score for a tablebase win found `ply` plies from the root. It is less than any mate score,
+    /// but greater than any evaluation.
+    #[inline]
+    pub fn tb_win(ply: usize) -> Self {
+        Score(-Self::mate_bound().0 - 1 - ply as i16)
+    }
+
+    /// Score for a cursed win. It is a draw, so the score is small, but still positive to make the
+    /// engine prefer such positions over the ordinary draws in hope the opponent errs.
+    #[inline]
+    pub fn cursed_win() -> Self {
+        Score(1)
+    }
+
+    #[inline]
+    pub fn from_wdl(wdl: Wdl, ply: usize) -> Self {
+        match wdl {
+            Wdl::Win => Self::tb_win(ply),
+            Wdl::CursedWin => Self::cursed_win(),
+            Wdl::Draw => Score(0),
+            Wdl::BlessedLoss => Score(-Self::cursed_win().0),
+            Wdl::Loss => Score(-Self::tb_win(ply).0),
+        }
+    }
+
     #[inline]
     pub fn value(self) -> i16 {
         self.0
@@ -47,6 +101,15 @@ impl Mul<i16> for Score {
     }
 }
 
+impl Neg for Score {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Score(-self.0)
+    }
+}
+
 impl From<Score> for i32 {
     #[inline]
     fn from(score: Score) -> i32 {
@@ -60,3 +123,24 @@ impl From<i32> for Score {
         Score::new(val as i16)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wdl() {
+        let cursed = Score::from_wdl(Wdl::CursedWin, 10);
+        assert!(cursed > Score::new(0));
+        assert!(cursed < Score::new(10));
+        let blessed = Score::from_wdl(Wdl::BlessedLoss, 10);
+        assert!(blessed < Score::new(0));
+        assert!(blessed > Score::new(-10));
+        assert_eq!(Score::from_wdl(Wdl::Draw, 10), Score::new(0));
+
+        let win = Score::from_wdl(Wdl::Win, 10);
+        assert!(win > Score::from_wdl(Wdl::Win, 11));
+        assert!(win.value() < -Score::mate_bound().value());
+        assert_eq!(Score::from_wdl(Wdl::Loss, 10).value(), -win.value());
+    }
+}
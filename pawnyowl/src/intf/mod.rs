@@ -3,6 +3,7 @@ pub mod score;
 
 pub use score::{BoundedScore, Score};
 
+use anyhow::Result;
 use opts::{Name, NameBuf, Opt, Val};
 use pawnyowl_board::{Board, Move};
 use std::{collections::HashMap, num::NonZeroU32, time::Duration};
@@ -36,16 +37,42 @@ pub struct TimeControl {
 pub enum SearchConstraint {
     Infinite,
     FixedDepth(usize),
+    FixedNodes(u64),
+    /// Search for a forced mate in the given number of moves.
+    Mate(u32),
     FixedTime(Duration),
     TimeControl(TimeControl),
 }
 
+#[derive(Clone, Debug)]
+pub struct SearchParams {
+    pub constraint: SearchConstraint,
+    /// Root moves to consider. If empty, all the legal moves are searched.
+    pub search_moves: Vec<Move>,
+    /// Search in pondering mode. The time limits only apply after the ponder hit, and the search
+    /// must not finish until either the ponder hit arrives or the search is stopped.
+    pub ponder: bool,
+}
+
+impl From<SearchConstraint> for SearchParams {
+    fn from(constraint: SearchConstraint) -> Self {
+        SearchParams {
+            constraint,
+            search_moves: Vec::new(),
+            ponder: false,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SearchInfo {
     pub depth: usize,
+    /// Maximum ply reached, including quiescence. Equals `depth` if the search doesn't track it.
+    pub seldepth: usize,
     pub pv: Vec<Move>,
     pub score: BoundedScore,
     pub nodes: Option<u64>,
+    pub hashfull: Option<u16>,
 }
 
 pub type StopCallback = Box<dyn FnOnce() + Send>;
@@ -58,15 +85,26 @@ pub trait Monitor: Sync {
     fn report_info(&self, i: &SearchInfo);
     fn report_nodes(&self, nodes: u64);
     fn report_cur_move(&self, m: Move, num: usize);
+    fn report_best(&self, _m: Move) {}
+
+    /// Returns `true` if the search is pondering, i.e. the ponder hit has not arrived yet.
+    fn is_pondering(&self) -> bool {
+        false
+    }
+
+    /// Blocks while the search is pondering, until the ponder hit or the stop arrives.
+    fn wait_ponder(&self) {}
 }
 
 pub trait Engine {
     fn meta(&self) -> EngineMeta;
     fn opts(&self) -> &HashMap<NameBuf, Opt>;
-    fn set_opt(&mut self, name: &Name, val: Val);
+    fn set_opt(&mut self, name: &Name, val: Val) -> Result<()>;
     fn set_debug(&mut self, value: bool);
     fn on_new_game(&mut self);
     fn set_position(&mut self, b: &Board, ms: &[Move]);
-    fn search(&mut self, c: SearchConstraint, mon: &dyn Monitor) -> SearchResult;
+    fn search(&mut self, p: &SearchParams, mon: &dyn Monitor) -> SearchResult;
     fn q_search(&mut self) -> Score;
+    /// Describes the static evaluation of the current position, one line per component.
+    fn eval(&mut self) -> Vec<String>;
 }
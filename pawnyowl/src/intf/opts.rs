@@ -8,6 +8,14 @@ use std::{
     str::FromStr,
 };
 
+/// Standard UCI option that switches castling to the Chess960 rules, with castling moves encoded
+/// as the king capturing its own rook.
+pub const OPT_CHESS960: &str = "UCI_Chess960";
+
+/// Prints the principal variation in SAN instead of the UCI coordinates. Off by default, as GUIs
+/// expect the coordinates.
+pub const OPT_SHOW_SAN_PV: &str = "ShowSanPv";
+
 #[derive(Debug)]
 #[repr(transparent)]
 pub struct Name(str);
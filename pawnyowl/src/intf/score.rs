@@ -36,6 +36,18 @@ impl Score {
         }
     }
 
+    #[inline]
+    pub fn sort_key(self) -> i64 {
+        // Centipawn scores fit into `i32`, and mate distances fit into `u32`, so mate scores never
+        // overlap with centipawn ones.
+        const MATE: i64 = 1 << 40;
+        match self {
+            Self::Cp(val) => val as i64,
+            Self::Mate { moves, win: true } => MATE - moves as i64,
+            Self::Mate { moves, win: false } => -MATE + moves as i64,
+        }
+    }
+
     fn as_cmp_tuple(&self) -> (i32, i64) {
         match *self {
             Self::Cp(val) => (0, val as i64),
@@ -131,7 +143,47 @@ mod tests {
                 win: true,
             },
         ];
+        let mut src2 = src;
         src.sort();
         assert_eq!(src, res);
+        src2.sort_by_key(|s| s.sort_key());
+        assert_eq!(src2, res);
+    }
+
+    #[test]
+    fn test_sort_key() {
+        let scores = [
+            Score::Cp(i32::MIN),
+            Score::Cp(-1),
+            Score::Cp(0),
+            Score::Cp(i32::MAX),
+            Score::Mate {
+                moves: u32::MAX,
+                win: true,
+            },
+            Score::Mate {
+                moves: 1,
+                win: true,
+            },
+            Score::Mate {
+                moves: u32::MAX,
+                win: false,
+            },
+            Score::Mate {
+                moves: 1,
+                win: false,
+            },
+        ];
+        for a in scores {
+            for b in scores {
+                assert_eq!(
+                    a.cmp(&b),
+                    a.sort_key().cmp(&b.sort_key()),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
     }
 }
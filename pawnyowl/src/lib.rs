@@ -3,4 +3,8 @@
 pub mod engine;
 pub mod eval;
 pub mod intf;
+pub mod pgn;
 pub mod uci;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,274 @@
+use anyhow::{Context, Result, bail};
+use pawnyowl_board::{Board, Move};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is unfinished or the result is not known (`*`).
+    Unknown,
+}
+
+impl FromStr for GameResult {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "1-0" => Ok(GameResult::WhiteWins),
+            "0-1" => Ok(GameResult::BlackWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unknown),
+            _ => bail!("bad result {:?}", s),
+        }
+    }
+}
+
+/// Game read from PGN.
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// Position from the `FEN` tag, or the standard start position.
+    pub start: Board,
+    pub moves: Vec<Move>,
+    /// Result from the `Result` tag, or from the game termination marker if there is no such tag.
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parses a tag pair like `[Event "Casual game"]` without the opening bracket. Returns the tag
+/// and the remaining text.
+fn parse_tag(s: &str) -> Result<((String, String), &str)> {
+    let s = s.trim_start();
+    let (name, s) = s.split_at(
+        s.find(|c: char| c.is_whitespace() || c == '"')
+            .unwrap_or(s.len()),
+    );
+    if name.is_empty() {
+        bail!("no tag name");
+    }
+    let mut chars = s
+        .trim_start()
+        .strip_prefix('"')
+        .with_context(|| format!("no value for tag {:?}", name))?
+        .chars();
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => value.push(
+                chars
+                    .next()
+                    .with_context(|| format!("unterminated value of tag {:?}", name))?,
+            ),
+            Some('"') => break,
+            Some(c) => value.push(c),
+            None => bail!("unterminated value of tag {:?}", name),
+        }
+    }
+    let rest = chars
+        .as_str()
+        .trim_start()
+        .strip_prefix(']')
+        .with_context(|| format!("unterminated tag {:?}", name))?;
+    Ok(((name.to_string(), value), rest))
+}
+
+/// Strips the move number from tokens like `12.`, `12...Nf6` or `...`.
+fn strip_move_number(token: &str) -> &str {
+    let digits = token
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(token.len());
+    if digits == token.len() {
+        // A number with the dots separated by a space.
+        return "";
+    }
+    if token[digits..].starts_with('.') {
+        return token[digits..].trim_start_matches('.');
+    }
+    // Castling with zeros, like `0-0`, starts with a digit too.
+    token
+}
+
+/// Splits the text of a PGN file into games. Each game but the first starts with the first tag
+/// after the movetext of the previous one.
+pub fn split_games(s: &str) -> Vec<&str> {
+    let mut games = Vec::new();
+    let (mut start, mut pos) = (0, 0);
+    let mut in_movetext = false;
+    for line in s.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            if in_movetext {
+                games.push(&s[start..pos]);
+                start = pos;
+                in_movetext = false;
+            }
+        } else if !trimmed.is_empty() {
+            in_movetext = true;
+        }
+        pos += line.len();
+    }
+    games.push(&s[start..]);
+    games.retain(|game| !game.trim().is_empty());
+    games
+}
+
+/// Reads a single game. Comments, NAGs and variations are skipped, and the reading stops at the
+/// game termination marker.
+pub fn parse_game(s: &str) -> Result<PgnGame> {
+    let mut tags = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(tail) = rest.strip_prefix('[') {
+        let (tag, tail) = parse_tag(tail)?;
+        tags.push(tag);
+        rest = tail.trim_start();
+    }
+    let start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => Board::from_str(fen).context("bad FEN tag")?,
+        None => Board::start(),
+    };
+    let mut result = match tags.iter().find(|(name, _)| name == "Result") {
+        Some((_, res)) => Some(GameResult::from_str(res)?),
+        None => None,
+    };
+
+    let mut board = start.clone();
+    let mut moves = Vec::new();
+    let mut depth = 0_usize;
+    let mut chars = rest.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' => {
+                if !chars.any(|(_, c)| c == '}') {
+                    bail!("unterminated comment");
+                }
+            }
+            ';' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).context("unbalanced ')'")?,
+            '$' => while chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {},
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) =
+                    chars.next_if(|&(_, c)| !c.is_whitespace() && !"{}();$".contains(c))
+                {
+                    end = j + c.len_utf8();
+                }
+                let token = &rest[i..end];
+                if depth > 0 {
+                    continue;
+                }
+                if let Ok(res) = GameResult::from_str(token) {
+                    result.get_or_insert(res);
+                    break;
+                }
+                let san = strip_move_number(token);
+                if san.chars().all(|c| c == '!' || c == '?') {
+                    continue;
+                }
+                let mv = Move::from_san(san, &board).with_context(|| {
+                    format!("bad move {:?} at move {}", san, board.raw().move_number)
+                })?;
+                board.make_move(mv)?;
+                moves.push(mv);
+            }
+        }
+    }
+    if depth != 0 {
+        bail!("unterminated variation");
+    }
+
+    Ok(PgnGame {
+        tags,
+        start,
+        moves,
+        result: result.unwrap_or(GameResult::Unknown),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game() {
+        let game = parse_game(
+            r#"[Event "Casual \"blitz\" game"]
+[Site "?"]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 (2. f4 exf4 (2... d5 {the (Falkbeer) countergambit}) 3. Nf3) 2... Nc6 $1
+3. Bb5 {The Spanish} 3...a6 4. Ba4!? Nf6 ; a comment till the end of line 5. d4
+5. O-O {castling} 5... Be7 1/2-1/2
+"#,
+        )
+        .unwrap();
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\" game"));
+        assert_eq!(game.tag("Site"), Some("?"));
+        assert_eq!(game.tag("White"), None);
+        assert_eq!(game.result, GameResult::Draw);
+        assert_eq!(game.start, Board::start());
+        let moves: Vec<_> = game.moves.iter().map(|mv| mv.to_string()).collect();
+        assert_eq!(
+            moves,
+            [
+                "e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4", "g8f6", "e1g1", "f8e7"
+            ]
+        );
+
+        // The result is taken from the marker if there is no tag.
+        let game = parse_game(
+            "[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n\n1. O-O-O Kf7 2. Kb1 0-1\n3. Ka1",
+        )
+        .unwrap();
+        assert_eq!(game.start.to_string(), "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(game.result, GameResult::BlackWins);
+
+        let game = parse_game("1. e4 1... e5 2.Nf3").unwrap();
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(game.result, GameResult::Unknown);
+    }
+
+    #[test]
+    fn test_split_games() {
+        let text = "[Event \"a\"]\n[Result \"1-0\"]\n\n1. e4 e5\n2. Qh5 1-0\n\n\
+                    [Event \"b\"]\n\n1. d4 *\n\n[Event \"c\"]\n[FEN \"4k3/8/8/8/8/8/8/4K3 w - - 0 1\"]\n\n*\n\n";
+        let games = split_games(text);
+        assert_eq!(games.len(), 3);
+        let games: Vec<_> = games.into_iter().map(|g| parse_game(g).unwrap()).collect();
+        assert_eq!(games[0].tag("Event"), Some("a"));
+        assert_eq!(games[0].moves.len(), 3);
+        assert_eq!(games[1].moves.len(), 1);
+        assert_eq!(games[2].tags.len(), 2);
+        assert!(games[2].moves.is_empty());
+        assert!(split_games("\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for pgn in [
+            "1. e4 e5 2. Ke3",
+            "1. e4 {unterminated",
+            "1. e4 (1. d4 d5",
+            "1. e4 ) e5",
+            "[Event \"x]\n1. e4",
+            "[FEN \"bad\"]\n1. e4",
+            "[Result \"2-0\"]\n1. e4",
+        ] {
+            assert!(parse_game(pgn).is_err(), "{}", pgn);
+        }
+        let err = parse_game("1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. Bxf7").unwrap_err();
+        assert_eq!(err.to_string(), "bad move \"Bxf7\" at move 4");
+    }
+}
@@ -0,0 +1,13 @@
+/// Deterministic pseudo-random generator for the test data.
+pub struct TestRng(u32);
+
+impl TestRng {
+    pub fn new(seed: u32) -> Self {
+        TestRng(seed)
+    }
+
+    pub fn next_u16(&mut self) -> u16 {
+        self.0 = self.0.wrapping_mul(1103515245).wrapping_add(12345);
+        (self.0 >> 16) as u16
+    }
+}
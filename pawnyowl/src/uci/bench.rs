@@ -0,0 +1,85 @@
+use crate::intf::{Engine, Monitor, SearchConstraint, SearchInfo, StopCallback};
+use pawnyowl_board::{Board, Move};
+use std::{
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_DEPTH: usize = 6;
+
+const FENS: [&str; 20] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq - 0 11",
+    "1rq1r1k1/1p3ppp/pB3n2/3ppP2/Pbb1P3/1PN2B2/2P2QPP/R1R4K w - - 1 21",
+    "4r1k1/3R1ppp/8/5P2/p7/6PP/4pK2/1rN1B3 w - - 4 43",
+    "r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1",
+    "r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1",
+    "r3k2r/8/5Q2/8/8/3q4/8/R3K2R w KQkq - 0 1",
+    "6K1/8/8/1k3q2/3Q4/8/8/8 w - - 0 1",
+    "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1",
+    "4k3/8/8/pppppppp/PPPPPPPP/8/8/4K3 w - - 0 1",
+    "8/PPPPPPPP/8/2k1K3/8/8/pppppppp/8 w - - 0 1",
+    "5K2/1N1N1N2/8/1N1N1N2/1n1n1n2/8/1n1n1n2/5k2 w - - 0 1",
+    "8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1",
+    "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+    "2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1",
+    "8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1",
+    "5K2/8/1Q6/2N5/8/1p2k3/8/8 w - - 0 1",
+    "8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1",
+    "8/5k2/8/5N2/5Q2/2K5/8/8 w - - 0 1",
+];
+
+#[derive(Default)]
+struct BenchMonitor {
+    callbacks: Mutex<Vec<StopCallback>>,
+    nodes: Mutex<u64>,
+}
+
+impl BenchMonitor {
+    fn update_nodes(&self, nodes: u64) {
+        let mut cur = self.nodes.lock().unwrap();
+        *cur = (*cur).max(nodes);
+    }
+}
+
+impl Monitor for BenchMonitor {
+    fn is_stopped(&self) -> bool {
+        false
+    }
+
+    fn register_on_stop(&self, callback: StopCallback) {
+        // The search is never stopped, but the callbacks must stay alive until it finishes.
+        self.callbacks.lock().unwrap().push(callback);
+    }
+
+    fn report_str(&self, _s: &str) {}
+
+    fn report_info(&self, i: &SearchInfo) {
+        if let Some(nodes) = i.nodes {
+            self.update_nodes(nodes);
+        }
+    }
+
+    fn report_nodes(&self, nodes: u64) {
+        self.update_nodes(nodes);
+    }
+
+    fn report_cur_move(&self, _m: Move, _num: usize) {}
+}
+
+/// Searches all the benchmark positions to the given depth. Returns the total number of nodes
+/// and the time spent.
+pub fn run(engine: &mut (impl Engine + ?Sized), depth: usize) -> (u64, Duration) {
+    let start = Instant::now();
+    let mut nodes = 0;
+    for fen in FENS {
+        engine.on_new_game();
+        engine.set_position(&Board::from_str(fen).unwrap(), &[]);
+        let monitor = BenchMonitor::default();
+        engine.search(&SearchConstraint::FixedDepth(depth).into(), &monitor);
+        nodes += monitor.nodes.into_inner().unwrap();
+    }
+    (nodes, start.elapsed())
+}
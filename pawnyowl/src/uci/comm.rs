@@ -1,13 +1,17 @@
-use crate::intf::{Engine, Monitor, SearchConstraint, SearchInfo, StopCallback, opts::Val};
+use crate::intf::{
+    Engine, Monitor, SearchInfo, SearchParams, SearchResult, StopCallback,
+    opts::{Name, NameBuf, OPT_CHESS960, OPT_SHOW_SAN_PV, Opt, Val},
+};
 use crate::uci::{
-    Warn,
-    io::{self, Command, Info, Message},
+    UciError, Warn, bench,
+    io::{self, Command, Info, Message, Position},
     sanitize,
     util::{DelayedState, StopState},
 };
-use anyhow::{Context, Result};
-use pawnyowl_board::Move;
+use anyhow::Result;
+use pawnyowl_board::{Board, Move, perft};
 use std::{
+    collections::HashMap,
     io::{BufRead, Write},
     sync::{
         Arc, Mutex, Weak,
@@ -22,23 +26,39 @@ struct SearchMonitor<'a, 'b, 'c> {
     start: Instant,
     output: &'a Mutex<&'b mut (dyn Write + Send + Sync)>,
     stop_state: &'c StopState,
+    san_board: Option<&'c Board>,
+    last_info: Mutex<Option<SearchInfo>>,
+    nodes: Mutex<Option<u64>>,
 }
 
 impl<'a, 'b, 'c> SearchMonitor<'a, 'b, 'c> {
     fn new(
         output: &'a Mutex<&'b mut (dyn Write + Send + Sync)>,
         stop_state: &'c StopState,
+        san_board: Option<&'c Board>,
     ) -> Self {
         Self {
             start: Instant::now(),
             output,
             stop_state,
+            san_board,
+            last_info: Mutex::new(None),
+            nodes: Mutex::new(None),
         }
     }
 
     fn time_passed(&self) -> Duration {
         Instant::now().duration_since(self.start)
     }
+
+    fn final_info(&self, best: Move) -> Option<SearchInfo> {
+        let mut info = self.last_info.lock().unwrap().take()?;
+        if best != Move::NULL && info.pv.first() != Some(&best) {
+            info.pv = vec![best];
+        }
+        info.nodes = info.nodes.max(*self.nodes.lock().unwrap());
+        Some(info)
+    }
 }
 
 impl Monitor for SearchMonitor<'_, '_, '_> {
@@ -50,23 +70,34 @@ impl Monitor for SearchMonitor<'_, '_, '_> {
         self.stop_state.register_on_stop(callback);
     }
 
+    fn is_pondering(&self) -> bool {
+        self.stop_state.is_pondering()
+    }
+
+    fn wait_ponder(&self) {
+        self.stop_state.wait_ponder();
+    }
+
     fn report_str(&self, s: &str) {
         let mut output = self.output.lock().unwrap();
         let _ = io::write_msg(&Message::Info(Info::String(s)), *output);
     }
 
     fn report_info(&self, info: &SearchInfo) {
+        *self.last_info.lock().unwrap() = Some(info.clone());
         let mut output = self.output.lock().unwrap();
         let _ = io::write_msg(
             &Message::Info(Info::Info {
                 time: self.time_passed(),
                 info,
+                san_board: self.san_board,
             }),
             *output,
         );
     }
 
     fn report_nodes(&self, nodes: u64) {
+        *self.nodes.lock().unwrap() = Some(nodes);
         let mut output = self.output.lock().unwrap();
         let _ = io::write_msg(
             &Message::Info(Info::Nodes {
@@ -83,26 +114,95 @@ impl Monitor for SearchMonitor<'_, '_, '_> {
     }
 }
 
+struct InfoWarn<'a, 'b>(&'a Mutex<&'b mut (dyn Write + Send + Sync)>);
+
+impl Warn for InfoWarn<'_, '_> {
+    fn warn(&mut self, msg: &str) {
+        let mut output = self.0.lock().unwrap();
+        let msg = format!("warning: {}", msg);
+        let _ = io::write_msg(&Message::Info(Info::String(&msg)), *output);
+    }
+}
+
+/// Work sent to the search thread.
+enum Job {
+    Search {
+        params: SearchParams,
+        /// Root position, if the PV must be printed in SAN.
+        san_board: Option<Board>,
+    },
+    Perft(Board, usize),
+}
+
+/// Runs perft on the search thread, reporting each root move as it is counted, so `stop` can abort
+/// it between root moves.
+fn run_perft(
+    mut board: Board,
+    depth: usize,
+    stop_state: &StopState,
+    output: &Mutex<&mut (dyn Write + Send + Sync)>,
+) -> Result<(), UciError> {
+    let mut total = 0;
+    let mut res = Ok(());
+    perft::perft_divide_with(&mut board, depth, |mv, count| {
+        total += count;
+        let mut output = output.lock().unwrap();
+        let msg = format!("{}: {}", mv, count);
+        res = io::write_msg(&Message::Info(Info::String(&msg)), *output);
+        res.is_ok() && !stop_state.is_stopped()
+    });
+    res?;
+    let mut output = output.lock().unwrap();
+    let msg = format!("Nodes searched: {}", total);
+    io::write_msg(&Message::Info(Info::String(&msg)), *output)?;
+    io::write_msg(
+        &Message::BestMove(SearchResult {
+            best: Move::NULL,
+            ponder: Move::NULL,
+        }),
+        *output,
+    )?;
+    Ok(())
+}
+
+fn is_enabled(opts: &HashMap<NameBuf, Opt>, name: &str) -> bool {
+    matches!(opts.get(<&Name>::from(name)), Some(Opt::Bool { val: true }))
+}
+
+fn write_feedback(output: &Mutex<&mut (dyn Write + Send + Sync)>, feedback: Vec<String>) {
+    if feedback.is_empty() {
+        return;
+    }
+    let mut output = output.lock().unwrap();
+    for msg in feedback {
+        let _ = io::write_msg(&Message::Info(Info::String(&msg)), *output);
+    }
+}
+
 pub fn comm(
     input: &mut dyn BufRead,
     output: &mut (dyn Write + Send + Sync),
     warn: &mut dyn Warn,
     engine: &mut (dyn Engine + Send + Sync),
-) -> Result<()> {
+) -> Result<(), UciError> {
     let meta = engine.meta();
     let mut opts = engine.opts().clone();
-    sanitize::opts(&opts)?;
+    sanitize::opts(&opts).map_err(|e| UciError::BadOption(format!("{:#}", e)))?;
 
     let output = Mutex::new(output);
     let engine = Mutex::new(engine);
     let delayed_state = Mutex::new(DelayedState::new());
     let searching = AtomicBool::new(false);
-    let (go_chan, go_chan_recv) = mpsc::sync_channel::<SearchConstraint>(0);
+    let mut debug = false;
+    let mut has_position = false;
+    let mut board = Board::start();
+    let (go_chan, go_chan_recv) = mpsc::sync_channel::<Job>(0);
     let (ack_chan_send, ack_chan) = mpsc::sync_channel::<Weak<StopState>>(0);
 
-    let try_apply_delayed_state = |delayed_state: &mut DelayedState| {
+    let try_apply_delayed_state = |delayed_state: &mut DelayedState, warn: &mut dyn Warn| {
         if !searching.load(Ordering::SeqCst) {
-            delayed_state.apply(*engine.try_lock().unwrap());
+            let feedback = delayed_state.apply(*engine.try_lock().unwrap(), warn);
+            write_feedback(&output, feedback);
         }
     };
 
@@ -124,25 +224,43 @@ pub fn comm(
             },
         );
 
-        let thread = scope.spawn(|| -> Result<()> {
+        let thread = scope.spawn(|| -> Result<(), UciError> {
             let go_chan = go_chan_recv;
             let ack_chan = ack_chan_send;
-            while let Ok(constr) = go_chan.recv() {
+            while let Ok(job) = go_chan.recv() {
                 searching.store(true, Ordering::SeqCst);
                 let mut engine = engine.lock().unwrap();
 
-                let stop_state = Arc::new(StopState::new());
+                let ponder = matches!(&job, Job::Search { params, .. } if params.ponder);
+                let stop_state = Arc::new(StopState::new(ponder));
                 ack_chan.send(Arc::downgrade(&stop_state)).unwrap();
-                let res = engine.search(constr, &SearchMonitor::new(&output, &stop_state));
-                drop(stop_state);
+                match job {
+                    Job::Search { params, san_board } => {
+                        let monitor = SearchMonitor::new(&output, &stop_state, san_board.as_ref());
+                        let res = engine.search(&params, &monitor);
+                        let (final_info, time) =
+                            (monitor.final_info(res.best), monitor.time_passed());
+                        drop(monitor);
+                        drop(stop_state);
 
-                {
-                    let mut output = output.lock().unwrap();
-                    io::write_msg(&Message::BestMove(res), *output)?;
+                        let mut output = output.lock().unwrap();
+                        if let Some(info) = &final_info {
+                            let san_board = san_board.as_ref();
+                            let info = Info::Info {
+                                time,
+                                info,
+                                san_board,
+                            };
+                            io::write_msg(&Message::Info(info), *output)?;
+                        }
+                        io::write_msg(&Message::BestMove(res), *output)?;
+                    }
+                    Job::Perft(board, depth) => run_perft(board, depth, &stop_state, &output)?,
                 }
 
                 let mut st = delayed_state.lock().unwrap();
-                st.apply(*engine);
+                let feedback = st.apply(*engine, &mut InfoWarn(&output));
+                write_feedback(&output, feedback);
                 // The order of drops is very important here!
                 drop(engine);
                 searching.store(false, Ordering::SeqCst);
@@ -151,13 +269,21 @@ pub fn comm(
             Ok(())
         });
 
-        let handle_thread_death = |thread: ScopedJoinHandle<'_, Result<()>>| -> Result<()> {
-            Err(thread.join().unwrap().unwrap_err()).context("running search thread")
-        };
+        let join_thread =
+            |thread: ScopedJoinHandle<'_, Result<(), UciError>>| -> Result<(), UciError> {
+                thread.join().unwrap_or_else(|payload| {
+                    let msg = payload
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown panic".into());
+                    Err(UciError::EnginePanic(msg))
+                })
+            };
 
-        while let Some(cmd) = io::read_cmd(input, warn).context("reading command")? {
+        while let Some(cmd) = io::read_cmd(input, is_enabled(&opts, OPT_CHESS960), warn)? {
             if thread.is_finished() {
-                return handle_thread_death(thread);
+                return join_thread(thread);
             }
             match cmd {
                 Command::Uci => {
@@ -175,9 +301,10 @@ pub fn comm(
                     io::write_msg(&Message::UciOk, *output)?;
                 }
                 Command::Debug(val) => {
+                    debug = val;
                     let mut st = delayed_state.lock().unwrap();
                     st.set_debug(val);
-                    try_apply_delayed_state(&mut st);
+                    try_apply_delayed_state(&mut st, warn);
                 }
                 Command::IsReady => {
                     let mut output = output.lock().unwrap();
@@ -192,7 +319,7 @@ pub fn comm(
                         Ok(val) => {
                             let mut st = delayed_state.lock().unwrap();
                             st.set_opt(name.as_name(), val);
-                            try_apply_delayed_state(&mut st);
+                            try_apply_delayed_state(&mut st, warn);
                         }
                         Err(err) => warn.warn(&format!(
                             "bad value \"{}\" for option \"{}\": {}",
@@ -206,38 +333,634 @@ pub fn comm(
                 Command::NewGame => {
                     let mut st = delayed_state.lock().unwrap();
                     st.set_new_game();
-                    try_apply_delayed_state(&mut st);
+                    try_apply_delayed_state(&mut st, warn);
                 }
                 Command::Position(pos) => {
+                    has_position = true;
+                    board = pos.board.clone();
+                    for mv in &pos.moves {
+                        unsafe { board.make_move_unchecked(*mv) };
+                    }
+                    if debug {
+                        let mut output = output.lock().unwrap();
+                        io::write_msg(
+                            &Message::Info(Info::String(&format!("fen {}", board))),
+                            *output,
+                        )?;
+                    }
                     let mut st = delayed_state.lock().unwrap();
                     st.set_position(pos);
-                    try_apply_delayed_state(&mut st);
+                    try_apply_delayed_state(&mut st, warn);
                 }
-                Command::Go(constr) => {
+                Command::Go {
+                    constraint,
+                    search_moves,
+                    ponder,
+                } => {
                     if searching.load(Ordering::SeqCst) {
                         warn.warn("search is already running");
-                    } else if let Ok(()) = go_chan.send(constr) {
+                        continue;
+                    }
+                    if !has_position {
+                        warn.warn("no position set, assuming the start position");
+                        has_position = true;
+                        let mut st = delayed_state.lock().unwrap();
+                        st.set_position(Box::new(Position {
+                            board: Board::start(),
+                            moves: Vec::new(),
+                        }));
+                        try_apply_delayed_state(&mut st, warn);
+                    }
+                    let mut params = SearchParams::from(constraint);
+                    params.ponder = ponder;
+                    if let Some(search_moves) = search_moves {
+                        for mv in &search_moves {
+                            match Move::from_uci_legal(mv, &board) {
+                                Ok(mv) => params.search_moves.push(mv),
+                                Err(e) => warn
+                                    .warn(&format!("bad move {:?} in \"searchmoves\": {}", mv, e)),
+                            }
+                        }
+                        if params.search_moves.is_empty() {
+                            warn.warn("no legal moves in \"searchmoves\", searching all moves");
+                        }
+                    }
+                    if let Ok(()) = go_chan.send(Job::Search {
+                        params,
+                        san_board: is_enabled(&opts, OPT_SHOW_SAN_PV).then(|| board.clone()),
+                    }) {
                         let stop = ack_chan.recv().unwrap();
                         guard.stop = stop;
                     } else {
                         // Could not send a command to the search thread. It means that the thread
                         // has terminated with either a panic or an error.
-                        return handle_thread_death(thread);
+                        return join_thread(thread);
                     }
                 }
-                Command::Stop => {
+                Command::Perft(depth) => {
                     if searching.load(Ordering::SeqCst) {
-                        if let Some(stop) = guard.stop.upgrade() {
-                            stop.stop();
-                        }
+                        warn.warn("search is already running");
+                        continue;
+                    }
+                    if depth == 0 {
+                        warn.warn("perft depth must be positive");
+                        continue;
+                    }
+                    if let Ok(()) = go_chan.send(Job::Perft(board.clone(), depth)) {
+                        let stop = ack_chan.recv().unwrap();
+                        guard.stop = stop;
+                    } else {
+                        return join_thread(thread);
+                    }
+                }
+                Command::Bench(depth) => {
+                    if searching.load(Ordering::SeqCst) {
+                        warn.warn("search is already running");
+                        continue;
+                    }
+                    let (nodes, time) = bench::run(*engine.lock().unwrap(), depth);
+                    // The benchmark has replaced the position in the engine.
+                    has_position = false;
+                    board = Board::start();
+                    let mut output = output.lock().unwrap();
+                    io::write_msg(&Message::Bench { nodes, time }, *output)?;
+                }
+                Command::Eval => {
+                    if searching.load(Ordering::SeqCst) {
+                        warn.warn("search is already running");
+                        continue;
+                    }
+                    if !has_position {
+                        warn.warn("no position set");
+                        continue;
+                    }
+                    let lines = engine.lock().unwrap().eval();
+                    let mut output = output.lock().unwrap();
+                    for ln in lines {
+                        let msg = format!("eval {}", ln);
+                        io::write_msg(&Message::Info(Info::String(&msg)), *output)?;
+                    }
+                }
+                Command::PonderHit => {
+                    if searching.load(Ordering::SeqCst)
+                        && let Some(stop) = guard.stop.upgrade()
+                    {
+                        stop.ponder_hit();
+                    }
+                }
+                Command::Stop => {
+                    if searching.load(Ordering::SeqCst)
+                        && let Some(stop) = guard.stop.upgrade()
+                    {
+                        stop.stop();
                     }
                 }
                 Command::Quit => break,
             }
         }
-        if thread.is_finished() {
-            return handle_thread_death(thread);
-        }
-        Ok(())
+        // Stop the search, if any, and wait for the search thread to finish.
+        drop(guard);
+        drop(go_chan);
+        join_thread(thread)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intf::{
+        BoundedScore, EngineMeta, Score, SearchResult,
+        opts::{Name, NameBuf, Opt},
+    };
+    use std::{collections::HashMap, str::FromStr};
+
+    #[derive(Default)]
+    struct TestWarn(Vec<String>);
+
+    impl Warn for TestWarn {
+        fn warn(&mut self, msg: &str) {
+            self.0.push(msg.to_string());
+        }
+    }
+
+    struct TestEngine {
+        opts: HashMap<NameBuf, Opt>,
+        reported: Move,
+        best: Move,
+        panics: bool,
+    }
+
+    impl Engine for TestEngine {
+        fn meta(&self) -> EngineMeta {
+            EngineMeta {
+                name: "test".into(),
+                author: "test".into(),
+            }
+        }
+
+        fn opts(&self) -> &HashMap<NameBuf, Opt> {
+            &self.opts
+        }
+
+        fn set_opt(&mut self, _name: &Name, _val: Val) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn set_debug(&mut self, _value: bool) {}
+        fn on_new_game(&mut self) {}
+        fn set_position(&mut self, _b: &Board, _ms: &[Move]) {}
+
+        fn search(&mut self, _p: &SearchParams, mon: &dyn Monitor) -> SearchResult {
+            assert!(!self.panics, "search failed");
+            mon.report_info(&SearchInfo {
+                depth: 3,
+                seldepth: 3,
+                pv: vec![self.reported],
+                score: Default::default(),
+                nodes: None,
+                hashfull: None,
+            });
+            mon.report_nodes(1234);
+            SearchResult {
+                best: self.best,
+                ponder: Move::NULL,
+            }
+        }
+
+        fn q_search(&mut self) -> Score {
+            Score::Cp(0)
+        }
+
+        fn eval(&mut self) -> Vec<String> {
+            vec!["total 0".into()]
+        }
+    }
+
+    fn run_engine(input: &str, engine: &mut (dyn Engine + Send + Sync)) -> (String, Vec<String>) {
+        let mut output = Vec::new();
+        let mut warn = TestWarn::default();
+        comm(&mut input.as_bytes(), &mut output, &mut warn, engine).unwrap();
+        (String::from_utf8(output).unwrap(), warn.0)
+    }
+
+    fn run_with_warnings(input: &str) -> (String, Vec<String>) {
+        run_engine(input, &mut crate::engine::Engine::new())
+    }
+
+    fn run(input: &str) -> String {
+        let (output, warnings) = run_with_warnings(input);
+        assert_eq!(warnings, Vec::<String>::new());
+        output
+    }
+
+    fn best_move(output: &str) -> &str {
+        let line = output
+            .lines()
+            .find(|ln| ln.starts_with("bestmove "))
+            .unwrap();
+        line.split_ascii_whitespace().nth(1).unwrap()
+    }
+
+    #[test]
+    fn test_debug_position() {
+        assert_eq!(run("position startpos moves e2e4\nquit\n"), "");
+        assert_eq!(
+            run("debug on\nposition startpos moves e2e4\nquit\n"),
+            "info string fen rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1\n"
+        );
+        assert_eq!(
+            run("debug on\ndebug off\nposition startpos moves e2e4\nquit\n"),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_go_without_position() {
+        let (output, warnings) = run_with_warnings("go depth 1\n");
+        assert_eq!(
+            warnings,
+            vec!["no position set, assuming the start position"]
+        );
+        assert!(Move::from_uci_legal(best_move(&output), &Board::start()).is_ok());
+
+        let output = run("position fen 8/8/8/8/8/8/8/K6k w - - 0 1 moves\ngo depth 1\n");
+        let b = Board::from_str("8/8/8/8/8/8/8/K6k w - - 0 1").unwrap();
+        assert!(Move::from_uci_legal(best_move(&output), &b).is_ok());
+    }
+
+    #[test]
+    fn test_search_moves() {
+        let output = run("position startpos moves e2e4\ngo depth 2 searchmoves a7a6 h7h5\n");
+        assert!(["a7a6", "h7h5"].contains(&best_move(&output)), "{}", output);
+
+        let (output, warnings) =
+            run_with_warnings("position startpos moves\ngo searchmoves e2e5 e7e5 depth 1\n");
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings[0].starts_with("bad move \"e2e5\""));
+        assert!(warnings[1].starts_with("bad move \"e7e5\""));
+        assert_eq!(
+            warnings[2],
+            "no legal moves in \"searchmoves\", searching all moves"
+        );
+        assert!(Move::from_uci_legal(best_move(&output), &Board::start()).is_ok());
+
+        let (output, warnings) =
+            run_with_warnings("position startpos moves\ngo searchmoves depth 1\n");
+        assert_eq!(warnings, vec!["empty \"searchmoves\", searching all moves"]);
+        assert!(Move::from_uci_legal(best_move(&output), &Board::start()).is_ok());
+    }
+
+    #[test]
+    fn test_go_nodes() {
+        let output = run("position startpos moves\ngo nodes 3000\n");
+        let nodes_ln = output.lines().rev().find(|ln| ln.starts_with("info time "));
+        assert!(nodes_ln.is_some(), "{}", output);
+        assert!(Move::from_uci_legal(best_move(&output), &Board::start()).is_ok());
+
+        let (_, warnings) = run_with_warnings("go depth 1 nodes 3000\n");
+        assert!(
+            warnings.contains(&"\"nodes\" ignored".to_string()),
+            "{:?}",
+            warnings
+        );
+    }
+
+    #[test]
+    fn test_ponder() {
+        let output = run("position startpos moves\ngo ponder movetime 100\nponderhit\n");
+        let b = Board::start();
+        let best = Move::from_uci_legal(best_move(&output), &b).unwrap();
+        assert_eq!(output.matches("bestmove ").count(), 1);
+        let mut b2 = b.clone();
+        b2.make_move(best).unwrap();
+        let ponder = output
+            .lines()
+            .find(|ln| ln.starts_with("bestmove "))
+            .unwrap();
+        let ponder = ponder.split_once(" ponder ").unwrap().1;
+        assert!(Move::from_uci_legal(ponder, &b2).is_ok(), "{}", output);
+    }
+
+    #[test]
+    fn test_go_mate() {
+        let output = run("position fen k7/8/2K5/8/8/8/8/7R w - - 0 1 moves\ngo mate 2\n");
+        let info = output.lines().rev().find(|ln| ln.starts_with("info depth"));
+        assert!(info.unwrap().contains(" score mate 2"), "{}", output);
+        let b = Board::from_str("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        assert!(Move::from_uci_legal(best_move(&output), &b).is_ok());
+
+        let (_, warnings) = run_with_warnings("go mate 0 depth 1\n");
+        assert_eq!(
+            warnings,
+            vec![
+                "bad \"mate\": must be positive",
+                "no position set, assuming the start position"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chess960() {
+        let moves = "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6";
+        let (_, warnings) = run_with_warnings(&format!("position startpos moves {} e1h1\n", moves));
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("bad move #7 \"e1h1\""),
+            "{:?}",
+            warnings
+        );
+
+        let output = run(&format!(
+            "setoption name UCI_Chess960 value true\n\
+             debug on\n\
+             position startpos moves {} e1h1\n\
+             go depth 1\n",
+            moves
+        ));
+        assert!(
+            output.starts_with(
+                "info string fen r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b ha - 5 4\n"
+            ),
+            "{}",
+            output
+        );
+        let b =
+            Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b ha - 5 4")
+                .unwrap();
+        assert!(Move::from_uci_legal(best_move(&output), &b).is_ok());
+
+        // Without the option, the rooks off the corners cannot castle, even if the FEN gives
+        // their files.
+        let fen = "1r2k1rr/8/8/8/8/8/8/1R2KR1R w HBhb - 0 1";
+        let output = run(&format!(
+            "debug on\nposition fen {} moves\ngo depth 1\n",
+            fen
+        ));
+        assert!(
+            output.starts_with("info string fen 1r2k1rr/8/8/8/8/8/8/1R2KR1R w Kk - 0 1\n"),
+            "{}",
+            output
+        );
+        let (_, warnings) = run_with_warnings(&format!("position fen {} moves e1b1\n", fen));
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        let output = run(&format!(
+            "setoption name UCI_Chess960 value true\ndebug on\nposition fen {} moves\ngo depth 1\n",
+            "1r2k1rr/8/8/8/8/8/8/1R2KR1R w KQkq - 0 1"
+        ));
+        assert!(
+            output.starts_with(&format!("info string fen {}\n", fen)),
+            "{}",
+            output
+        );
+
+        let output = run("setoption name UCI_Chess960 value true\nuci\n");
+        assert!(output.contains("option name UCI_Chess960 type check default true\n"));
+    }
+
+    #[test]
+    fn test_eval() {
+        let output = run("position startpos moves e2e4\neval\n");
+        let lines: Vec<_> = output.lines().collect();
+        assert!(lines.len() >= 2, "{}", output);
+        assert!(lines.iter().all(|ln| ln.starts_with("info string eval ")));
+        assert!(
+            lines
+                .iter()
+                .any(|ln| ln.starts_with("info string eval king safety ")),
+            "{}",
+            output
+        );
+        assert!(lines.last().unwrap().ends_with(" (white side)"));
+
+        let (output, warnings) = run_with_warnings("eval\n");
+        assert_eq!(output, "");
+        assert_eq!(warnings, vec!["no position set"]);
+    }
+
+    #[test]
+    fn test_san_pv() {
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let b = Board::from_str(fen).unwrap();
+        let input = format!("position fen {} moves\ngo depth 2\n", fen);
+        let output = run(&input);
+        let info = output.lines().find(|ln| ln.contains(" pv ")).unwrap();
+        let pv = info.split_once(" pv ").unwrap().1;
+        assert!(Move::from_uci_legal(pv.split(' ').next().unwrap(), &b).is_ok());
+
+        let output = run(&format!("setoption name ShowSanPv value true\n{}", input));
+        let infos: Vec<_> = output.lines().filter(|ln| ln.contains(" pv ")).collect();
+        assert!(!infos.is_empty(), "{}", output);
+        for info in infos {
+            let pv = info.split_once(" pv ").unwrap().1;
+            let mut b = b.clone();
+            for mv in pv.split(' ').take_while(|s| !s.is_empty() && *s != "score") {
+                let mv = Move::from_san(mv, &b).unwrap();
+                b.make_move(mv).unwrap();
+            }
+            assert!(pv.starts_with('K') || pv.starts_with('R'), "{}", info);
+        }
+        assert!(Move::from_uci_legal(best_move(&output), &b).is_ok());
+
+        // If the PV doesn't match the board, the moves are printed in UCI.
+        let mv = |s| Move::from_uci_legal(s, &Board::start()).unwrap();
+        let info = SearchInfo {
+            depth: 1,
+            seldepth: 1,
+            pv: vec![mv("e2e4"), mv("d2d4")],
+            score: BoundedScore::default(),
+            nodes: None,
+            hashfull: None,
+        };
+        let mut output = Vec::new();
+        let msg = Message::Info(Info::Info {
+            time: Duration::ZERO,
+            info: &info,
+            san_board: Some(&b),
+        });
+        io::write_msg(&msg, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(" pv e2e4 d2d4 score "), "{}", output);
+    }
+
+    #[test]
+    fn test_reset() {
+        let output = run("position startpos moves e2e4\nsetoption name Reset\ngo depth 1\n");
+        assert!(output.starts_with("info string Reset done\n"), "{}", output);
+        assert!(Move::from_uci_legal(best_move(&output), &Board::start()).is_ok());
+    }
+
+    fn perft_output(board: Board, depth: usize, stop_state: &StopState) -> String {
+        let mut output = Vec::new();
+        let out: &mut (dyn Write + Send + Sync) = &mut output;
+        run_perft(board, depth, stop_state, &Mutex::new(out)).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_perft() {
+        let mut b = Board::start();
+        b.make_uci_move("e2e4").unwrap();
+        let output = perft_output(b.clone(), 2, &StopState::new(false));
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 22, "{}", output);
+        let mut total = 0;
+        for ln in &lines[..20] {
+            let (mv, count) = ln
+                .strip_prefix("info string ")
+                .unwrap()
+                .split_once(": ")
+                .unwrap();
+            assert!(Move::from_uci_legal(mv, &b).is_ok());
+            total += count.parse::<u64>().unwrap();
+        }
+        assert_eq!(total, 600);
+        assert_eq!(lines[0], "info string a7a5: 30");
+        assert_eq!(
+            lines[20..],
+            ["info string Nodes searched: 600", "bestmove 0000"]
+        );
+
+        // A stopped perft reports only the first root move.
+        let stop_state = StopState::new(false);
+        stop_state.stop();
+        let output = perft_output(Board::start(), 4, &stop_state);
+        assert_eq!(
+            output,
+            "info string a2a3: 8457\n\
+             info string Nodes searched: 8457\n\
+             bestmove 0000\n"
+        );
+
+        // Both spellings run the same command. The end of input may stop the perft early, so only
+        // the first and the last lines are checked.
+        for input in [
+            "position startpos moves\nperft 2\n",
+            "position startpos moves\ngo perft 2\n",
+        ] {
+            let output = run(input);
+            let lines: Vec<_> = output.lines().collect();
+            assert_eq!(lines[0], "info string a2a3: 20", "{}", output);
+            assert_eq!(lines.last(), Some(&"bestmove 0000"), "{}", output);
+        }
+
+        let (_, warnings) = run_with_warnings("perft 0\n");
+        assert_eq!(warnings, vec!["perft depth must be positive"]);
+        let (_, warnings) = run_with_warnings("go perft 0\n");
+        assert_eq!(warnings, vec!["perft depth must be positive"]);
+    }
+
+    #[test]
+    fn test_bench() {
+        let mut engine = TestEngine {
+            opts: HashMap::new(),
+            reported: Move::NULL,
+            best: Move::NULL,
+            panics: false,
+        };
+        let (output, warnings) = run_engine("bench 3\n", &mut engine);
+        assert_eq!(warnings, Vec::<String>::new());
+        let (nodes, nps) = output
+            .trim_end()
+            .strip_suffix(" nps")
+            .and_then(|s| s.split_once(" nodes "))
+            .unwrap();
+        assert_eq!(nodes, "24680");
+        assert!(nps.parse::<u64>().is_ok(), "{}", output);
+
+        let (output, warnings) = run_with_warnings("bench 1\ngo depth 1\n");
+        assert!(
+            output.lines().next().unwrap().ends_with(" nps"),
+            "{}",
+            output
+        );
+        assert_eq!(
+            warnings,
+            vec!["no position set, assuming the start position"]
+        );
+        assert!(Move::from_uci_legal(best_move(&output), &Board::start()).is_ok());
+    }
+
+    #[test]
+    fn test_final_info() {
+        let b = Board::start();
+        let e2e4 = Move::from_uci_legal("e2e4", &b).unwrap();
+        let d2d4 = Move::from_uci_legal("d2d4", &b).unwrap();
+        for reported in [e2e4, d2d4] {
+            let mut engine = TestEngine {
+                opts: HashMap::new(),
+                reported,
+                best: e2e4,
+                panics: false,
+            };
+            let (output, _) = run_engine("position startpos moves\ngo depth 3\n", &mut engine);
+            let lines: Vec<_> = output.lines().collect();
+            let pos = lines
+                .iter()
+                .position(|ln| ln.starts_with("bestmove "))
+                .unwrap();
+            assert_eq!(lines[pos], "bestmove e2e4");
+            let info = lines[pos - 1];
+            assert!(info.starts_with("info depth 3 seldepth 3 "), "{}", info);
+            assert!(info.contains(" nodes 1234"), "{}", info);
+            assert!(info.ends_with(" pv e2e4 score cp 0"), "{}", info);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        struct Broken;
+
+        impl std::io::Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
+            }
+        }
+
+        impl Write for Broken {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut engine = crate::engine::Engine::new();
+        let mut warn = TestWarn::default();
+        let res = comm(
+            &mut std::io::BufReader::new(Broken),
+            &mut Vec::new(),
+            &mut warn,
+            &mut engine,
+        );
+        assert!(matches!(res, Err(UciError::Io(_))), "{:?}", res);
+
+        let res = comm(&mut &b"uci\n"[..], &mut Broken, &mut warn, &mut engine);
+        assert!(matches!(res, Err(UciError::Writer(_))), "{:?}", res);
+
+        let res = comm(
+            &mut &b"isready\n\xff\n"[..],
+            &mut Vec::new(),
+            &mut warn,
+            &mut engine,
+        );
+        assert!(matches!(res, Err(UciError::Parse(_))), "{:?}", res);
+
+        let mut engine = TestEngine {
+            opts: HashMap::new(),
+            reported: Move::NULL,
+            best: Move::NULL,
+            panics: true,
+        };
+        let res = comm(
+            &mut &b"go depth 1\nisready\n"[..],
+            &mut Vec::new(),
+            &mut warn,
+            &mut engine,
+        );
+        match res {
+            Err(UciError::EnginePanic(msg)) => assert_eq!(msg, "search failed"),
+            _ => panic!("unexpected result {:?}", res),
+        }
+    }
+}
@@ -3,13 +3,13 @@ use crate::intf::{
     opts::{Name, NameBuf, Opt},
     score::Bound,
 };
-use crate::uci::{Warn, sanitize};
+use crate::uci::{UciError, Warn, bench, sanitize};
 use anyhow::{Context, Result, anyhow};
-use pawnyowl_board::{Board, Move};
+use pawnyowl_board::{Board, Move, RawBoard};
 use std::{
     borrow::Cow,
     error::Error,
-    io::{BufRead, Write},
+    io::{BufRead, ErrorKind, Write},
     num::NonZeroU32,
     str::FromStr,
     time::Duration,
@@ -26,10 +26,21 @@ pub enum Command {
     Uci,
     Debug(bool),
     IsReady,
-    SetOption { name: NameBuf, value: String },
+    SetOption {
+        name: NameBuf,
+        value: String,
+    },
     NewGame,
     Position(Box<Position>),
-    Go(SearchConstraint),
+    Go {
+        constraint: SearchConstraint,
+        search_moves: Option<Vec<String>>,
+        ponder: bool,
+    },
+    PonderHit,
+    Perft(usize),
+    Bench(usize),
+    Eval,
     Stop,
     Quit,
 }
@@ -41,6 +52,8 @@ pub enum Info<'a> {
     Info {
         time: Duration,
         info: &'a SearchInfo,
+        /// Root position of the search, if the PV must be printed in SAN.
+        san_board: Option<&'a Board>,
     },
     Nodes {
         time: Duration,
@@ -60,6 +73,7 @@ pub enum Message<'a> {
     ReadyOk,
     Info(Info<'a>),
     BestMove(SearchResult),
+    Bench { nodes: u64, time: Duration },
 }
 
 fn sanitize_str(s: &str) -> Cow<'_, str> {
@@ -82,7 +96,17 @@ fn calc_nps(nodes: u64, time: &Duration) -> Option<u64> {
     nps.try_into().ok()
 }
 
-pub fn write_msg(msg: &Message, w: &mut (impl Write + ?Sized)) -> Result<()> {
+pub fn write_msg(msg: &Message, w: &mut (impl Write + ?Sized)) -> Result<(), UciError> {
+    if let Message::Option { name, value } = msg {
+        sanitize::opt_name(name)
+            .context("sanitizing option name")
+            .and_then(|_| sanitize::opt(value).context("sanitizing option value"))
+            .map_err(|e| UciError::BadOption(format!("{:#}", e)))?;
+    }
+    do_write_msg(msg, w).map_err(UciError::Writer)
+}
+
+fn do_write_msg(msg: &Message, w: &mut (impl Write + ?Sized)) -> std::io::Result<()> {
     match msg {
         Message::UciOk => writeln!(w, "uciok")?,
         Message::Id(meta) => {
@@ -90,8 +114,6 @@ pub fn write_msg(msg: &Message, w: &mut (impl Write + ?Sized)) -> Result<()> {
             writeln!(w, "id author {}", sanitize_str(&meta.author))?;
         }
         Message::Option { name, value } => {
-            sanitize::opt_name(name).context("sanitizing option name")?;
-            sanitize::opt(value).context("sanitizing option value")?;
             let mut s = format!("option name {}", name);
             match value {
                 Opt::Bool { val } => s += &format!(" type check default {}", val),
@@ -125,16 +147,31 @@ pub fn write_msg(msg: &Message, w: &mut (impl Write + ?Sized)) -> Result<()> {
         Message::ReadyOk => writeln!(w, "readyok")?,
         Message::Info(info) => match info {
             Info::String(s) => writeln!(w, "info string {}", sanitize_str(s))?,
-            Info::Info { time, info } => {
-                let mut s = format!("info depth {} time {}", info.depth, time.as_millis());
+            Info::Info {
+                time,
+                info,
+                san_board,
+            } => {
+                let mut s = format!(
+                    "info depth {} seldepth {} time {}",
+                    info.depth,
+                    info.seldepth,
+                    time.as_millis()
+                );
                 if let Some(nodes) = info.nodes {
                     s += &format!(" nodes {}", nodes);
                     if let Some(nps) = calc_nps(nodes, time) {
                         s += &format!(" nps {}", nps);
                     }
                 }
+                if let Some(hashfull) = info.hashfull {
+                    s += &format!(" hashfull {}", hashfull);
+                }
                 if !info.pv.is_empty() {
-                    let pv = info.pv.iter().map(ToString::to_string).collect::<Vec<_>>();
+                    let pv = match san_board {
+                        Some(b) => pv_to_san(b, &info.pv),
+                        None => info.pv.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    };
                     s += &format!(" pv {}", pv.join(" "));
                 }
                 match info.score.score {
@@ -169,15 +206,35 @@ pub fn write_msg(msg: &Message, w: &mut (impl Write + ?Sized)) -> Result<()> {
                 writeln!(w, "bestmove {} ponder {}", res.best, res.ponder)?;
             }
         }
+        Message::Bench { nodes, time } => {
+            let nps = (*nodes as u128) * 1000 / time.as_millis().max(1);
+            writeln!(w, "{} nodes {} nps", nodes, nps)?;
+        }
     }
     Ok(())
 }
 
+fn pv_to_san(b: &Board, pv: &[Move]) -> Vec<String> {
+    let mut b = b.clone();
+    let mut res = Vec::new();
+    for (i, &mv) in pv.iter().enumerate() {
+        if mv.validate(&b).is_err() {
+            // The PV does not follow from the board, so print the rest of it as is.
+            res.extend(pv[i..].iter().map(ToString::to_string));
+            break;
+        }
+        res.push(mv.to_san(&b));
+        unsafe { b.make_move_unchecked(mv) };
+    }
+    res
+}
+
 fn parse_position<'a>(
     mut tokens: impl Iterator<Item = &'a str>,
+    chess960: bool,
     warn: &mut dyn Warn,
 ) -> Option<Box<Position>> {
-    let board = match tokens.next() {
+    let mut board = match tokens.next() {
         Some("startpos") => {
             loop {
                 match tokens.next() {
@@ -203,7 +260,7 @@ fn parse_position<'a>(
                     Some(token) => fen_tokens.push(token),
                 }
             }
-            match Board::from_str(&fen_tokens.join(" ")) {
+            match Board::from_fen(&fen_tokens.join(" "), chess960) {
                 Ok(b) => b,
                 Err(e) => {
                     warn.warn(&format!("bad fen: {}", e));
@@ -221,6 +278,17 @@ fn parse_position<'a>(
         }
     };
 
+    // The option alone decides the castling rules, even if the FEN gives the rook files. Castling
+    // moves are parsed as the king capturing its own rook in Chess960 mode.
+    if board.raw().chess960 != chess960 {
+        board = RawBoard {
+            chess960,
+            ..*board.raw()
+        }
+        .try_into()
+        .expect("changing the castling rules must keep the position valid");
+    }
+
     let mut tmp_board = board.clone();
     let mut moves = Vec::new();
     for (i, token) in tokens.enumerate() {
@@ -252,10 +320,7 @@ fn parse_msec(token: Option<&str>) -> Result<Duration> {
     Ok(Duration::from_millis(parse_int(token)?))
 }
 
-fn parse_go<'a>(
-    mut tokens: impl Iterator<Item = &'a str>,
-    warn: &mut dyn Warn,
-) -> Option<SearchConstraint> {
+fn parse_go<'a>(mut tokens: impl Iterator<Item = &'a str>, warn: &mut dyn Warn) -> Option<Command> {
     const SUBCOMMANDS: &[&str] = &[
         "searchmoves",
         "ponder",
@@ -269,17 +334,24 @@ fn parse_go<'a>(
         "mate",
         "movetime",
         "infinite",
+        "perft",
     ];
 
     // We don't try to support some weird combination of parameters here. Instead, we follow the
     // simple logic described below.
     //
-    // First, try to search for "depth", "movetime" or "infinite" options and use first of them
-    // found. Otherwise, assume that we use a time control and look up for the corresponding
-    // options. If they are also not found, assume infinite search.
+    // First, try to search for "depth", "nodes", "mate", "movetime" or "infinite" options and use
+    // first of them found. Otherwise, assume that we use a time control and look up for the
+    // corresponding options. If they are also not found, assume infinite search.
     //
     // Such behavior might cause bugs in GUIs in some weird cases. If that happens, feel free to
     // adjust the logic or submit an issue.
+    //
+    // "perft" is not a part of UCI, but is widely supported. If it is present, no search is
+    // started, and all the other options are ignored.
+    let mut perft = None;
+    let mut search_moves = None;
+    let mut ponder = false;
     let mut time_control = None;
     let mut constraint = None;
     let default_time_control = || {
@@ -301,6 +373,7 @@ fn parse_go<'a>(
     loop {
         let mut token = tokens.next();
         if token == Some("searchmoves") {
+            let moves: &mut Vec<String> = search_moves.get_or_insert_default();
             loop {
                 token = tokens.next();
                 match token {
@@ -308,6 +381,7 @@ fn parse_go<'a>(
                         if SUBCOMMANDS.contains(&token) {
                             break;
                         }
+                        moves.push(token.into());
                     }
                     None => break,
                 }
@@ -316,9 +390,7 @@ fn parse_go<'a>(
         }
         match token {
             Some("searchmoves") => warn.warn("two \"searchmoves\" in a row"),
-            Some("ponder") => {
-                // Not supported.
-            }
+            Some("ponder") => ponder = true,
             Some("wtime") => match parse_msec(tokens.next()) {
                 Ok(t) => force_time_control!().white.time = t,
                 Err(e) => warn.warn(&format!("bad \"wtime\": {}", e)),
@@ -346,14 +418,21 @@ fn parse_go<'a>(
                 },
                 Err(e) => warn.warn(&format!("bad \"depth\": {}", e)),
             },
-            Some("nodes") => {
-                // Not supported.
-                _ = tokens.next();
-            }
-            Some("mate") => {
-                // Not supported.
-                _ = tokens.next();
-            }
+            Some("nodes") => match parse_int(tokens.next()) {
+                Ok(v) => match &constraint {
+                    None => constraint = Some(SearchConstraint::FixedNodes(v)),
+                    Some(_) => warn.warn("\"nodes\" ignored"),
+                },
+                Err(e) => warn.warn(&format!("bad \"nodes\": {}", e)),
+            },
+            Some("mate") => match parse_int(tokens.next()) {
+                Ok(0) => warn.warn("bad \"mate\": must be positive"),
+                Ok(v) => match &constraint {
+                    None => constraint = Some(SearchConstraint::Mate(v)),
+                    Some(_) => warn.warn("\"mate\" ignored"),
+                },
+                Err(e) => warn.warn(&format!("bad \"mate\": {}", e)),
+            },
             Some("movetime") => match parse_msec(tokens.next()) {
                 Ok(t) => match &constraint {
                     None => constraint = Some(SearchConstraint::FixedTime(t)),
@@ -365,26 +444,50 @@ fn parse_go<'a>(
                 None => constraint = Some(SearchConstraint::Infinite),
                 Some(_) => warn.warn("\"infinite\" ignored"),
             },
+            Some("perft") => match parse_int(tokens.next()) {
+                Ok(v) => perft = Some(v),
+                Err(e) => warn.warn(&format!("bad \"perft\": {}", e)),
+            },
             Some(tok) => warn.warn(&format!("bad token: {:?}", tok)),
             None => break,
         }
     }
 
-    if let Some(constraint) = constraint {
-        Some(constraint)
+    if let Some(depth) = perft {
+        return Some(Command::Perft(depth));
+    }
+    let constraint = if let Some(constraint) = constraint {
+        constraint
     } else if let Some(time_control) = time_control {
-        Some(SearchConstraint::TimeControl(time_control))
+        SearchConstraint::TimeControl(time_control)
     } else {
         warn.warn("no options for \"go\", starting infinite search");
-        Some(SearchConstraint::Infinite)
+        SearchConstraint::Infinite
+    };
+    if search_moves.as_ref().is_some_and(Vec::is_empty) {
+        warn.warn("empty \"searchmoves\", searching all moves");
+        search_moves = None;
     }
+    Some(Command::Go {
+        constraint,
+        search_moves,
+        ponder,
+    })
 }
 
-pub fn read_cmd(r: &mut (impl BufRead + ?Sized), warn: &mut dyn Warn) -> Result<Option<Command>> {
+/// Reads the next command. `chess960` selects the castling rules used to parse the positions.
+pub fn read_cmd(
+    r: &mut (impl BufRead + ?Sized),
+    chess960: bool,
+    warn: &mut dyn Warn,
+) -> Result<Option<Command>, UciError> {
     let mut ln = String::new();
     loop {
         ln.clear();
-        let bytes = r.read_line(&mut ln)?;
+        let bytes = r.read_line(&mut ln).map_err(|e| match e.kind() {
+            ErrorKind::InvalidData => UciError::Parse("input is not valid UTF-8".into()),
+            _ => UciError::Io(e),
+        })?;
         if bytes == 0 {
             return Ok(None);
         }
@@ -444,14 +547,55 @@ pub fn read_cmd(r: &mut (impl BufRead + ?Sized), warn: &mut dyn Warn) -> Result<
                     }
                     return Ok(Some(Command::NewGame));
                 }
-                "position" => match parse_position(tokens, warn) {
+                "position" => match parse_position(tokens, chess960, warn) {
                     Some(p) => return Ok(Some(Command::Position(p))),
                     None => break,
                 },
                 "go" => match parse_go(tokens, warn) {
-                    Some(c) => return Ok(Some(Command::Go(c))),
+                    Some(c) => return Ok(Some(c)),
                     None => break,
                 },
+                "bench" => {
+                    let depth = match tokens.next() {
+                        Some(token) => match parse_int(Some(token)) {
+                            Ok(depth) => depth,
+                            Err(e) => {
+                                warn.warn(&format!("bad \"bench\" depth: {}", e));
+                                break;
+                            }
+                        },
+                        None => bench::DEFAULT_DEPTH,
+                    };
+                    if tokens.next().is_some() {
+                        warn.warn("extra data in \"bench\"");
+                    }
+                    return Ok(Some(Command::Bench(depth)));
+                }
+                "perft" => {
+                    let depth = match parse_int(tokens.next()) {
+                        Ok(depth) => depth,
+                        Err(e) => {
+                            warn.warn(&format!("bad \"perft\" depth: {}", e));
+                            break;
+                        }
+                    };
+                    if tokens.next().is_some() {
+                        warn.warn("extra data in \"perft\"");
+                    }
+                    return Ok(Some(Command::Perft(depth)));
+                }
+                "eval" => {
+                    if tokens.next().is_some() {
+                        warn.warn("extra data in \"eval\"");
+                    }
+                    return Ok(Some(Command::Eval));
+                }
+                "ponderhit" => {
+                    if tokens.next().is_some() {
+                        warn.warn("extra data in \"ponderhit\"");
+                    }
+                    return Ok(Some(Command::PonderHit));
+                }
                 "stop" => {
                     if tokens.next().is_some() {
                         warn.warn("extra data in \"stop\"");
@@ -1,10 +1,27 @@
+mod bench;
 mod comm;
 mod io;
 mod sanitize;
 mod util;
 
+use thiserror::Error;
+
 pub trait Warn {
     fn warn(&mut self, msg: &str);
 }
 
+#[derive(Debug, Error)]
+pub enum UciError {
+    #[error("reading command: {0}")]
+    Io(#[source] std::io::Error),
+    #[error("parsing command: {0}")]
+    Parse(String),
+    #[error("bad option: {0}")]
+    BadOption(String),
+    #[error("engine panicked: {0}")]
+    EnginePanic(String),
+    #[error("writing message: {0}")]
+    Writer(#[source] std::io::Error),
+}
+
 pub use comm::comm;
@@ -2,12 +2,12 @@ use crate::intf::{
     Engine, StopCallback,
     opts::{Name, NameBuf, Val},
 };
-use crate::uci::io::Position;
+use crate::uci::{Warn, io::Position};
 use std::{
     collections::HashMap,
     mem,
     sync::{
-        Mutex,
+        Condvar, Mutex,
         atomic::{AtomicBool, Ordering},
     },
 };
@@ -41,12 +41,27 @@ impl DelayedState {
         self.opts.insert(name.to_owned(), val);
     }
 
-    pub fn apply(&mut self, engine: &mut (impl Engine + ?Sized)) {
+    // Returns feedback messages for the actions that were performed.
+    pub fn apply(
+        &mut self,
+        engine: &mut (impl Engine + ?Sized),
+        warn: &mut dyn Warn,
+    ) -> Vec<String> {
+        let mut feedback = Vec::new();
         if let Some(debug) = self.debug.take() {
             engine.set_debug(debug);
         }
         for (name, val) in self.opts.drain() {
-            engine.set_opt(name.as_name(), val);
+            let is_action = matches!(val, Val::Action);
+            match engine.set_opt(name.as_name(), val) {
+                Ok(()) if is_action => feedback.push(format!("{} done", name.as_str())),
+                Ok(()) => {}
+                Err(err) => warn.warn(&format!(
+                    "cannot set option \"{}\": {:#}",
+                    name.as_str(),
+                    err
+                )),
+            }
         }
         if mem::replace(&mut self.new_game, false) {
             engine.on_new_game();
@@ -54,22 +69,53 @@ impl DelayedState {
         if let Some(position) = self.position.take() {
             engine.set_position(&position.board, &position.moves[..]);
         }
+        feedback
     }
 }
 
 pub struct StopState {
     is_stopped: AtomicBool,
+    is_pondering: AtomicBool,
     on_stop: Mutex<Option<Vec<StopCallback>>>,
+    /// Signaled when the ponder hit or the stop arrives.
+    ponder_lock: Mutex<()>,
+    ponder_cond: Condvar,
 }
 
 impl StopState {
-    pub fn new() -> Self {
+    pub fn new(ponder: bool) -> Self {
         Self {
             is_stopped: AtomicBool::new(false),
+            is_pondering: AtomicBool::new(ponder),
             on_stop: Mutex::new(Some(Vec::new())),
+            ponder_lock: Mutex::new(()),
+            ponder_cond: Condvar::new(),
+        }
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.is_pondering.load(Ordering::Acquire)
+    }
+
+    pub fn ponder_hit(&self) {
+        self.is_pondering.store(false, Ordering::Release);
+        self.wake_ponder();
+    }
+
+    /// Blocks until the ponder hit or the stop arrives.
+    pub fn wait_ponder(&self) {
+        let mut guard = self.ponder_lock.lock().unwrap();
+        while self.is_pondering() && !self.is_stopped() {
+            guard = self.ponder_cond.wait(guard).unwrap();
         }
     }
 
+    fn wake_ponder(&self) {
+        // Take the lock so the waiter cannot miss the wakeup between its check and its wait.
+        let _guard = self.ponder_lock.lock().unwrap();
+        self.ponder_cond.notify_all();
+    }
+
     pub fn is_stopped(&self) -> bool {
         self.is_stopped.load(Ordering::Acquire)
     }
@@ -78,6 +124,7 @@ impl StopState {
         if self.is_stopped.swap(true, Ordering::AcqRel) {
             return;
         }
+        self.wake_ponder();
         let mut on_stop = self.on_stop.lock().unwrap();
         for cb in on_stop.take().unwrap() {
             cb();
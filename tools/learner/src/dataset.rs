@@ -1,5 +1,11 @@
+use anyhow::{Context, Result};
 use burn::{data::dataloader::batcher::Batcher, prelude::*};
+use pawnyowl::eval::layers::feature::PsqFeatureLayer;
+use pawnyowl::pgn;
+use pawnyowl_board::{Board, Color};
+use std::fs;
 
+#[derive(Clone, Copy, Debug)]
 pub enum GameResult {
     WhiteWins,
     Draw,
@@ -16,6 +22,15 @@ impl GameResult {
     }
 }
 
+/// Positions taken from each PGN game.
+#[derive(Clone, Copy, Debug)]
+pub struct PgnSampling {
+    /// Take every `stride`-th quiet position.
+    pub stride: usize,
+    /// Skip the opening plies, as the positions there are the same in many games.
+    pub skip_plies: usize,
+}
+
 #[derive(Clone)]
 pub struct BoardBatcher<B: Backend> {
     device: B::Device,
@@ -41,6 +56,78 @@ pub struct BoardItem {
     pub target: f64,
 }
 
+impl BoardItem {
+    pub fn new(board: &Board, result: GameResult) -> Self {
+        let mut features = [0_i8; 64 * 6];
+        let mut stage = 0;
+        for (sq, cell) in board.iter_pieces() {
+            if cell.color() == Some(Color::White) {
+                features[cell.piece().unwrap().index() * 64 + sq.index()] += 1;
+            } else {
+                features[cell.piece().unwrap().index() * 64 + sq.flipped_rank().index()] -= 1;
+            }
+            stage += PsqFeatureLayer::STAGE_WEIGHTS[cell.index()];
+        }
+        Self {
+            features,
+            stage,
+            target: result.target(),
+        }
+    }
+}
+
+/// Replays the games from a PGN file and labels their quiet positions with the game result.
+///
+/// A position is quiet if the side to move is not in check and the move played from it is not a
+/// capture. Games without a result or with bad moves are skipped.
+pub fn read_pgn(filename: &str, sampling: PgnSampling) -> Result<Vec<BoardItem>> {
+    let text = fs::read_to_string(filename).with_context(|| format!("reading {}", filename))?;
+    let mut items = Vec::new();
+    for (i, game) in pgn::split_games(&text).into_iter().enumerate() {
+        let game = match pgn::parse_game(game) {
+            Ok(game) => game,
+            Err(e) => {
+                eprintln!("Skipping game {}: {:#}", i + 1, e);
+                continue;
+            }
+        };
+        let result = match game.result {
+            pgn::GameResult::WhiteWins => GameResult::WhiteWins,
+            pgn::GameResult::Draw => GameResult::Draw,
+            pgn::GameResult::BlackWins => GameResult::BlackWins,
+            pgn::GameResult::Unknown => continue,
+        };
+        match sample_game(&game, result, sampling) {
+            Ok(mut game_items) => items.append(&mut game_items),
+            Err(e) => eprintln!("Skipping game {}: {:#}", i + 1, e),
+        }
+    }
+    Ok(items)
+}
+
+/// Replays one game and takes its quiet positions. Fails if the game contains a bad move.
+fn sample_game(
+    game: &pgn::PgnGame,
+    result: GameResult,
+    sampling: PgnSampling,
+) -> Result<Vec<BoardItem>> {
+    let mut items = Vec::new();
+    let mut board = game.start.clone();
+    let mut quiet = 0;
+    for (ply, &mv) in game.moves.iter().enumerate() {
+        if ply >= sampling.skip_plies && !board.is_check() && !mv.is_capture(&board) {
+            if quiet % sampling.stride.max(1) == 0 {
+                items.push(BoardItem::new(&board, result));
+            }
+            quiet += 1;
+        }
+        board
+            .make_move(mv)
+            .with_context(|| format!("bad move {} at ply {}", mv, ply + 1))?;
+    }
+    Ok(items)
+}
+
 impl<B: Backend> Batcher<BoardItem, BoardBatch<B>> for BoardBatcher<B> {
     fn batch(&self, items: Vec<BoardItem>) -> BoardBatch<B> {
         let parse_items = |item: &BoardItem| {
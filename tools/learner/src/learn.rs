@@ -1,13 +1,16 @@
-use crate::dataset::{BoardBatch, BoardBatcher, BoardItem, GameResult};
+use crate::dataset::{self, BoardBatch, BoardBatcher, BoardItem, GameResult, PgnSampling};
 use anyhow::{Result, bail};
 use burn::backend::Autodiff;
 use burn::backend::ndarray::NdArray;
+#[cfg(feature = "wgpu")]
+use burn::backend::{Wgpu, wgpu::WgpuDevice};
 use burn::data::dataloader::DataLoaderBuilder;
 use burn::data::dataset::{Dataset, DatasetIterator};
+use burn::module::Param;
 use burn::nn::Sigmoid;
 use burn::nn::loss::MseLoss;
 use burn::optim::AdamConfig;
-use burn::tensor::Float;
+use burn::tensor::{Float, TensorData};
 use burn::train::metric::LossMetric;
 use burn::train::{RegressionOutput, TrainOutput, TrainStep, ValidStep};
 use burn::{
@@ -21,12 +24,19 @@ use burn::{
     train::LearnerBuilder,
 };
 use burn_ndarray::NdArrayDevice;
-use pawnyowl::eval::layers::feature::{PsqFeatureLayer, ScorePair};
+use clap::ValueEnum;
+use pawnyowl::eval::layers::{
+    feature::{PsqFeatureLayer, ScorePair},
+    material::MaterialLayer,
+    mobility::MobilityLayer,
+    pawns::PawnLayer,
+};
 use pawnyowl::eval::{model::PsqModel, score::Score};
-use pawnyowl_board::{Board, Cell, Color, Sq};
+use pawnyowl_board::{Board, Cell, Color, Piece, Sq};
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use std::fs;
 use std::io::BufReader;
 use std::str::FromStr;
 use std::{fs::File, io::BufRead};
@@ -80,6 +90,36 @@ struct TrainingConfig {
     pub learning_rate: f64,
 }
 
+/// Values that replace the defaults of `TrainingConfig`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TrainingOverrides {
+    pub num_epochs: Option<usize>,
+    pub batch_size: Option<usize>,
+    pub learning_rate: Option<f64>,
+    pub train_ratio: Option<f64>,
+    pub seed: Option<u64>,
+}
+
+impl TrainingOverrides {
+    fn apply(self, config: &mut TrainingConfig) {
+        if let Some(num_epochs) = self.num_epochs {
+            config.num_epochs = num_epochs;
+        }
+        if let Some(batch_size) = self.batch_size {
+            config.batch_size = batch_size;
+        }
+        if let Some(learning_rate) = self.learning_rate {
+            config.learning_rate = learning_rate;
+        }
+        if let Some(train_ratio) = self.train_ratio {
+            config.train_ratio = train_ratio;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+    }
+}
+
 #[derive(Module, Debug)]
 struct Model<B: Backend> {
     linear: Linear<B>,
@@ -160,35 +200,25 @@ fn parse_result(s: &str) -> Result<GameResult> {
     }
 }
 
-fn read_lines(filename: &str, seed: u64) -> Result<Vec<BoardItem>> {
+fn read_lines(filename: &str) -> Result<Vec<BoardItem>> {
     let file = File::open(filename)?;
     let reader = BufReader::new(file);
     let fens: Vec<String> = reader.lines().skip(1).collect::<Result<_, _>>()?;
     let parse_fens = |line: &String| -> Result<_> {
         let (fen, result) = split_last_comma(line);
         let board = Board::from_str(fen)?;
+        Ok(BoardItem::new(&board, parse_result(result)?))
+    };
+    fens.iter().map(parse_fens).collect()
+}
 
-        let mut features = [0_i8; 64 * 6];
-        let mut stage = 0;
-        for sq in Sq::iter() {
-            let cell = board.get(sq);
-            if let Some(c) = cell.color() {
-                if c == Color::White {
-                    features[cell.piece().unwrap().index() * 64 + sq.index()] += 1;
-                } else {
-                    features[cell.piece().unwrap().index() * 64 + sq.flipped_rank().index()] -= 1;
-                }
-                stage += PsqFeatureLayer::STAGE_WEIGHTS[cell.index()];
-            }
-        }
-        let target = parse_result(result)?.target();
-        Ok(BoardItem {
-            features,
-            stage,
-            target,
-        })
+/// Reads the `fen,result` CSV, or the PGN games if the file name ends with `.pgn`.
+fn read_dataset(filename: &str, sampling: PgnSampling, seed: u64) -> Result<Vec<BoardItem>> {
+    let mut items = if filename.ends_with(".pgn") {
+        dataset::read_pgn(filename, sampling)?
+    } else {
+        read_lines(filename)?
     };
-    let mut items = fens.iter().map(parse_fens).collect::<Result<Vec<_>>>()?;
     let mut rng = StdRng::seed_from_u64(seed);
     items.shuffle(&mut rng);
     Ok(items)
@@ -203,10 +233,19 @@ fn split_lines(items: Vec<BoardItem>, ratio: f64) -> (Vec<BoardItem>, Vec<BoardI
     (items, second)
 }
 
-fn train<B: AutodiffBackend>(dataset: &str, artifact: &str, model_path: &str, device: B::Device) {
-    let config = TrainingConfig::new(ModelConfig {}, AdamConfig::new());
-
-    let lines = match read_lines(dataset, config.seed) {
+fn train<B: AutodiffBackend>(
+    dataset: &str,
+    sampling: PgnSampling,
+    overrides: TrainingOverrides,
+    resume: Option<&str>,
+    artifact: &str,
+    model_path: &str,
+    device: B::Device,
+) {
+    let mut config = TrainingConfig::new(ModelConfig {}, AdamConfig::new());
+    overrides.apply(&mut config);
+
+    let lines = match read_dataset(dataset, sampling, config.seed) {
         Ok(lines) => {
             println!("Dataset loaded: {} items", lines.len());
             Ok(lines)
@@ -222,6 +261,16 @@ fn train<B: AutodiffBackend>(dataset: &str, artifact: &str, model_path: &str, de
     let train_dataset = MainDataset::new(items_train);
     let valid_dataset = MainDataset::new(items_valid);
 
+    let mut model = config.model.init::<B>(&device);
+    let base_model = resume.map(|path| {
+        let base_model =
+            load_model(path).unwrap_or_else(|e| panic!("Error loading model {}: {:#}", path, e));
+        let weights = import_layer(base_model.feature_layer(), base_model.weight_scale());
+        set_layer_weights(&mut model.linear, &weights, &device);
+        println!("Resuming from {}", path);
+        base_model
+    });
+
     let batcher_train = BoardBatcher::<B>::new(device.clone());
     let batcher_valid = BoardBatcher::<B::InnerBackend>::new(device.clone());
 
@@ -232,7 +281,8 @@ fn train<B: AutodiffBackend>(dataset: &str, artifact: &str, model_path: &str, de
         .build(train_dataset);
 
     let dataloader_valid = DataLoaderBuilder::new(batcher_valid)
-        .batch_size(valid_dataset.len())
+        // With all the items used for training, the validation set is empty.
+        .batch_size(valid_dataset.len().max(1))
         .shuffle(config.seed)
         .num_workers(config.num_workers)
         .build(valid_dataset);
@@ -243,30 +293,81 @@ fn train<B: AutodiffBackend>(dataset: &str, artifact: &str, model_path: &str, de
         .devices(vec![device.clone()])
         .num_epochs(config.num_epochs)
         .summary()
-        .build(
-            config.model.init::<B>(&device),
-            config.optimizer.init(),
-            config.learning_rate,
-        );
+        .build(model, config.optimizer.init(), config.learning_rate);
 
     let model_trained = learner.fit(dataloader_train, dataloader_valid);
-    let weights = get_layer_weights(&model_trained.linear);
+    let (feature_layer, weight_scale) = export_layer(&get_layer_weights(&model_trained.linear));
+
+    // Only the piece-square tables are trained, so the other layers are carried over from the
+    // resumed model.
+    let mut model = base_model.unwrap_or_else(|| {
+        PsqModel::from_layers(
+            PsqFeatureLayer::new([ScorePair::default(); 64 * Cell::COUNT]),
+            MobilityLayer::default(),
+            PawnLayer::default(),
+            MaterialLayer::default(),
+        )
+    });
+    model.set_feature_layer(feature_layer, weight_scale);
+    model.store(model_path).unwrap();
+}
+
+/// Backend to train the model on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TrainBackend {
+    #[default]
+    Cpu,
+    #[cfg(feature = "wgpu")]
+    Wgpu,
+}
+
+pub fn learn_model(
+    dataset: &str,
+    sampling: PgnSampling,
+    overrides: TrainingOverrides,
+    resume: Option<&str>,
+    backend: TrainBackend,
+    artifact: &str,
+    model_path: &str,
+) {
+    match backend {
+        TrainBackend::Cpu => train::<Autodiff<NdArray<f32>>>(
+            dataset,
+            sampling,
+            overrides,
+            resume,
+            artifact,
+            model_path,
+            NdArrayDevice::Cpu,
+        ),
+        #[cfg(feature = "wgpu")]
+        TrainBackend::Wgpu => train::<Autodiff<Wgpu>>(
+            dataset,
+            sampling,
+            overrides,
+            resume,
+            artifact,
+            model_path,
+            WgpuDevice::default(),
+        ),
+    }
+}
 
+/// Converts the trained weights into the piece-square table, scaled so that a pawn is worth about
+/// 100. The weights of Black pieces are taken from the mirrored squares.
+///
+/// Returns the table and the raw weight of one centipawn, which is the same for both halves of the
+/// score pairs.
+fn export_layer(weights: &[Vec<f32>]) -> (PsqFeatureLayer, f32) {
     let mut o_pawn_weights: Vec<f32> = weights[8..=55].iter().map(|row| row[0]).collect();
     let mut e_pawn_weights: Vec<f32> = weights[8..=55].iter().map(|row| row[1]).collect();
     let o_pawn = median(o_pawn_weights.as_mut_slice());
     let e_pawn = median(e_pawn_weights.as_mut_slice());
+    let scale = (o_pawn + e_pawn) / 2.0 / 100.0;
 
     let weights: Vec<Vec<f32>> = weights
         .iter()
-        .map(|row| {
-            let mut new_row = row.clone();
-            new_row[0] /= (o_pawn + e_pawn) / 2.0;
-            new_row[0] *= 100.0;
-            new_row[1] /= (o_pawn + e_pawn) / 2.0;
-            new_row[1] *= 100.0;
-            new_row
-        })
+        .map(|row| row.iter().map(|w| w / scale).collect())
         .collect();
 
     let mut feature_layer_weights: [ScorePair; 64 * Cell::COUNT] =
@@ -289,16 +390,25 @@ fn train<B: AutodiffBackend>(dataset: &str, artifact: &str, model_path: &str, de
             feature_layer_weights[PsqFeatureLayer::input_index(cell, sq)] = score;
         }
     }
+    (PsqFeatureLayer::new(feature_layer_weights), scale)
+}
 
-    let model = PsqModel::from_layers(PsqFeatureLayer::new(feature_layer_weights));
-    model.store(model_path).unwrap();
+/// Inverse of [`export_layer`], up to the rounding of the table.
+fn import_layer(layer: &PsqFeatureLayer, scale: f32) -> Vec<Vec<f32>> {
+    let mut weights = vec![vec![0.0; 2]; 64 * 6];
+    for p in Piece::iter() {
+        for sq in Sq::iter() {
+            let pair = layer.weight(Cell::make(Color::White, p), sq);
+            weights[p.index() * 64 + sq.index()] = [pair.first(), pair.second()]
+                .map(|score| f32::from(score.value()) * scale)
+                .to_vec();
+        }
+    }
+    weights
 }
 
-pub fn learn_model(dataset: &str, artifact: &str, model_path: &str) {
-    type Backend = NdArray<f32>;
-    type AutodiffBackend = Autodiff<Backend>;
-    let device = NdArrayDevice::Cpu;
-    train::<AutodiffBackend>(dataset, artifact, model_path, device);
+fn load_model(path: &str) -> Result<PsqModel> {
+    PsqModel::load(&fs::read(path)?)
 }
 
 fn median(numbers: &mut [f32]) -> f32 {
@@ -323,3 +433,48 @@ fn get_layer_weights<B: Backend>(linear_layer: &Linear<B>) -> Vec<Vec<f32>> {
 
     weights
 }
+
+fn set_layer_weights<B: Backend>(
+    linear_layer: &mut Linear<B>,
+    weights: &[Vec<f32>],
+    device: &B::Device,
+) {
+    let shape = [weights.len(), weights[0].len()];
+    let data = TensorData::new(weights.concat(), shape);
+    linear_layer.weight = Param::from_tensor(Tensor::from_data(data, device));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_layer_round_trip() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut weights: Vec<Vec<f32>> = (0..64 * 6)
+            .map(|_| vec![rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)])
+            .collect();
+        for row in &mut weights[8..=55] {
+            row[0] += 1.5;
+            row[1] += 2.0;
+        }
+
+        let device = Default::default();
+        let mut linear = LinearConfig::new(64 * 6, 2)
+            .with_bias(false)
+            .init::<NdArray<f32>>(&device);
+        set_layer_weights(&mut linear, &weights, &device);
+        assert_eq!(get_layer_weights(&linear), weights);
+
+        // The raw weights are preserved up to the rounding of the table to whole centipawns.
+        let (layer, scale) = export_layer(&weights);
+        let imported = import_layer(&layer, scale);
+        assert_eq!(imported.len(), weights.len());
+        for (a, b) in imported.iter().zip(&weights) {
+            for (x, y) in a.iter().zip(b) {
+                assert!((x - y).abs() <= scale * 0.501, "{} {}", x, y);
+            }
+        }
+    }
+}
@@ -2,17 +2,81 @@ pub mod dataset;
 pub mod learn;
 
 use clap::Parser;
-use learn::learn_model;
+use dataset::PgnSampling;
+use learn::{TrainBackend, TrainingOverrides, learn_model};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
+    /// `fen,result` CSV file, or PGN games if the name ends with `.pgn`.
     dataset: String,
     artifact: String,
     model: String,
+    /// Take every N-th quiet position of each PGN game.
+    #[arg(long, default_value_t = 1)]
+    stride: usize,
+    /// Skip this many opening plies of each PGN game.
+    #[arg(long, default_value_t = 10)]
+    skip_plies: usize,
+    /// Number of training epochs.
+    #[arg(long, value_parser = parse_positive)]
+    epochs: Option<usize>,
+    #[arg(long, value_parser = parse_positive)]
+    batch_size: Option<usize>,
+    /// Learning rate.
+    #[arg(long)]
+    lr: Option<f64>,
+    /// Share of the dataset used for training, in (0, 1]. The rest is used for validation.
+    #[arg(long, value_parser = parse_train_ratio)]
+    train_ratio: Option<f64>,
+    /// Seed for shuffling the dataset.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Continue training from a stored model. Its layers other than the piece-square tables are
+    /// kept as is.
+    #[arg(long)]
+    resume: Option<String>,
+    /// Backend to train on.
+    #[arg(long, value_enum, default_value_t = TrainBackend::Cpu)]
+    backend: TrainBackend,
+}
+
+fn parse_positive(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("must be positive".into()),
+        Ok(val) => Ok(val),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_train_ratio(s: &str) -> Result<f64, String> {
+    let ratio: f64 = s.parse().map_err(|e| e.to_string())?;
+    if !(ratio > 0.0 && ratio <= 1.0) {
+        return Err(format!("{} is not in (0, 1]", ratio));
+    }
+    Ok(ratio)
 }
 
 fn main() {
     let args = Args::parse();
-    learn_model(&args.dataset, &args.artifact, &args.model);
+    let sampling = PgnSampling {
+        stride: args.stride,
+        skip_plies: args.skip_plies,
+    };
+    let overrides = TrainingOverrides {
+        num_epochs: args.epochs,
+        batch_size: args.batch_size,
+        learning_rate: args.lr,
+        train_ratio: args.train_ratio,
+        seed: args.seed,
+    };
+    learn_model(
+        &args.dataset,
+        sampling,
+        overrides,
+        args.resume.as_deref(),
+        args.backend,
+        &args.artifact,
+        &args.model,
+    );
 }